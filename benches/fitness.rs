@@ -0,0 +1,45 @@
+//! Cost of one fitness evaluation, the optimisers' hot path.
+//!
+//! Run with `cargo bench --bench fitness`.
+
+use criterion::{black_box, criterion_group, criterion_main, Criterion};
+use peptide_opt::peptide::{self, combined_fitness, PeptideProblem};
+
+/// Motifs of 3, 9 and 21 residues; each is scored against itself.
+const SEQUENCES: [(&str, &str); 3] = [
+    ("short", "RGD"),
+    ("medium", "GGAGGVGKS"),
+    ("long", "CIGCINGSMRKSDWKNHKPWH"),
+];
+
+fn motif_index(seq: &str) -> usize {
+    peptide::list_motifs()
+        .into_iter()
+        .find(|(_, m)| m == seq)
+        .map(|(i, _)| i)
+        .expect("benchmark sequence is a built-in motif")
+}
+
+fn bench_combined_fitness(c: &mut Criterion) {
+    let mut group = c.benchmark_group("combined_fitness");
+    for (name, text) in SEQUENCES {
+        peptide::set_motif(motif_index(text)).unwrap();
+        let seq = peptide::parse_sequence(text).unwrap();
+        group.bench_function(name, |b| b.iter(|| combined_fitness(black_box(&seq))));
+    }
+    group.finish();
+}
+
+fn bench_energy_best_motif(c: &mut Criterion) {
+    let mut group = c.benchmark_group("energy_best_motif");
+    for (name, text) in SEQUENCES {
+        let seq = peptide::parse_sequence(text).unwrap();
+        group.bench_function(name, |b| {
+            b.iter(|| PeptideProblem::energy_best_motif(black_box(&seq)))
+        });
+    }
+    group.finish();
+}
+
+criterion_group!(benches, bench_combined_fitness, bench_energy_best_motif);
+criterion_main!(benches);
@@ -0,0 +1,152 @@
+//! Pairwise alignment of a peptide against a motif, scored with BLOSUM62.
+
+use crate::peptide::motif_score;
+use std::ops::Range;
+
+/// Score of the optimal global (Needleman–Wunsch) alignment of `a` and `b`,
+/// with BLOSUM62 substitutions and a linear penalty of `gap` per gap position.
+/// Substitutions are scored by `motif_score`, so `b` may contain wildcards.
+///
+/// Keeps only two rows of the DP table: O(|a|·|b|) time, O(|b|) memory.
+pub fn needleman_wunsch(a: &[u8], b: &[u8], gap: i32) -> i32 {
+    let mut prev: Vec<i32> = (0..=b.len() as i32).map(|j| -gap * j).collect();
+    let mut curr = vec![0; b.len() + 1];
+
+    for (i, &x) in a.iter().enumerate() {
+        curr[0] = -gap * (i as i32 + 1);
+        for (j, &y) in b.iter().enumerate() {
+            let diag = prev[j] + motif_score(x, y);
+            let up = prev[j + 1] - gap;
+            let left = curr[j] - gap;
+            curr[j + 1] = diag.max(up).max(left);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    prev[b.len()]
+}
+
+/// Best local alignment found by `smith_waterman_region`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LocalMatch {
+    pub score: i32,
+    /// Aligned region of the first sequence.
+    pub a: Range<usize>,
+    /// Aligned region of the second sequence.
+    pub b: Range<usize>,
+}
+
+/// Score of the best local (Smith–Waterman) alignment of `a` and `b`, with
+/// BLOSUM62 substitutions and a linear gap penalty; never below 0.
+pub fn smith_waterman(a: &[u8], b: &[u8], gap: i32) -> i32 {
+    let mut prev = vec![0; b.len() + 1];
+    let mut curr = vec![0; b.len() + 1];
+    let mut best = 0;
+
+    for &x in a {
+        for (j, &y) in b.iter().enumerate() {
+            let diag = prev[j] + motif_score(x, y);
+            let up = prev[j + 1] - gap;
+            let left = curr[j] - gap;
+            curr[j + 1] = diag.max(up).max(left).max(0);
+            best = best.max(curr[j + 1]);
+        }
+        std::mem::swap(&mut prev, &mut curr);
+    }
+    best
+}
+
+/// Like `smith_waterman`, but also traces back which regions aligned.
+/// Keeps the full DP table, so prefer `smith_waterman` for scoring alone.
+pub fn smith_waterman_region(a: &[u8], b: &[u8], gap: i32) -> LocalMatch {
+    let cols = b.len() + 1;
+    let mut h = vec![0i32; (a.len() + 1) * cols];
+    let (mut best, mut end) = (0, (0, 0));
+
+    for i in 1..=a.len() {
+        for j in 1..=b.len() {
+            let diag = h[(i - 1) * cols + j - 1] + motif_score(a[i - 1], b[j - 1]);
+            let up = h[(i - 1) * cols + j] - gap;
+            let left = h[i * cols + j - 1] - gap;
+            let v = diag.max(up).max(left).max(0);
+            h[i * cols + j] = v;
+            if v > best {
+                best = v;
+                end = (i, j);
+            }
+        }
+    }
+
+    // walk back from the best cell until the score drops to zero
+    let (mut i, mut j) = end;
+    while i > 0 && j > 0 && h[i * cols + j] > 0 {
+        let v = h[i * cols + j];
+        if v == h[(i - 1) * cols + j - 1] + motif_score(a[i - 1], b[j - 1]) {
+            i -= 1;
+            j -= 1;
+        } else if v == h[(i - 1) * cols + j] - gap {
+            i -= 1;
+        } else {
+            j -= 1;
+        }
+    }
+
+    LocalMatch {
+        score: best,
+        a: i..end.0,
+        b: j..end.1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::BLOSUM62;
+    use crate::peptide::parse_sequence;
+
+    #[test]
+    fn test_identical_sequences_score_the_diagonal() {
+        let seq = parse_sequence("RGDSP").unwrap();
+        let diagonal: i32 = seq
+            .iter()
+            .map(|&aa| BLOSUM62[aa as usize][aa as usize] as i32)
+            .sum();
+        assert_eq!(needleman_wunsch(&seq, &seq, 4), diagonal);
+    }
+
+    #[test]
+    fn test_single_insertion_costs_one_gap() {
+        let motif = parse_sequence("RGD").unwrap();
+        let peptide = parse_sequence("RGWD").unwrap();
+        // R/R 5 + G/G 6 + D/D 6, W against a gap
+        assert_eq!(needleman_wunsch(&peptide, &motif, 4), 17 - 4);
+        assert_eq!(needleman_wunsch(&motif, &peptide, 4), 17 - 4);
+    }
+
+    #[test]
+    fn test_empty_sequences() {
+        let motif = parse_sequence("RGD").unwrap();
+        assert_eq!(needleman_wunsch(&[], &motif, 4), -12);
+        assert_eq!(needleman_wunsch(&[], &[], 4), 0);
+    }
+
+    #[test]
+    fn test_local_alignment_finds_embedded_motif() {
+        let motif = parse_sequence("RGD").unwrap();
+        let carrier = parse_sequence("AAWRGDWAA").unwrap();
+        assert_eq!(smith_waterman(&carrier, &motif, 4), 17);
+
+        let m = smith_waterman_region(&carrier, &motif, 4);
+        assert_eq!(m.score, 17);
+        assert_eq!(m.a, 3..6);
+        assert_eq!(m.b, 0..3);
+    }
+
+    #[test]
+    fn test_local_score_is_never_negative() {
+        let a = parse_sequence("WWWW").unwrap();
+        let b = parse_sequence("DDDD").unwrap();
+        assert_eq!(smith_waterman(&a, &b, 4), 0);
+        let m = smith_waterman_region(&a, &b, 4);
+        assert_eq!((m.score, m.a.len(), m.b.len()), (0, 0, 0));
+    }
+}
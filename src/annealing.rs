@@ -0,0 +1,185 @@
+//! Simulated annealing: a single-solution search that accepts worse moves
+//! with a probability that shrinks as the temperature cools.
+
+use crate::problem::TSProblem;
+use rand::{Rng, SeedableRng};
+use std::time::{Duration, Instant};
+
+/// Outcome of `SimulatedAnnealing::run`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct AnnealResult<I> {
+    pub best: I,
+    /// Best fitness so far after every step.
+    pub trace: Vec<(usize, f64)>,
+    /// Step that produced `best`; `0` if the start was never improved.
+    pub best_found_at: usize,
+    /// Number of `P::fitness` calls made by the run.
+    pub evaluations: u64,
+    /// Proposals that were accepted, improving or not.
+    pub accepted: usize,
+}
+
+/// Geometric cooling from `t_start` down to `t_end`, multiplying the
+/// temperature by `alpha` after every proposal.
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(bound = ""))]
+pub struct SimulatedAnnealing<P: TSProblem> {
+    pub t_start: f64,
+    pub t_end: f64,
+    pub alpha: f64,
+    /// Wall-clock limit checked before each step; the best so far is
+    /// returned once it is exceeded.
+    pub time_budget: Option<Duration>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) _phantom: std::marker::PhantomData<P>,
+}
+
+impl<P: TSProblem> SimulatedAnnealing<P> {
+    pub fn new(t_start: f64, t_end: f64, alpha: f64) -> Self {
+        Self {
+            t_start,
+            t_end,
+            alpha,
+            time_budget: None,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// Number of proposals the schedule makes.
+    pub fn steps(&self) -> usize {
+        assert!(
+            self.alpha > 0.0 && self.alpha < 1.0,
+            "cooling factor must be in (0, 1), got {}",
+            self.alpha
+        );
+        assert!(self.t_end > 0.0, "final temperature must be positive");
+        let mut t = self.t_start;
+        let mut n = 0;
+        while t > self.t_end {
+            t *= self.alpha;
+            n += 1;
+        }
+        n
+    }
+
+    pub fn run(&self, seed: u64) -> AnnealResult<P::Individ> {
+        let steps = self.steps();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let mut curr = P::random_individual(&mut rng);
+        let mut curr_f = P::fitness(&curr);
+        let mut evaluations = 1;
+        let mut best = curr.clone();
+        let mut best_f = curr_f;
+        let mut best_found_at = 0;
+        let mut accepted = 0;
+
+        let mut trace = Vec::new();
+        let start = Instant::now();
+        let mut t = self.t_start;
+
+        for step in 0..steps {
+            if self.time_budget.is_some_and(|b| start.elapsed() >= b) {
+                break;
+            }
+
+            if let Some((cand, _)) = P::neighbourhood(&mut rng, &curr, 1).pop() {
+                let f = P::fitness(&cand);
+                evaluations += 1;
+
+                // Metropolis criterion
+                let delta = f - curr_f;
+                if delta <= 0.0 || rng.gen::<f64>() < (-delta / t).exp() {
+                    curr = cand;
+                    curr_f = f;
+                    accepted += 1;
+                }
+                if curr_f < best_f {
+                    best = curr.clone();
+                    best_f = curr_f;
+                    best_found_at = step;
+                }
+            }
+            trace.push((step, best_f));
+            t *= self.alpha;
+        }
+
+        AnnealResult {
+            best,
+            trace,
+            best_found_at,
+            evaluations,
+            accepted,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peptide::{global_lock, set_motif, PeptideProblem};
+
+    /// Walk on the integers where every point scores the same.
+    struct Flat;
+
+    impl TSProblem for Flat {
+        type Individ = i64;
+        type Move = i64;
+
+        fn random_individual<R: Rng>(_rng: &mut R) -> i64 {
+            0
+        }
+
+        fn fitness(_ind: &i64) -> f64 {
+            1.0
+        }
+
+        fn neighbourhood<R: Rng>(rng: &mut R, ind: &i64, size: usize) -> Vec<(i64, i64)> {
+            (0..size)
+                .map(|_| {
+                    let step = if rng.gen() { 1 } else { -1 };
+                    (ind + step, step)
+                })
+                .collect()
+        }
+
+        fn apply_move(ind: &mut i64, mv: &i64) {
+            *ind += mv;
+        }
+    }
+
+    #[test]
+    fn test_steps_follow_schedule() {
+        let sa = SimulatedAnnealing::<Flat>::new(10.0, 1.0, 0.5);
+        // 10, 5, 2.5, 1.25 are above the final temperature
+        assert_eq!(sa.steps(), 4);
+        assert_eq!(SimulatedAnnealing::<Flat>::new(1.0, 1.0, 0.5).steps(), 0);
+    }
+
+    #[test]
+    fn test_flat_landscape_is_random_walk() {
+        let sa = SimulatedAnnealing::<Flat>::new(1.0, 1e-3, 0.99);
+        let res = sa.run(5);
+        // no move is ever worse, so every proposal is taken
+        assert_eq!(res.accepted, sa.steps());
+        assert_eq!(res.evaluations, sa.steps() as u64 + 1);
+        // ties never replace the best, which stays the start
+        assert_eq!(res.best, 0);
+        assert_eq!(res.best_found_at, 0);
+        assert!(res.trace.iter().all(|&(_, f)| f == 1.0));
+    }
+
+    #[test]
+    fn test_returns_best_visited() {
+        let _guard = global_lock();
+        set_motif(3).unwrap();
+
+        let sa = SimulatedAnnealing::<PeptideProblem>::new(5.0, 0.01, 0.99);
+        let res = sa.run(11);
+        assert_eq!(res.trace.len(), sa.steps());
+        assert!(res.trace.windows(2).all(|w| w[1].1 <= w[0].1));
+        let final_f = res.trace.last().unwrap().1;
+        assert_eq!(PeptideProblem::fitness(&res.best), final_f);
+        assert_eq!(res.trace[res.best_found_at].1, final_f);
+        // hot start: plenty of worse moves were taken
+        assert!(res.accepted > 0 && res.accepted < sa.steps());
+    }
+}
@@ -0,0 +1,247 @@
+//! Pluggable recombination: a `CrossoverOp` turns two parent sequences into
+//! two children, so new operators can be used by `GeneticAlgorithm` without
+//! extending the `Crossover` enum.
+
+use crate::genetic::{recombine_pair, Crossover};
+use crate::problem::SequenceProblem;
+use rand::{Rng, RngCore};
+use std::marker::PhantomData;
+
+/// Recombination of two parent sequences.
+pub trait CrossoverOp: Send + Sync {
+    /// Two children of `a` and `b`; the first is the one a GA that breeds a
+    /// single offspring per pair keeps.
+    fn cross(&self, a: &[u8], b: &[u8], rng: &mut dyn RngCore) -> (Vec<u8>, Vec<u8>);
+
+    /// Name shown in reports.
+    fn name(&self) -> &str {
+        "custom"
+    }
+}
+
+/// The positional schemes; the second child takes the complementary genes
+/// at the same cut points.
+impl CrossoverOp for Crossover {
+    fn cross(&self, a: &[u8], b: &[u8], rng: &mut dyn RngCore) -> (Vec<u8>, Vec<u8>) {
+        recombine_pair(a, b, *self, rng)
+    }
+
+    fn name(&self) -> &str {
+        match self {
+            Crossover::SinglePoint => "single-point",
+            Crossover::TwoPoint => "two-point",
+            Crossover::Uniform => "uniform",
+            Crossover::Pmx => "pmx",
+        }
+    }
+}
+
+/// Greedy locus-by-locus recombination: where the parents differ, each
+/// child keeps whichever allele scores better under `P::fitness` given the
+/// loci already decided. Costs two evaluations per differing locus.
+pub struct SmartUniform<P>(PhantomData<fn() -> P>);
+
+impl<P> SmartUniform<P> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<P> Default for SmartUniform<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: SequenceProblem> CrossoverOp for SmartUniform<P> {
+    fn cross(&self, a: &[u8], b: &[u8], rng: &mut dyn RngCore) -> (Vec<u8>, Vec<u8>) {
+        let mut score = |seq: &Vec<u8>| P::fitness(seq);
+        (
+            smart_uniform(a, b, rng, &mut score),
+            smart_uniform(b, a, rng, &mut score),
+        )
+    }
+
+    fn name(&self) -> &str {
+        "smart-uniform"
+    }
+}
+
+/// Child of `parent_a` that takes `parent_b`'s allele wherever it lowers
+/// `score`; the first locus is then drawn at random to keep diversity.
+pub(crate) fn smart_uniform<R: Rng + ?Sized>(
+    parent_a: &[u8],
+    parent_b: &[u8],
+    rng: &mut R,
+    score: &mut impl FnMut(&Vec<u8>) -> f64,
+) -> Vec<u8> {
+    let len = parent_a.len().min(parent_b.len());
+    let mut child = parent_a.to_vec(); // start as clone of A (cheap)
+
+    for i in 0..len {
+        if parent_a[i] == parent_b[i] {
+            continue;
+        } // no choice to make
+
+        // try allele from B
+        let old = child[i];
+        child[i] = parent_b[i];
+        let fit_b = score(&child);
+
+        // keep A's allele
+        child[i] = old;
+        let fit_a = score(&child);
+
+        // choose the better allele (lower energy)
+        if fit_b < fit_a {
+            child[i] = parent_b[i];
+        }
+    }
+
+    // randomise the first locus to keep diversity
+    if rng.gen::<bool>() {
+        child[0] = parent_b[0];
+    }
+
+    child
+}
+
+/// Crossover operators looked up by name, e.g. to resolve a command-line
+/// choice against built-in and user-registered operators alike.
+#[derive(Default)]
+pub struct CrossoverRegistry {
+    ops: Vec<Box<dyn CrossoverOp>>,
+}
+
+impl CrossoverRegistry {
+    /// Empty registry.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// The `Crossover` schemes plus smart-uniform over `P`.
+    pub fn with_builtins<P: SequenceProblem + 'static>() -> Self {
+        let mut registry = Self::new();
+        for kind in [
+            Crossover::SinglePoint,
+            Crossover::TwoPoint,
+            Crossover::Uniform,
+            Crossover::Pmx,
+        ] {
+            registry.register(Box::new(kind));
+        }
+        registry.register(Box::new(SmartUniform::<P>::new()));
+        registry
+    }
+
+    /// Adds `op` under its `name`, replacing an operator of the same name.
+    pub fn register(&mut self, op: Box<dyn CrossoverOp>) {
+        self.ops.retain(|o| o.name() != op.name());
+        self.ops.push(op);
+    }
+
+    /// Removes and returns the operator called `name`.
+    pub fn take(&mut self, name: &str) -> Option<Box<dyn CrossoverOp>> {
+        let idx = self.ops.iter().position(|o| o.name() == name)?;
+        Some(self.ops.remove(idx))
+    }
+
+    pub fn get(&self, name: &str) -> Option<&dyn CrossoverOp> {
+        self.ops
+            .iter()
+            .find(|o| o.name() == name)
+            .map(|o| o.as_ref())
+    }
+
+    /// Registered names in registration order.
+    pub fn names(&self) -> Vec<&str> {
+        self.ops.iter().map(|o| o.name()).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genetic::GeneticAlgorithm;
+    use crate::peptide::{global_lock, set_motif, PeptideProblem};
+    use crate::problem::TSProblem;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    /// Children are copies of the parents.
+    struct Parents;
+
+    impl CrossoverOp for Parents {
+        fn cross(&self, a: &[u8], b: &[u8], _rng: &mut dyn RngCore) -> (Vec<u8>, Vec<u8>) {
+            (a.to_vec(), b.to_vec())
+        }
+    }
+
+    #[test]
+    fn test_custom_operator_in_ga() {
+        let _guard = global_lock();
+        set_motif(2).unwrap();
+
+        let mut ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(20)
+            .generations(5)
+            .crossover_prob(1.0)
+            .crossover(Parents)
+            .build();
+        assert_eq!(ga.crossover.name(), "custom");
+        let res = ga.run(3);
+        assert_eq!(res.progress.len(), 5);
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let (a, b) = (vec![0, 1, 2, 3], vec![4, 5, 6, 7]);
+        let child = PeptideProblem::crossover(&a, &b, &Parents, &mut rng);
+        assert_eq!(child, a);
+    }
+
+    #[test]
+    fn test_complementary_children() {
+        let mut rng = StdRng::seed_from_u64(6);
+        let (a, b) = (vec![0u8; 8], vec![1u8; 8]);
+        for kind in [
+            Crossover::SinglePoint,
+            Crossover::TwoPoint,
+            Crossover::Uniform,
+        ] {
+            let (c1, c2) = kind.cross(&a, &b, &mut rng);
+            assert!(c1.iter().zip(&c2).all(|(x, y)| x + y == 1));
+        }
+    }
+
+    #[test]
+    fn test_smart_uniform_keeps_better_alleles() {
+        let mut rng = StdRng::seed_from_u64(2);
+        let (a, b) = (vec![1, 0, 1, 0, 1], vec![0, 1, 0, 1, 0]);
+        // every 1 costs, so the child ends up all zeros whichever parent
+        // supplies the random first locus
+        let mut score = |seq: &Vec<u8>| seq.iter().map(|&g| g as f64).sum();
+        for _ in 0..10 {
+            assert_eq!(smart_uniform(&a, &b, &mut rng, &mut score), vec![0; 5]);
+        }
+    }
+
+    #[test]
+    fn test_registry_lookup() {
+        let mut registry = CrossoverRegistry::with_builtins::<PeptideProblem>();
+        assert_eq!(
+            registry.names(),
+            [
+                "single-point",
+                "two-point",
+                "uniform",
+                "pmx",
+                "smart-uniform"
+            ]
+        );
+        assert_eq!(registry.get("pmx").unwrap().name(), "pmx");
+        assert!(registry.get("custom").is_none());
+
+        registry.register(Box::new(Parents));
+        assert!(registry.take("custom").is_some());
+        assert!(registry.get("custom").is_none());
+    }
+}
@@ -1,6 +1,16 @@
 /// AA - amino acids
 pub const AA_LETTERS: &[u8; 20] = b"ACDEFGHIKLMNPQRSTVWY";
 
+/// Number of residue types; valid residue indices are `0..ALPHABET_SIZE`.
+pub const ALPHABET_SIZE: usize = AA_LETTERS.len();
+
+/// Background amino-acid composition of UniProtKB/Swiss-Prot (percent), in
+/// `AA_LETTERS` order.
+pub const BACKGROUND_FREQ: [f32; 20] = [
+    8.25, 1.38, 5.46, 6.71, 3.86, 7.07, 2.27, 5.91, 5.80, 9.65, 2.41, 4.06, 4.74, 3.93, 5.53, 6.64,
+    5.35, 6.86, 1.10, 2.92,
+];
+
 /// BLOcks SUbstitution Matrix, version 62 (it tells us how substituting one AA for another affects it's functionality)
 pub const BLOSUM62: [[i8; 20]; 20] = [
     /*A*/
@@ -84,3 +94,108 @@ pub const BLOSUM62: [[i8; 20]; 20] = [
         -2, -2, -3, -2, 3, -3, 2, -1, -2, -1, -1, -2, -3, -1, -2, -2, -2, -1, 2, 7,
     ],
 ];
+
+/// Dipeptide instability weight values (DIWV) of Guruprasad et al. (1990),
+/// `INSTABILITY_DIWV[a][b]` for the dipeptide `ab`; used by the instability index.
+pub const INSTABILITY_DIWV: [[f32; 20]; 20] = [
+    /*A*/
+    [
+        1.00, 44.94, -7.49, 1.00, 1.00, 1.00, -7.49, 1.00, 1.00, 1.00, 1.00, 1.00, 20.26, 1.00,
+        1.00, 1.00, 1.00, 1.00, 1.00, 1.00,
+    ],
+    /*C*/
+    [
+        1.00, 1.00, 20.26, 1.00, 1.00, 1.00, 33.60, 1.00, 1.00, 20.26, 33.60, 1.00, 20.26, -6.54,
+        1.00, 1.00, 33.60, -6.54, 24.68, 1.00,
+    ],
+    /*D*/
+    [
+        1.00, 1.00, 1.00, 1.00, -6.54, 1.00, 1.00, 1.00, -7.49, 1.00, 1.00, 1.00, 1.00, 1.00,
+        -6.54, 20.26, -14.03, 1.00, 1.00, 1.00,
+    ],
+    /*E*/
+    [
+        1.00, 44.94, 20.26, 33.60, 1.00, 1.00, -6.54, 20.26, 1.00, 1.00, 1.00, 1.00, 20.26, 20.26,
+        1.00, 20.26, 1.00, 1.00, -14.03, 1.00,
+    ],
+    /*F*/
+    [
+        1.00, 1.00, 13.34, 1.00, 1.00, 1.00, 1.00, 1.00, -14.03, 1.00, 1.00, 1.00, 20.26, 1.00,
+        1.00, 1.00, 1.00, 1.00, 1.00, 33.60,
+    ],
+    /*G*/
+    [
+        -7.49, 1.00, 1.00, -6.54, 1.00, 13.34, 1.00, -7.49, -7.49, 1.00, 1.00, -7.49, 1.00, 1.00,
+        1.00, 1.00, -7.49, 1.00, 13.34, -7.49,
+    ],
+    /*H*/
+    [
+        1.00, 1.00, 1.00, 1.00, -9.37, -9.37, 1.00, 44.94, 24.68, 1.00, 1.00, 24.68, -1.88, 1.00,
+        1.00, 1.00, -6.54, 1.00, -1.88, 44.94,
+    ],
+    /*I*/
+    [
+        1.00, 1.00, 1.00, 44.94, 1.00, 1.00, 13.34, 1.00, -7.49, 20.26, 1.00, 1.00, -1.88, 1.00,
+        1.00, 1.00, 1.00, -7.49, 1.00, 1.00,
+    ],
+    /*K*/
+    [
+        1.00, 1.00, 1.00, 1.00, 1.00, -7.49, 1.00, -7.49, 1.00, -7.49, 33.60, 1.00, -6.54, 24.64,
+        33.60, 1.00, 1.00, -7.49, 1.00, 1.00,
+    ],
+    /*L*/
+    [
+        1.00, 1.00, 1.00, 1.00, 1.00, 1.00, 1.00, 1.00, -7.49, 1.00, 1.00, 1.00, 20.26, 33.60,
+        20.26, 1.00, 1.00, 1.00, 24.68, 1.00,
+    ],
+    /*M*/
+    [
+        13.34, 1.00, 1.00, 1.00, 1.00, 1.00, 58.28, 1.00, 1.00, 1.00, -1.88, 1.00, 44.94, -6.54,
+        -6.54, 44.94, -1.88, 1.00, 1.00, 24.68,
+    ],
+    /*N*/
+    [
+        1.00, -1.88, 1.00, 1.00, -14.03, -14.03, 1.00, 44.94, 24.68, 1.00, 1.00, 1.00, -1.88,
+        -6.54, 1.00, 1.00, -7.49, 1.00, -9.37, 1.00,
+    ],
+    /*P*/
+    [
+        20.26, -6.54, -6.54, 18.38, 20.26, 1.00, 1.00, 1.00, 1.00, 1.00, -6.54, 1.00, 20.26, 20.26,
+        -6.54, 20.26, 1.00, 20.26, -1.88, 1.00,
+    ],
+    /*Q*/
+    [
+        1.00, -6.54, 20.26, 20.26, -6.54, 1.00, 1.00, 1.00, 1.00, 1.00, 1.00, 1.00, 20.26, 20.26,
+        1.00, 44.94, 1.00, -6.54, 1.00, -6.54,
+    ],
+    /*R*/
+    [
+        1.00, 1.00, 1.00, 1.00, 1.00, -7.49, 20.26, 1.00, 1.00, 1.00, 1.00, 13.34, 20.26, 20.26,
+        58.28, 44.94, 1.00, 1.00, 58.28, -6.54,
+    ],
+    /*S*/
+    [
+        1.00, 33.60, 1.00, 20.26, 1.00, 1.00, 1.00, 1.00, 1.00, 1.00, 1.00, 1.00, 44.94, 20.26,
+        20.26, 20.26, 1.00, 1.00, 1.00, 1.00,
+    ],
+    /*T*/
+    [
+        1.00, 1.00, 1.00, 20.26, 13.34, -7.49, 1.00, 1.00, 1.00, 1.00, 1.00, -14.03, 1.00, -6.54,
+        1.00, 1.00, 1.00, 1.00, -14.03, 1.00,
+    ],
+    /*V*/
+    [
+        1.00, 1.00, -14.03, 1.00, 1.00, -7.49, 1.00, 1.00, -1.88, 1.00, 1.00, 1.00, 20.26, 1.00,
+        1.00, 1.00, -7.49, 1.00, 1.00, -6.54,
+    ],
+    /*W*/
+    [
+        -14.03, 1.00, 1.00, 1.00, 1.00, -9.37, 24.68, 1.00, 1.00, 13.34, 24.68, 13.34, 1.00, 1.00,
+        1.00, 1.00, -14.03, -7.49, 1.00, 1.00,
+    ],
+    /*Y*/
+    [
+        24.68, 1.00, 24.68, -6.54, 1.00, -7.49, 13.34, 1.00, 1.00, 1.00, 44.94, 1.00, 13.34, 1.00,
+        -15.91, 1.00, -7.49, 1.00, -9.37, 13.34,
+    ],
+];
@@ -0,0 +1,95 @@
+//! Per-position diversity of a sequence population.
+
+use crate::data::ALPHABET_SIZE;
+
+/// Shannon entropy in bits of the residue distribution at every position,
+/// from `0` (converged) to `log2(20)` (uniform). Sequences shorter than the
+/// longest one only count at the positions they cover.
+pub fn position_entropy(pop: &[Vec<u8>]) -> Vec<f32> {
+    let len = pop.iter().map(Vec::len).max().unwrap_or(0);
+    (0..len)
+        .map(|pos| {
+            let mut counts = [0usize; ALPHABET_SIZE];
+            for seq in pop.iter().filter(|s| pos < s.len()) {
+                counts[seq[pos] as usize] += 1;
+            }
+            let n: usize = counts.iter().sum();
+            -counts
+                .iter()
+                .filter(|&&c| c > 0)
+                .map(|&c| {
+                    let p = c as f32 / n as f32;
+                    p * p.log2()
+                })
+                .sum::<f32>()
+        })
+        .collect()
+}
+
+/// Most frequent residue at every position and the share of the sequences
+/// covering that position that carry it; ties go to the lower residue index.
+/// Like `position_entropy`, shorter sequences only count where they reach.
+pub fn consensus(pop: &[Vec<u8>]) -> (Vec<u8>, Vec<f32>) {
+    let len = pop.iter().map(Vec::len).max().unwrap_or(0);
+    (0..len)
+        .map(|pos| {
+            let mut counts = [0usize; ALPHABET_SIZE];
+            for seq in pop.iter().filter(|s| pos < s.len()) {
+                counts[seq[pos] as usize] += 1;
+            }
+            let n: usize = counts.iter().sum();
+            let (aa, &c) = counts
+                .iter()
+                .enumerate()
+                .rev()
+                .max_by_key(|&(_, c)| c)
+                .unwrap();
+            (aa as u8, c as f32 / n as f32)
+        })
+        .unzip()
+}
+
+/// Mean of `position_entropy`; `0` for an empty population.
+pub fn mean_entropy(pop: &[Vec<u8>]) -> f32 {
+    let h = position_entropy(pop);
+    if h.is_empty() {
+        return 0.0;
+    }
+    h.iter().sum::<f32>() / h.len() as f32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_uniform_population_has_zero_entropy() {
+        let pop = vec![vec![3u8, 7, 0, 19]; 10];
+        assert_eq!(position_entropy(&pop), vec![0.0; 4]);
+        assert_eq!(mean_entropy(&pop), 0.0);
+        assert_eq!(mean_entropy(&[]), 0.0);
+    }
+
+    #[test]
+    fn test_consensus() {
+        // position 0 is unanimous, position 1 split 2:1:1
+        let pop = vec![vec![5u8, 1], vec![5, 1], vec![5, 2], vec![5, 3]];
+        let (seq, share) = consensus(&pop);
+        assert_eq!(seq, vec![5, 1]);
+        assert_eq!(share, vec![1.0, 0.5]);
+
+        // an even split goes to the lower residue index
+        let (seq, share) = consensus(&[vec![4u8], vec![2]]);
+        assert_eq!((seq, share), (vec![2], vec![0.5]));
+        assert_eq!(consensus(&[]), (vec![], vec![]));
+    }
+
+    #[test]
+    fn test_entropy_in_bits() {
+        // position 0 splits evenly over two residues, position 1 over four
+        let pop = vec![vec![0u8, 0], vec![0, 1], vec![1, 2], vec![1, 3]];
+        let h = position_entropy(&pop);
+        assert!((h[0] - 1.0).abs() < 1e-6);
+        assert!((h[1] - 2.0).abs() < 1e-6);
+    }
+}
@@ -0,0 +1,163 @@
+//! Reading and writing peptides in FASTA format.
+
+use crate::output::sequence_string;
+use crate::peptide::{combined_fitness, is_biologically_valid, parse_sequence, MotifError};
+use std::io::{self, Write};
+
+/// Residues per sequence line when writing.
+const LINE_WIDTH: usize = 60;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct FastaRecord {
+    /// Header line without the leading `>`.
+    pub header: String,
+    /// Sequence as one-letter codes, line breaks removed.
+    pub sequence: String,
+}
+
+/// Header for a designed peptide, e.g. `motif1_neighbourga_fit-12.34`.
+pub fn design_header(motif: usize, algorithm: &str, fitness: f32) -> String {
+    let algorithm: String = algorithm
+        .chars()
+        .filter(|c| c.is_ascii_alphanumeric())
+        .map(|c| c.to_ascii_lowercase())
+        .collect();
+    format!("motif{}_{}_fit{:.2}", motif, algorithm, fitness)
+}
+
+/// Writes one record; `seq` holds residue indices into `AA_LETTERS`.
+pub fn write_record<W: Write>(out: &mut W, header: &str, seq: &[u8]) -> io::Result<()> {
+    writeln!(out, ">{}", header)?;
+    let letters = sequence_string(seq);
+    for line in letters.as_bytes().chunks(LINE_WIDTH) {
+        out.write_all(line)?;
+        writeln!(out)?;
+    }
+    Ok(())
+}
+
+/// Splits FASTA text into records. Blank lines and `;` comments are
+/// skipped, and text before the first header is ignored.
+pub fn parse(text: &str) -> Vec<FastaRecord> {
+    let mut records: Vec<FastaRecord> = Vec::new();
+    for line in text.lines().map(str::trim) {
+        if line.is_empty() || line.starts_with(';') {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('>') {
+            records.push(FastaRecord {
+                header: header.trim().to_string(),
+                sequence: String::new(),
+            });
+        } else if let Some(last) = records.last_mut() {
+            last.sequence.push_str(line);
+        }
+    }
+    records
+}
+
+impl FastaRecord {
+    /// First word of the header, the conventional sequence id.
+    pub fn id(&self) -> &str {
+        self.header.split_whitespace().next().unwrap_or("")
+    }
+}
+
+/// A record scored by `score_records`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScoredRecord {
+    pub id: String,
+    pub sequence: Vec<u8>,
+    pub fitness: f32,
+    pub valid: bool,
+}
+
+/// Scores every record with `combined_fitness` against the current motif
+/// and ranks them best first. Records that are not valid peptides are
+/// returned separately with the parse error.
+pub fn score_records(records: &[FastaRecord]) -> (Vec<ScoredRecord>, Vec<(String, MotifError)>) {
+    let mut scored = Vec::new();
+    let mut rejected = Vec::new();
+    for rec in records {
+        match parse_sequence(&rec.sequence) {
+            Ok(sequence) => scored.push(ScoredRecord {
+                id: rec.id().to_string(),
+                fitness: combined_fitness(&sequence),
+                valid: is_biologically_valid(&sequence),
+                sequence,
+            }),
+            Err(e) => rejected.push((rec.id().to_string(), e)),
+        }
+    }
+    scored.sort_by(|a, b| a.fitness.total_cmp(&b.fitness));
+    (scored, rejected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peptide::parse_sequence;
+
+    #[test]
+    fn test_design_header() {
+        assert_eq!(
+            design_header(1, "NeighbourGA", -12.344),
+            "motif1_neighbourga_fit-12.34"
+        );
+        assert_eq!(
+            design_header(0, "Normal GA", 3.0),
+            "motif0_normalga_fit3.00"
+        );
+    }
+
+    #[test]
+    fn test_write_then_parse() {
+        let short = parse_sequence("RGD").unwrap();
+        let long: Vec<u8> = (0..130).map(|i| (i % 20) as u8).collect();
+
+        let mut out = Vec::new();
+        write_record(&mut out, &design_header(1, "Tabu", -17.0), &short).unwrap();
+        write_record(&mut out, &design_header(2, "Normal GA", 1.5), &long).unwrap();
+        let text = String::from_utf8(out).unwrap();
+
+        // long sequences wrap onto several lines
+        assert_eq!(text.lines().count(), 2 + 1 + 3);
+
+        let records = parse(&text);
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].header, "motif1_tabu_fit-17.00");
+        assert_eq!(records[0].sequence, "RGD");
+        assert_eq!(records[1].header, "motif2_normalga_fit1.50");
+        assert_eq!(parse_sequence(&records[1].sequence).unwrap(), long);
+    }
+
+    #[test]
+    fn test_parse_skips_blank_and_comment_lines() {
+        let records = parse("junk\n; comment\n>a\nRG\n\nD\n>b\n");
+        assert_eq!(records.len(), 2);
+        assert_eq!(records[0].sequence, "RGD");
+        assert_eq!(records[1].sequence, "");
+    }
+
+    #[test]
+    fn test_score_fixture() {
+        use crate::peptide::{global_lock, set_motif, PeptideProblem, ScoringMode};
+
+        let _guard = global_lock();
+        set_motif(1).unwrap();
+        PeptideProblem::set_scoring_mode(ScoringMode::Global { gap: 4 });
+        let records = parse(include_str!("../data/candidates_example.fasta"));
+        let (scored, rejected) = score_records(&records);
+        PeptideProblem::set_scoring_mode(ScoringMode::Positional);
+
+        assert_eq!(records.len(), 5);
+        assert_eq!(rejected.len(), 1);
+        assert_eq!(rejected[0].0, "not_a_peptide");
+
+        let ids: Vec<&str> = scored.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids[0], "exact");
+        assert_eq!(*ids.last().unwrap(), "unrelated");
+        assert!(scored.windows(2).all(|w| w[0].fitness <= w[1].fitness));
+        set_motif(0).unwrap();
+    }
+}
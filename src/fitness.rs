@@ -0,0 +1,152 @@
+//! Composable fitness: weighted terms summed into one objective.
+use crate::nepre;
+use crate::peptide::{get_use_best_motif, net_charge, FitnessWeights, PeptideProblem};
+
+/// One component of the objective (lower = better).
+pub trait FitnessTerm: Send + Sync {
+    /// Unweighted score of `seq`.
+    fn score(&self, seq: &[u8]) -> f32;
+
+    /// Factor the score is multiplied by in a `CompositeFitness`.
+    fn weight(&self) -> f32;
+}
+
+/// Motif similarity: negated BLOSUM62 score against the current motif, or
+/// against the best-matching one in best-motif mode.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct BlosumTerm {
+    pub weight: f32,
+}
+
+impl FitnessTerm for BlosumTerm {
+    fn score(&self, seq: &[u8]) -> f32 {
+        if get_use_best_motif() {
+            PeptideProblem::energy_best_motif(seq)
+        } else {
+            PeptideProblem::energy(seq)
+        }
+    }
+
+    fn weight(&self) -> f32 {
+        self.weight
+    }
+}
+
+/// Pairwise neighbourhood energy summed over adjacent residues, plus the
+/// window-3 energies when a triplet table is loaded (`nepre::set_triplet_matrix`).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct NepreTerm {
+    pub weight: f32,
+}
+
+impl FitnessTerm for NepreTerm {
+    fn score(&self, seq: &[u8]) -> f32 {
+        let pairs: f32 = seq.windows(2).map(|w| nepre::pair(w[0], w[1])).sum();
+        if nepre::has_triplet() {
+            pairs + nepre::triplet_energy(seq)
+        } else {
+            pairs
+        }
+    }
+
+    fn weight(&self) -> f32 {
+        self.weight
+    }
+}
+
+/// Distance of the net charge from `target`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ChargeTerm {
+    pub weight: f32,
+    pub target: f32,
+}
+
+impl FitnessTerm for ChargeTerm {
+    fn score(&self, seq: &[u8]) -> f32 {
+        (net_charge(seq) - self.target).abs()
+    }
+
+    fn weight(&self) -> f32 {
+        self.weight
+    }
+}
+
+/// Weighted sum of fitness terms.
+pub struct CompositeFitness(pub Vec<Box<dyn FitnessTerm>>);
+
+impl CompositeFitness {
+    /// The objective behind `combined_fitness`: BLOSUM and NEPRE, plus the
+    /// charge term when a target charge is set.
+    pub fn from_weights(weights: &FitnessWeights) -> Self {
+        let mut terms: Vec<Box<dyn FitnessTerm>> = vec![
+            Box::new(BlosumTerm {
+                weight: weights.blosum,
+            }),
+            Box::new(NepreTerm {
+                weight: weights.nepre,
+            }),
+        ];
+        if let Some(target) = weights.target_charge {
+            terms.push(Box::new(ChargeTerm {
+                weight: weights.charge,
+                target,
+            }));
+        }
+        Self(terms)
+    }
+
+    pub fn score(&self, seq: &[u8]) -> f32 {
+        self.0.iter().map(|t| t.weight() * t.score(seq)).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peptide::{combined_fitness_weighted, global_lock, parse_sequence, set_motif};
+
+    /// Penalises every residue equally.
+    struct LengthTerm;
+
+    impl FitnessTerm for LengthTerm {
+        fn score(&self, seq: &[u8]) -> f32 {
+            seq.len() as f32
+        }
+
+        fn weight(&self) -> f32 {
+            0.5
+        }
+    }
+
+    #[test]
+    fn test_composite_sums_weighted_terms() {
+        let _guard = global_lock();
+        set_motif(1).unwrap();
+
+        let seq = parse_sequence("RGE").unwrap();
+        let blosum = BlosumTerm { weight: 2.0 };
+        let composite = CompositeFitness(vec![Box::new(blosum), Box::new(LengthTerm)]);
+        let expected = 2.0 * blosum.score(&seq) + 0.5 * 3.0;
+        assert!((composite.score(&seq) - expected).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_default_composite_matches_combined_fitness() {
+        let _guard = global_lock();
+        set_motif(2).unwrap();
+
+        let seq = parse_sequence("KRDEWY").unwrap();
+        for weights in [
+            FitnessWeights::default(),
+            FitnessWeights {
+                target_charge: Some(1.0),
+                ..FitnessWeights::default()
+            },
+        ] {
+            assert_eq!(
+                CompositeFitness::from_weights(&weights).score(&seq),
+                combined_fitness_weighted(&seq, &weights)
+            );
+        }
+    }
+}
@@ -1,18 +1,105 @@
-use rand::prelude::*;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+use std::collections::HashMap;
+use std::marker::PhantomData;
+use std::time::{Duration, Instant};
 
-use crate::nepre;
-use crate::peptide::combined_fitness;
-use crate::peptide::is_biologically_valid;
-use crate::peptide::PeptideProblem;
-use crate::problem::TSProblem;
+use crate::crossover::smart_uniform;
+use crate::genetic::{dedup, worst_indices, GenerationCallback, Immigrants, PopulationCallback};
+use crate::hall_of_fame::HallOfFame;
+use crate::hill_climb::ClimbMode;
+use crate::mutation::{GuidedSubstitution, Inversion, MutationSet, Substitution};
+use crate::peptide::{InitMode, ValidityConfig};
+use crate::problem::SequenceProblem;
+use crate::util::best_index;
+
+/// What hill climbing of an offspring passes on to the next generation.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum LocalSearchMode {
+    /// The improved genome replaces the child.
+    #[default]
+    Lamarckian,
+    /// The child keeps its genome but is selected on the improved fitness,
+    /// which preserves diversity at the cost of slower convergence. Progress
+    /// statistics report that learned fitness.
+    Baldwinian,
+}
+
+/// What happens to offspring that fail the plausibility filter.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ConstraintMode {
+    /// The child is replaced by a fresh random valid individual.
+    #[default]
+    Reject,
+    /// The child is edited towards validity (`SequenceProblem::repair_validity`)
+    /// and only replaced if that does not succeed.
+    Repair,
+    /// The child is kept and scored with `NeighCfg::violation_penalty` added
+    /// per broken rule, so evolution can smooth violations out. The final
+    /// best is then not guaranteed to be valid.
+    Penalty,
+}
 
 #[derive(Clone, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub struct NeighCfg {
     pub pop_size: usize,
     pub crossover_p: f32,
     pub mutation_p: f32,
     pub smart_xover: bool,
     pub max_gens: usize,
+    pub seed: u64,
+    /// Memoise fitness by sequence (see `FitnessCache`).
+    pub fitness_cache: bool,
+    /// Stop after this many consecutive generations without the best fitness
+    /// improving by more than `min_improvement`; `0` disables early stopping.
+    pub patience: usize,
+    pub min_improvement: f32,
+    /// Wall-clock limit checked before each generation.
+    pub time_budget: Option<Duration>,
+    /// Rules of the biological-plausibility filter applied to offspring.
+    pub validity: ValidityConfig,
+    /// How offspring that break those rules are handled.
+    pub constraint_mode: ConstraintMode,
+    /// Fitness added per violation in `ConstraintMode::Penalty`.
+    pub violation_penalty: f32,
+    /// How the initial population and replacement offspring are drawn.
+    pub init: InitMode,
+    /// Draw substituted residues by BLOSUM62 similarity to the motif residue
+    /// at that position instead of uniformly.
+    pub guided_mutation: bool,
+    /// Replace repeated offspring by fresh valid individuals before they are
+    /// scored.
+    pub dedup: bool,
+    /// Whether hill-climbing improvements are written back into the genome.
+    pub local_search: LocalSearchMode,
+    /// Substitution hill climbing takes the first improving residue at each
+    /// position or the best one.
+    pub climb_mode: ClimbMode,
+    /// Repeat the hill-climbing sweep until a full pass changes nothing.
+    pub climb_until_stable: bool,
+    /// Chance that an offspring is considered for hill climbing (with
+    /// `smart_xover` only); `0` disables local search.
+    pub local_search_prob: f32,
+    /// Chance that a considered offspring of at most `short_pep_len`
+    /// residues is climbed.
+    pub short_pep_prob: f32,
+    /// Chance that a considered longer offspring is climbed.
+    pub long_pep_prob: f32,
+    pub short_pep_len: usize,
+    /// Capacity of the run's `HallOfFame`; at least one entry is always kept.
+    pub hall_of_fame: usize,
+    /// Share of the population bred anew each generation, in `(0, 1]`. At
+    /// `1` the offspring replace the whole population (plus elitism); below
+    /// it the run is steady-state and the offspring replace only the worst
+    /// individuals, so the best always survives.
+    pub replacement_fraction: f32,
+    /// Replace the worst individuals by fresh valid ones on stagnation (see
+    /// `Immigrants`).
+    pub immigrants: Option<Immigrants>,
 }
 
 impl Default for NeighCfg {
@@ -23,49 +110,324 @@ impl Default for NeighCfg {
             mutation_p: 0.25,
             smart_xover: true,
             max_gens: 500,
+            seed: 0,
+            fitness_cache: false,
+            patience: 0,
+            min_improvement: 0.0,
+            time_budget: None,
+            validity: ValidityConfig::default(),
+            constraint_mode: ConstraintMode::Reject,
+            violation_penalty: 5.0,
+            init: InitMode::Uniform,
+            guided_mutation: false,
+            dedup: false,
+            local_search: LocalSearchMode::Lamarckian,
+            climb_mode: ClimbMode::Best,
+            climb_until_stable: false,
+            local_search_prob: 0.20,
+            short_pep_prob: 0.60,
+            long_pep_prob: 0.20,
+            short_pep_len: 5,
+            hall_of_fame: 10,
+            replacement_fraction: 1.0,
+            immigrants: None,
         }
     }
 }
-pub struct NeighbourGA<'a> {
-    problem: &'a PeptideProblem,
+
+/// Entries kept before the cache is flushed, to bound memory.
+const CACHE_CAPACITY: usize = 1 << 20;
+
+/// Memo table for `P::fitness`, keyed by sequence.
+///
+/// Smart crossover and population evaluation re-score many identical sequences.
+/// When disabled every lookup is a fresh evaluation; either way
+/// `evaluations` counts the real `P::fitness` calls.
+pub struct FitnessCache<P: SequenceProblem> {
+    enabled: bool,
+    map: HashMap<Vec<u8>, f32>,
+    evaluations: u64,
+    _phantom: PhantomData<P>,
+}
+
+impl<P: SequenceProblem> FitnessCache<P> {
+    pub fn new(enabled: bool) -> Self {
+        Self {
+            enabled,
+            map: HashMap::new(),
+            evaluations: 0,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn get(&mut self, seq: &Vec<u8>) -> f32 {
+        if let Some(&f) = self.lookup(seq) {
+            return f;
+        }
+        let f = P::fitness(seq) as f32;
+        self.evaluations += 1;
+        self.store(seq, f);
+        f
+    }
+
+    /// Scores a whole population; misses are evaluated in parallel with `rayon`.
+    pub fn get_all(&mut self, seqs: &[Vec<u8>]) -> Vec<f32> {
+        let mut out = vec![0.0; seqs.len()];
+        let mut missing = Vec::new();
+        for (i, seq) in seqs.iter().enumerate() {
+            match self.lookup(seq) {
+                Some(&f) => out[i] = f,
+                None => missing.push(i),
+            }
+        }
+
+        #[cfg(feature = "rayon")]
+        let iter = missing.par_iter();
+        #[cfg(not(feature = "rayon"))]
+        let iter = missing.iter();
+        let computed: Vec<f32> = iter.map(|&i| P::fitness(&seqs[i]) as f32).collect();
+
+        self.evaluations += computed.len() as u64;
+        for (&i, &f) in missing.iter().zip(&computed) {
+            out[i] = f;
+            self.store(&seqs[i], f);
+        }
+        out
+    }
+
+    /// Number of `P::fitness` calls made so far.
+    pub fn evaluations(&self) -> u64 {
+        self.evaluations
+    }
+
+    fn lookup(&self, seq: &[u8]) -> Option<&f32> {
+        if self.enabled {
+            self.map.get(seq)
+        } else {
+            None
+        }
+    }
+
+    fn store(&mut self, seq: &[u8], f: f32) {
+        if !self.enabled {
+            return;
+        }
+        if self.map.len() >= CACHE_CAPACITY {
+            self.map.clear();
+        }
+        self.map.insert(seq.to_vec(), f);
+    }
+}
+
+/// Per-generation statistics: (generation, min, max, avg) fitness.
+pub type NeighStats = (usize, f32, f32, f32);
+
+/// Outcome of `NeighbourGA::run`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NeighResult {
+    pub best: Vec<u8>,
+    pub progress: Vec<NeighStats>,
+    /// First generation whose best individual matched the fitness of `best`.
+    pub best_found_at: usize,
+    /// See `NeighbourGA::evaluations`.
+    pub evaluations: u64,
+    /// Last generation run when early stopping or the time budget ended the
+    /// run, `None` if it ran in full.
+    pub stopped_at: Option<usize>,
+    /// Offspring replaced by `NeighCfg::dedup` in every generation.
+    pub duplicates: Vec<usize>,
+    /// Random immigrants injected after every generation.
+    pub immigrants: Vec<usize>,
+    /// Best distinct sequences of the run with their fitness, best first.
+    pub hall_of_fame: Vec<(Vec<u8>, f32)>,
+}
+
+/// GA with smart (locus-by-locus) crossover and hill climbing over any
+/// `SequenceProblem`.
+pub struct NeighbourGA<P: SequenceProblem> {
     cfg: NeighCfg,
-    rng: ThreadRng,
+    rng: StdRng,
     population: Vec<Vec<u8>>,
     fitness: Vec<f32>,
+    cache: FitnessCache<P>,
+    /// Applied to every child after crossover.
+    mutations: MutationSet,
+    /// Fed with the true fitness of every genome as it is scored, before
+    /// any Baldwinian override.
+    hall_of_fame: HallOfFame<Vec<u8>, f32>,
+    /// `substitution_delta` calls made by hill climbing.
+    delta_evaluations: u64,
+    on_generation: Option<GenerationCallback>,
+    on_population: Option<PopulationCallback>,
 }
 
-impl<'a> NeighbourGA<'a> {
-    pub fn new(problem: &'a PeptideProblem, cfg: NeighCfg) -> Self {
-        let mut rng = thread_rng();
+impl<P: SequenceProblem + 'static> NeighbourGA<P> {
+    pub fn new(cfg: NeighCfg) -> Self {
+        assert!(
+            cfg.replacement_fraction > 0.0 && cfg.replacement_fraction <= 1.0,
+            "replacement fraction must be in (0, 1], got {}",
+            cfg.replacement_fraction
+        );
+        let mut rng = StdRng::seed_from_u64(cfg.seed);
         let mut population = Vec::with_capacity(cfg.pop_size);
         for _ in 0..cfg.pop_size {
-            population.push(PeptideProblem::random_individual(&mut rng));
+            population.push(P::initial_individual(&mut rng, cfg.init));
         }
+        let cache = FitnessCache::new(cfg.fitness_cache);
+        let hall_of_fame = HallOfFame::new(cfg.hall_of_fame.max(1));
+        let mutations = Self::default_mutations(&cfg);
         let mut ga = Self {
-            problem,
             cfg,
             rng,
             population,
             fitness: Vec::new(),
+            cache,
+            mutations,
+            hall_of_fame,
+            delta_evaluations: 0,
+            on_generation: None,
+            on_population: None,
         };
         ga.evaluate();
         ga
     }
 
-    pub fn run(&mut self) -> Vec<u8> {
-        for _gen in 0..self.cfg.max_gens {
-            self.step_generation();
+    pub fn run(&mut self) -> NeighResult {
+        let mut progress = Vec::new();
+        let mut best_so_far = self.best().1;
+        let mut stale = 0;
+        let mut stopped_at = None;
+        let mut duplicates = Vec::new();
+        let mut immigrant_stale = 0;
+        let mut immigrants = Vec::new();
+        let start = Instant::now();
+
+        for gen in 0..self.cfg.max_gens {
+            if self.cfg.time_budget.is_some_and(|b| start.elapsed() >= b) {
+                stopped_at = gen.checked_sub(1);
+                break;
+            }
+            duplicates.push(self.step_generation());
+            progress.push(self.generation_stats(gen));
+            let (idx, best) = self.best();
+            if let Some(cb) = &mut self.on_generation {
+                cb(gen, best, &self.population[idx]);
+            }
+            if let Some(cb) = &mut self.on_population {
+                cb(gen, &self.population);
+            }
+
+            // early stopping on a plateau of the best-ever fitness
+            let improved = best < best_so_far - self.cfg.min_improvement;
+            if improved {
+                stale = 0;
+            } else {
+                stale += 1;
+            }
+            best_so_far = best_so_far.min(best);
+            if self.cfg.patience > 0 && stale >= self.cfg.patience {
+                stopped_at = Some(gen);
+                immigrants.push(0);
+                break;
+            }
+
+            let mut injected = 0;
+            if let Some(im) = self.cfg.immigrants {
+                immigrant_stale = if improved { 0 } else { immigrant_stale + 1 };
+                if immigrant_stale >= im.stagnation_limit {
+                    immigrant_stale = 0;
+                    injected = self.inject_immigrants(im);
+                }
+            }
+            immigrants.push(injected);
+        }
+
+        // the best ever seen; with elitism that is also the final best
+        // unless Baldwinian fitness hides it
+        let (best, best_f) = self.hall_of_fame.best().cloned().unwrap();
+        let best_found_at = progress.iter().position(|p| p.1 <= best_f).unwrap_or(0);
+        NeighResult {
+            best,
+            progress,
+            best_found_at,
+            evaluations: self.evaluations(),
+            stopped_at,
+            duplicates,
+            immigrants,
+            hall_of_fame: self.hall_of_fame.entries().to_vec(),
         }
-        self.best_individual().to_vec()
     }
 
-    pub fn best(&self) -> (usize, f32) {
-        self.fitness
+    /// The `n` best distinct sequences seen over a whole run, with their
+    /// fitness and sorted best first; fewer if the run saw fewer.
+    pub fn run_top_n(&mut self, n: usize) -> Vec<(Vec<u8>, f32)> {
+        let mut hall_of_fame = HallOfFame::new(n.max(1));
+        hall_of_fame.update(&self.population, &self.fitness);
+        self.hall_of_fame = hall_of_fame;
+        let mut top = self.run().hall_of_fame;
+        top.truncate(n);
+        top
+    }
+
+    fn generation_stats(&self, gen: usize) -> NeighStats {
+        let min = self.fitness.iter().copied().fold(f32::INFINITY, f32::min);
+        let max = self
+            .fitness
             .iter()
-            .enumerate()
-            .min_by(|a, b| a.1.partial_cmp(b.1).unwrap())
-            .map(|(i, &f)| (i, f))
-            .unwrap()
+            .copied()
+            .fold(f32::NEG_INFINITY, f32::max);
+        let avg = self.fitness.iter().sum::<f32>() / self.fitness.len() as f32;
+        (gen, min, max, avg)
+    }
+
+    /// Registers a callback invoked after every generation with its index,
+    /// the best fitness and the best sequence; it must not panic.
+    pub fn on_generation(&mut self, cb: GenerationCallback) {
+        self.on_generation = Some(cb);
+    }
+
+    /// Substitution (guided if `guided_mutation` is set) and inversion,
+    /// each fired with probability `mutation_p`.
+    pub fn default_mutations(cfg: &NeighCfg) -> MutationSet {
+        let p = cfg.mutation_p as f64;
+        let set = if cfg.guided_mutation {
+            MutationSet::new().with(GuidedSubstitution::<P>::new(), p)
+        } else {
+            MutationSet::new().with(
+                Substitution {
+                    alphabet: P::ALPHABET,
+                },
+                p,
+            )
+        };
+        set.with(Inversion, p)
+    }
+
+    /// Replaces the default mutation operators, see `default_mutations`.
+    pub fn set_mutations(&mut self, mutations: MutationSet) {
+        self.mutations = mutations;
+    }
+
+    /// Registers a callback invoked after every generation with its index
+    /// and the population; it must not panic.
+    pub fn on_population(&mut self, cb: PopulationCallback) {
+        self.on_population = Some(cb);
+    }
+
+    /// Index and fitness of the best individual, the lowest index on a tie.
+    pub fn best(&self) -> (usize, f32) {
+        best_index(&self.fitness).unwrap()
+    }
+
+    /// Number of fitness evaluations performed so far: full scores plus
+    /// the incremental `substitution_delta` scores of hill climbing.
+    pub fn evaluations(&self) -> u64 {
+        self.cache.evaluations() + self.delta_evaluations
+    }
+
+    /// The `substitution_delta` part of `evaluations`.
+    pub fn delta_evaluations(&self) -> u64 {
+        self.delta_evaluations
     }
 
     pub fn best_individual(&self) -> &[u8] {
@@ -73,10 +435,31 @@ impl<'a> NeighbourGA<'a> {
         &self.population[idx]
     }
 
-    fn step_generation(&mut self) {
-        let mut next_pop = Vec::with_capacity(self.cfg.pop_size);
+    /// The current generation, the final one after `run`.
+    pub fn population(&self) -> &[Vec<u8>] {
+        &self.population
+    }
+
+    /// Offspring bred per generation under `replacement_fraction`; fewer
+    /// than the population whenever the run is steady-state.
+    fn offspring_count(&self) -> usize {
+        let pop_size = self.cfg.pop_size;
+        if self.cfg.replacement_fraction >= 1.0 {
+            return pop_size;
+        }
+        let n = (self.cfg.replacement_fraction * pop_size as f32).round() as usize;
+        n.min(pop_size.saturating_sub(1)).max(1)
+    }
+
+    /// Breeds the next generation; returns the number of duplicates replaced.
+    fn step_generation(&mut self) -> usize {
+        let (elite_idx, elite_fit) = self.best();
+        let elite = self.population[elite_idx].clone();
+        let n_offspring = self.offspring_count();
+        let mut next_pop = Vec::with_capacity(n_offspring);
+        let mut learned = Vec::with_capacity(n_offspring);
 
-        while next_pop.len() < self.cfg.pop_size {
+        while next_pop.len() < n_offspring {
             let p1 = self.tournament_pick(3);
             let p2 = self.tournament_pick(3);
 
@@ -85,9 +468,10 @@ impl<'a> NeighbourGA<'a> {
 
             let (mut child_a, mut child_b) = if self.rng.gen::<f32>() < self.cfg.crossover_p {
                 if self.cfg.smart_xover {
+                    let mut score = |seq: &Vec<u8>| self.cache.get(seq) as f64;
                     (
-                        smart_uniform(parent_a, parent_b, &mut self.rng),
-                        smart_uniform(parent_b, parent_a, &mut self.rng),
+                        smart_uniform(parent_a, parent_b, &mut self.rng, &mut score),
+                        smart_uniform(parent_b, parent_a, &mut self.rng, &mut score),
                     )
                 } else {
                     uniform_crossover(parent_a, parent_b, &mut self.rng)
@@ -96,62 +480,138 @@ impl<'a> NeighbourGA<'a> {
                 (parent_a.clone(), parent_b.clone())
             };
 
-            mutate_all(&mut child_a, self.cfg.mutation_p, &mut self.rng);
-            mutate_all(&mut child_b, self.cfg.mutation_p, &mut self.rng);
+            self.mutations.apply(&mut child_a, &mut self.rng);
+            self.mutations.apply(&mut child_b, &mut self.rng);
 
-            PeptideProblem::repair(&mut child_a);
-            PeptideProblem::repair(&mut child_b);
+            P::repair(&mut child_a);
+            P::repair(&mut child_b);
 
-            if self.cfg.smart_xover && self.rng.gen::<f32>() < 0.20 {
-                let lc_prob = if child_a.len() <= 5 { 0.60 } else { 0.20 };
-                if self.rng.gen::<f32>() < lc_prob {
-                    hill_climb_optimize(&mut child_a);
-                }
+            // fitness learned by Baldwinian local search
+            let mut learned_a = None;
+            let mut learned_b = None;
+            if self.wants_local_search(&child_a) {
+                learned_a = self.local_search(&mut child_a);
             }
-            if self.cfg.smart_xover && self.rng.gen::<f32>() < 0.20 {
-                let lc_prob = if child_b.len() <= 5 { 0.60 } else { 0.20 };
-                if self.rng.gen::<f32>() < lc_prob {
-                    hill_climb_optimize(&mut child_b);
-                }
+            if self.wants_local_search(&child_b) {
+                learned_b = self.local_search(&mut child_b);
             }
 
             // —--- Biological-plausibility filter —---
-            if !is_biologically_valid(&child_a) {
-                child_a = loop {
-                    let mut cand = PeptideProblem::random_individual(&mut self.rng);
-                    PeptideProblem::repair(&mut cand);
-                    if is_biologically_valid(&cand) {
-                        break cand;
-                    }
-                };
+            if self.enforce_validity(&mut child_a) {
+                learned_a = None;
             }
-            if !is_biologically_valid(&child_b) {
-                child_b = loop {
-                    let mut cand = PeptideProblem::random_individual(&mut self.rng);
-                    PeptideProblem::repair(&mut cand);
-                    if is_biologically_valid(&cand) {
-                        break cand;
-                    }
-                };
+            if self.enforce_validity(&mut child_b) {
+                learned_b = None;
             }
             // —--- end filter —---
 
             next_pop.push(child_a);
-            if next_pop.len() < self.cfg.pop_size {
+            learned.push(learned_a);
+            if next_pop.len() < n_offspring {
                 next_pop.push(child_b);
+                learned.push(learned_b);
             }
         }
 
+        let duplicates = if self.cfg.dedup {
+            let replaced = dedup(&mut next_pop, &mut self.rng, |rng| {
+                fresh_valid::<P, _>(rng, &self.cfg)
+            });
+            // fitness learned by a replaced genome no longer applies
+            for &i in &replaced {
+                learned[i] = None;
+            }
+            replaced.len()
+        } else {
+            0
+        };
+
+        if n_offspring < self.cfg.pop_size {
+            // steady-state: the offspring take the slots of the worst
+            let mut fitness = self.score(&next_pop);
+            for (f, l) in fitness.iter_mut().zip(learned) {
+                if let Some(l) = l {
+                    *f = l;
+                }
+            }
+            let mut worst_first: Vec<usize> = (0..self.population.len()).collect();
+            worst_first.sort_by(|&a, &b| self.fitness[b].total_cmp(&self.fitness[a]));
+            for ((slot, child), f) in worst_first.into_iter().zip(next_pop).zip(fitness) {
+                self.population[slot] = child;
+                self.fitness[slot] = f;
+            }
+            return duplicates;
+        }
+
         self.population = next_pop;
         self.evaluate();
-        let (best_idx, _) = self.best();
-        let elite = self.population[best_idx].clone();
-        let pop_len = self.population.len();
-        let elite_present = self.population.contains(&elite);
-        if !elite_present {
-            // ensure not already present
-            let rnd_idx = self.rng.gen_range(0..pop_len);
-            self.population[rnd_idx] = elite;
+        for (f, l) in self.fitness.iter_mut().zip(learned) {
+            if let Some(l) = l {
+                *f = l;
+            }
+        }
+
+        // elitism: the previous generation's best replaces the worst offspring
+        // unless it survived breeding
+        if !self.population.contains(&elite) {
+            let (worst_idx, _) = self
+                .fitness
+                .iter()
+                .enumerate()
+                .max_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+                .unwrap();
+            self.population[worst_idx] = elite;
+            self.fitness[worst_idx] = elite_fit;
+        }
+        duplicates
+    }
+
+    /// Applies `constraint_mode` to an offspring; `true` if its genome was
+    /// changed.
+    fn enforce_validity(&mut self, child: &mut Vec<u8>) -> bool {
+        if self.cfg.constraint_mode == ConstraintMode::Penalty
+            || P::is_valid(child, &self.cfg.validity)
+        {
+            return false;
+        }
+        if self.cfg.constraint_mode == ConstraintMode::Repair {
+            P::repair_validity(child, &self.cfg.validity, &mut self.rng);
+            if P::is_valid(child, &self.cfg.validity) {
+                return true;
+            }
+        }
+        *child = fresh_valid::<P, _>(&mut self.rng, &self.cfg);
+        true
+    }
+
+    /// Whether `child` is hill-climbed: a first draw against
+    /// `local_search_prob`, then one against the probability for its length
+    /// (short peptides are cheap to climb, so they get their own).
+    fn wants_local_search(&mut self, child: &[u8]) -> bool {
+        let length_prob = if child.len() <= self.cfg.short_pep_len {
+            self.cfg.short_pep_prob
+        } else {
+            self.cfg.long_pep_prob
+        };
+        self.cfg.smart_xover
+            && self.rng.gen::<f32>() < self.cfg.local_search_prob
+            && self.rng.gen::<f32>() < length_prob
+    }
+
+    /// Hill-climbs `child`. Lamarckian search keeps the improved genome;
+    /// Baldwinian search restores the original and returns the improved
+    /// fitness to select it on.
+    fn local_search(&mut self, child: &mut [u8]) -> Option<f32> {
+        match self.cfg.local_search {
+            LocalSearchMode::Lamarckian => {
+                self.delta_evaluations += hill_climb_optimize::<P>(child, &self.cfg);
+                None
+            }
+            LocalSearchMode::Baldwinian => {
+                let mut improved = child.to_vec();
+                self.delta_evaluations += hill_climb_optimize::<P>(&mut improved, &self.cfg);
+                Some(self.cache.get(&improved) + self.penalty(&improved))
+            }
         }
     }
 
@@ -168,53 +628,127 @@ impl<'a> NeighbourGA<'a> {
         best_idx
     }
 
+    /// Fitness surcharge of `seq` under `ConstraintMode::Penalty`, else `0`.
+    fn penalty(&self, seq: &[u8]) -> f32 {
+        if self.cfg.constraint_mode != ConstraintMode::Penalty {
+            return 0.0;
+        }
+        self.cfg.violation_penalty * P::violations(seq, &self.cfg.validity) as f32
+    }
+
     fn evaluate(&mut self) {
-        self.fitness = self
-            .population
+        let population = std::mem::take(&mut self.population);
+        self.fitness = self.score(&population);
+        self.population = population;
+    }
+
+    /// Replaces the worst individuals by fresh valid ones; returns how many.
+    fn inject_immigrants(&mut self, im: Immigrants) -> usize {
+        let worst = worst_indices(&self.fitness, im.count(self.population.len()));
+        let fresh: Vec<Vec<u8>> = worst
             .iter()
-            .map(|seq| self.fitness_of(seq))
+            .map(|_| fresh_valid::<P, _>(&mut self.rng, &self.cfg))
             .collect();
+        let fitness = self.score(&fresh);
+        for ((&idx, seq), f) in worst.iter().zip(fresh).zip(fitness) {
+            self.population[idx] = seq;
+            self.fitness[idx] = f;
+        }
+        worst.len()
     }
 
-    fn fitness_of(&self, seq: &[u8]) -> f32 {
-        combined_fitness(seq)
+    /// Fitness of `seqs` including any penalty, recorded in the hall of fame.
+    fn score(&mut self, seqs: &[Vec<u8>]) -> Vec<f32> {
+        let mut fitness = self.cache.get_all(seqs);
+        for (f, seq) in fitness.iter_mut().zip(seqs) {
+            *f += self.penalty(seq);
+        }
+        self.hall_of_fame.update(seqs, &fitness);
+        fitness
     }
 }
 
-fn hill_climb_optimize(seq: &mut [u8]) {
-    let mut best_score = combined_fitness(seq);
+/// Upper bound on the sweeps of a multi-pass climb.
+const MAX_CLIMB_PASSES: usize = 100;
 
-    for pos in 0..seq.len() {
-        let orig = seq[pos];
-        let mut best_local = best_score;
-        let mut best_aa = orig;
+/// Greedy per-position substitution; returns the number of deltas scored.
+///
+/// `ClimbMode::Best` commits the best of the alternatives at each position,
+/// `ClimbMode::First` the first one that lowers fitness. With
+/// `climb_until_stable` the sweep repeats until a pass changes nothing.
+fn hill_climb_optimize<P: SequenceProblem>(seq: &mut [u8], cfg: &NeighCfg) -> u64 {
+    let mut scored = 0;
+    // once valid, every committed substitution keeps it so and the cheaper
+    // local check applies
+    let mut valid = P::is_valid(seq, &cfg.validity);
+    for _ in 0..MAX_CLIMB_PASSES {
+        let mut changed = false;
+        for pos in 0..seq.len() {
+            let orig = seq[pos];
+            let mut best_delta = 0.0;
+            let mut best_aa = orig;
 
-        // test the 19 alternative amino acids
-        for aa in 0u8..20 {
-            if aa == orig {
-                continue;
-            }
-            seq[pos] = aa;
+            // test the 19 alternative amino acids
+            for aa in 0..P::ALPHABET as u8 {
+                if aa == orig {
+                    continue;
+                }
 
-            // keep search inside biologically plausible space
-            if !is_biologically_valid(seq) {
-                continue;
-            }
+                // keep search inside biologically plausible space
+                let keeps_valid = if valid {
+                    P::substitution_keeps_valid(seq, pos, aa, &cfg.validity)
+                } else {
+                    seq[pos] = aa;
+                    let ok = P::is_valid(seq, &cfg.validity);
+                    seq[pos] = orig;
+                    ok
+                };
+                if !keeps_valid {
+                    continue;
+                }
 
-            let score = combined_fitness(seq);
-            if score < best_local {
-                best_local = score;
-                best_aa = aa;
+                // only the terms touching `pos` change
+                let delta = P::substitution_delta(seq, pos, aa);
+                scored += 1;
+                if delta < best_delta {
+                    best_delta = delta;
+                    best_aa = aa;
+                    if cfg.climb_mode == ClimbMode::First {
+                        break;
+                    }
+                }
             }
+
+            // commit the best substitution found for this position
+            seq[pos] = best_aa;
+            changed |= best_aa != orig;
+            valid |= best_aa != orig;
         }
+        if !cfg.climb_until_stable || !changed {
+            break;
+        }
+    }
+    scored
+}
 
-        // commit the best substitution found for this position
-        seq[pos] = best_aa;
-        best_score = best_local;
+/// Draws `fresh_valid` tries before settling for an invalid individual.
+const FRESH_VALID_ATTEMPTS: usize = 1000;
+
+/// Random individual that passes the plausibility filter, or the last draw
+/// if none of `FRESH_VALID_ATTEMPTS` does (the filter may be unsatisfiable).
+fn fresh_valid<P: SequenceProblem, R: Rng>(rng: &mut R, cfg: &NeighCfg) -> Vec<u8> {
+    let mut cand = Vec::new();
+    for _ in 0..FRESH_VALID_ATTEMPTS {
+        cand = P::initial_individual(rng, cfg.init);
+        P::repair(&mut cand);
+        if P::is_valid(&cand, &cfg.validity) {
+            break;
+        }
     }
+    cand
 }
 
-fn uniform_crossover(a: &[u8], b: &[u8], rng: &mut ThreadRng) -> (Vec<u8>, Vec<u8>) {
+fn uniform_crossover<R: Rng>(a: &[u8], b: &[u8], rng: &mut R) -> (Vec<u8>, Vec<u8>) {
     let mut child_a = a.to_vec();
     let mut child_b = b.to_vec();
     for i in 0..a.len() {
@@ -226,57 +760,423 @@ fn uniform_crossover(a: &[u8], b: &[u8], rng: &mut ThreadRng) -> (Vec<u8>, Vec<u
     (child_a, child_b)
 }
 
-fn smart_uniform(parent_a: &[u8], parent_b: &[u8], rng: &mut ThreadRng) -> Vec<u8> {
-    let len = parent_a.len();
-    let mut child = parent_a.to_vec(); // start as clone of A (cheap)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peptide::{combined_fitness, global_lock, set_motif, PeptideProblem};
+    use crate::problem::TSProblem;
+
+    fn small_cfg(seed: u64) -> NeighCfg {
+        NeighCfg {
+            pop_size: 20,
+            max_gens: 5,
+            seed,
+            ..NeighCfg::default()
+        }
+    }
 
-    for i in 0..len {
-        if parent_a[i] == parent_b[i] {
-            continue;
-        } // no choice to make
+    #[test]
+    fn test_same_seed_same_result() {
+        let _guard = global_lock();
+        set_motif(1).unwrap();
 
-        // try allele from B
-        let old = child[i];
-        child[i] = parent_b[i];
-        let fit_b = combined_fitness(&child);
+        let a = NeighbourGA::<PeptideProblem>::new(small_cfg(7)).run();
+        let b = NeighbourGA::<PeptideProblem>::new(small_cfg(7)).run();
+        assert_eq!(a, b);
+    }
 
-        // keep A's allele
-        child[i] = old;
-        let fit_a = combined_fitness(&child);
+    #[test]
+    fn test_unsatisfiable_validity_terminates() {
+        let _guard = global_lock();
+        set_motif(1).unwrap();
 
-        // choose the better allele (lower energy)
-        if fit_b < fit_a {
-            child[i] = parent_b[i];
+        // no peptide has an average hydropathy above 10
+        let cfg = NeighCfg {
+            validity: ValidityConfig {
+                hydro_min: 10.0,
+                hydro_max: 20.0,
+                ..ValidityConfig::default()
+            },
+            ..small_cfg(2)
+        };
+        let mut rng = StdRng::seed_from_u64(1);
+        let cand = fresh_valid::<PeptideProblem, _>(&mut rng, &cfg);
+        assert!(!PeptideProblem::is_valid(&cand, &cfg.validity));
+        assert_eq!(NeighbourGA::<PeptideProblem>::new(cfg).run().best.len(), 3);
+    }
+
+    #[test]
+    fn test_fitness_cache_saves_evaluations() {
+        let _guard = global_lock();
+        set_motif(1).unwrap();
+
+        let mut plain = NeighbourGA::<PeptideProblem>::new(small_cfg(5));
+        let plain_best = plain.run();
+
+        let cached_cfg = NeighCfg {
+            fitness_cache: true,
+            ..small_cfg(5)
+        };
+        let mut cached = NeighbourGA::<PeptideProblem>::new(cached_cfg);
+        let cached_best = cached.run();
+
+        // memoisation must not change the search, only its cost
+        assert_eq!(plain_best.best, cached_best.best);
+        assert_eq!(plain_best.progress, cached_best.progress);
+        assert_eq!(cached_best.evaluations, cached.evaluations());
+        assert_eq!(cached.delta_evaluations(), plain.delta_evaluations());
+        let full = |ga: &NeighbourGA<PeptideProblem>| ga.evaluations() - ga.delta_evaluations();
+        assert!(
+            full(&cached) * 2 < full(&plain),
+            "cached {} vs plain {}",
+            full(&cached),
+            full(&plain)
+        );
+    }
+
+    #[test]
+    fn test_elite_survives_disruptive_breeding() {
+        let _guard = global_lock();
+        set_motif(7).unwrap();
+
+        // heavy mutation of every child practically never reproduces the elite
+        let cfg = NeighCfg {
+            pop_size: 10,
+            smart_xover: false,
+            crossover_p: 1.0,
+            mutation_p: 1.0,
+            ..small_cfg(3)
+        };
+        let mut ga = NeighbourGA::<PeptideProblem>::new(cfg);
+        let elite = crate::peptide::parse_sequence(&crate::peptide::motif_string(7)).unwrap();
+        ga.population[0] = elite.clone();
+        ga.evaluate();
+        let elite_fit = ga.best().1;
+
+        for _ in 0..5 {
+            ga.step_generation();
+            assert!(ga.population.contains(&elite));
+            assert!(ga.best().1 <= elite_fit);
         }
     }
 
-    // randomise the first locus to keep diversity
-    if rng.gen::<bool>() {
-        child[0] = parent_b[0];
+    #[test]
+    fn test_steady_state_keeps_best() {
+        let _guard = global_lock();
+        set_motif(2).unwrap();
+
+        let cfg = NeighCfg {
+            replacement_fraction: 0.1,
+            mutation_p: 1.0,
+            ..small_cfg(5)
+        };
+        let mut ga = NeighbourGA::<PeptideProblem>::new(cfg);
+        assert_eq!(ga.offspring_count(), 2);
+        for _ in 0..20 {
+            let before = ga.population.clone();
+            let (idx, best_fit) = ga.best();
+            let best = before[idx].clone();
+            ga.step_generation();
+            assert!(ga.population.contains(&best));
+            assert!(ga.best().1 <= best_fit);
+            let replaced = before
+                .iter()
+                .zip(&ga.population)
+                .filter(|(a, b)| a != b)
+                .count();
+            assert!(replaced <= 2);
+        }
     }
 
-    child
-}
+    #[test]
+    fn test_on_generation_called_every_generation() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+        use std::sync::Arc;
+
+        let _guard = global_lock();
+        set_motif(1).unwrap();
 
-fn mutate_all(seq: &mut [u8], p: f32, rng: &mut ThreadRng) {
-    if rng.gen::<f32>() < p {
-        mutate_substitution(seq, rng);
+        let count = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&count);
+        let mut ga = NeighbourGA::<PeptideProblem>::new(small_cfg(2));
+        ga.on_generation(Box::new(move |gen, _, seq: &[u8]| {
+            assert_eq!(gen, counter.fetch_add(1, Ordering::SeqCst));
+            assert_eq!(seq.len(), 3);
+        }));
+        ga.run();
+        assert_eq!(count.load(Ordering::SeqCst), 5);
     }
-    if rng.gen::<f32>() < p {
-        mutate_inversion(seq, rng);
+
+    #[test]
+    fn test_early_stopping() {
+        let _guard = global_lock();
+        set_motif(1).unwrap();
+
+        let cfg = NeighCfg {
+            max_gens: 500,
+            patience: 5,
+            ..small_cfg(1)
+        };
+        let result = NeighbourGA::<PeptideProblem>::new(cfg).run();
+        let stop = result.stopped_at.expect("run should stop early");
+        assert!(stop < 499);
+        assert_eq!(result.progress.len(), stop + 1);
     }
-}
 
-fn mutate_substitution(seq: &mut [u8], rng: &mut ThreadRng) {
-    let idx = rng.gen_range(0..seq.len());
-    seq[idx] = rng.gen_range(0..20) as u8;
-}
+    #[test]
+    fn test_time_budget() {
+        let _guard = global_lock();
+        set_motif(6).unwrap();
+
+        let cfg = NeighCfg {
+            max_gens: 1_000_000,
+            time_budget: Some(Duration::from_millis(50)),
+            ..small_cfg(1)
+        };
+        let start = Instant::now();
+        let result = NeighbourGA::<PeptideProblem>::new(cfg).run();
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(result.stopped_at, Some(result.progress.len() - 1));
+    }
+
+    #[test]
+    fn test_progress_has_one_entry_per_generation() {
+        let _guard = global_lock();
+        set_motif(1).unwrap();
+
+        let progress = NeighbourGA::<PeptideProblem>::new(small_cfg(3))
+            .run()
+            .progress;
+        assert_eq!(progress.len(), 5);
+        for (i, &(gen, min, max, avg)) in progress.iter().enumerate() {
+            assert_eq!(gen, i);
+            assert!(min <= avg + 1e-4 && avg <= max + 1e-4);
+        }
+
+        let res = NeighbourGA::<PeptideProblem>::new(small_cfg(3)).run();
+        let found = res.progress[res.best_found_at].1;
+        assert_eq!(found, res.progress.last().unwrap().1);
+        assert!(res.progress[..res.best_found_at]
+            .iter()
+            .all(|p| p.1 > found));
+    }
+
+    #[test]
+    fn test_guided_mutation_on_motif_1() {
+        let _guard = global_lock();
+        set_motif(1).unwrap();
+
+        let (mut plain_sum, mut guided_sum) = (0.0, 0.0);
+        for seed in 0..6 {
+            let cfg = NeighCfg {
+                smart_xover: false,
+                mutation_p: 0.8,
+                ..small_cfg(seed)
+            };
+            let guided_cfg = NeighCfg {
+                guided_mutation: true,
+                ..cfg.clone()
+            };
+            plain_sum += combined_fitness(&NeighbourGA::<PeptideProblem>::new(cfg).run().best);
+            guided_sum +=
+                combined_fitness(&NeighbourGA::<PeptideProblem>::new(guided_cfg).run().best);
+        }
+        assert!(
+            guided_sum <= plain_sum,
+            "guided {} vs plain {}",
+            guided_sum / 6.0,
+            plain_sum / 6.0
+        );
+    }
+
+    #[test]
+    fn test_baldwinian_keeps_original_genome() {
+        let _guard = global_lock();
+        set_motif(7).unwrap();
+
+        let mut rng = StdRng::seed_from_u64(1);
+        let orig = PeptideProblem::random_individual(&mut rng);
+        let mut climbed = orig.clone();
+        hill_climb_optimize::<PeptideProblem>(&mut climbed, &NeighCfg::default());
+        assert_ne!(climbed, orig);
+
+        let mut lamarck = NeighbourGA::<PeptideProblem>::new(small_cfg(0));
+        let mut child = orig.clone();
+        assert_eq!(lamarck.local_search(&mut child), None);
+        assert_eq!(child, climbed);
+
+        let mut baldwin = NeighbourGA::<PeptideProblem>::new(NeighCfg {
+            local_search: LocalSearchMode::Baldwinian,
+            ..small_cfg(0)
+        });
+        let mut child = orig.clone();
+        let learned = baldwin.local_search(&mut child);
+        assert_eq!(child, orig);
+        assert_eq!(learned, Some(combined_fitness(&climbed)));
+        assert!(learned.unwrap() < combined_fitness(&orig));
+    }
+
+    #[test]
+    fn test_climb_modes_evaluation_counts() {
+        let _guard = global_lock();
+        set_motif(7).unwrap();
+
+        let mut rng = StdRng::seed_from_u64(4);
+        let starts: Vec<_> = (0..10)
+            .map(|_| PeptideProblem::random_individual(&mut rng))
+            .collect();
+        let climb = |cfg: &NeighCfg| {
+            let mut scored = 0;
+            let mut fitness = 0.0;
+            for start in &starts {
+                let mut seq = start.clone();
+                scored += hill_climb_optimize::<PeptideProblem>(&mut seq, cfg);
+                assert!(combined_fitness(&seq) <= combined_fitness(start));
+                fitness += combined_fitness(&seq);
+            }
+            (scored, fitness)
+        };
+
+        let (best_scored, best_fit) = climb(&NeighCfg::default());
+        let (first_scored, _) = climb(&NeighCfg {
+            climb_mode: ClimbMode::First,
+            ..NeighCfg::default()
+        });
+        let (stable_scored, stable_fit) = climb(&NeighCfg {
+            climb_until_stable: true,
+            ..NeighCfg::default()
+        });
+
+        // one best-improvement sweep scores every valid alternative once
+        assert!(best_scored <= 10 * 22 * 19);
+        assert!(first_scored < best_scored);
+        // further passes cost more and can only improve the result
+        assert!(stable_scored > best_scored);
+        assert!(stable_fit <= best_fit);
+    }
+
+    #[test]
+    fn test_zero_local_search_prob_disables_climbing() {
+        let _guard = global_lock();
+        set_motif(3).unwrap();
+
+        let mut ga = NeighbourGA::<PeptideProblem>::new(NeighCfg {
+            local_search_prob: 0.0,
+            ..small_cfg(2)
+        });
+        ga.run();
+        assert_eq!(ga.delta_evaluations(), 0);
+
+        // the defaults do climb
+        let mut ga = NeighbourGA::<PeptideProblem>::new(small_cfg(2));
+        ga.run();
+        assert!(ga.delta_evaluations() > 0);
+    }
+
+    #[test]
+    fn test_dedup_keeps_population_unique() {
+        let _guard = global_lock();
+        set_motif(1).unwrap();
+
+        let mut ga = NeighbourGA::<PeptideProblem>::new(NeighCfg {
+            pop_size: 12,
+            dedup: true,
+            ..small_cfg(3)
+        });
+        let res = ga.run();
+        assert_eq!(res.duplicates.len(), 5);
+        assert!(res.duplicates.iter().sum::<usize>() > 0);
+        // elitism only re-adds the elite when it is missing, so no repeats
+        let pop = &ga.population;
+        for i in 0..pop.len() {
+            assert!(!pop[..i].contains(&pop[i]), "{:?} repeated", pop[i]);
+        }
+    }
+
+    #[test]
+    fn test_immigrants_after_stagnation() {
+        let _guard = global_lock();
+        set_motif(1).unwrap();
+
+        // offspring are unfiltered copies, so nothing improves until the
+        // immigrants arrive
+        let mut ga = NeighbourGA::<PeptideProblem>::new(NeighCfg {
+            crossover_p: 0.0,
+            mutation_p: 0.0,
+            smart_xover: false,
+            constraint_mode: ConstraintMode::Penalty,
+            immigrants: Some(Immigrants {
+                stagnation_limit: 2,
+                fraction: 0.25,
+            }),
+            ..small_cfg(6)
+        });
+        let res = ga.run();
+        assert_eq!(res.immigrants.len(), 5);
+        assert_eq!(res.immigrants[..2], [0, 5]);
+    }
+
+    #[test]
+    fn test_run_top_n() {
+        let _guard = global_lock();
+        set_motif(4).unwrap();
+
+        let top = NeighbourGA::<PeptideProblem>::new(small_cfg(3)).run_top_n(3);
+        assert_eq!(top.len(), 3);
+        assert!(top[0].0 != top[1].0 && top[1].0 != top[2].0 && top[0].0 != top[2].0);
+        assert!(top.windows(2).all(|w| w[0].1 <= w[1].1));
+        // the top entry is at least as good as what `run` reports
+        let best = NeighbourGA::<PeptideProblem>::new(small_cfg(3)).run().best;
+        assert!(top[0].1 <= combined_fitness(&best));
+    }
+
+    #[test]
+    fn test_constraint_modes_on_invalid_child() {
+        let _guard = global_lock();
+        set_motif(5).unwrap();
+
+        // a run of five Leu and a Cys-Cys pair: three violations
+        let invalid = crate::peptide::parse_sequence("LLLLLAGCCGKE").unwrap();
+        let validity = ValidityConfig::default();
+        assert_eq!(PeptideProblem::violations(&invalid, &validity), 3);
+
+        let ga_with = |constraint_mode| {
+            NeighbourGA::<PeptideProblem>::new(NeighCfg {
+                constraint_mode,
+                ..small_cfg(2)
+            })
+        };
+
+        let mut ga = ga_with(ConstraintMode::Reject);
+        let mut child = invalid.clone();
+        assert!(ga.enforce_validity(&mut child));
+        assert!(PeptideProblem::is_valid(&child, &validity));
+        assert_eq!(ga.penalty(&invalid), 0.0);
+
+        // repair keeps most of the genetic material
+        let mut ga = ga_with(ConstraintMode::Repair);
+        let mut child = invalid.clone();
+        assert!(ga.enforce_validity(&mut child));
+        assert!(PeptideProblem::is_valid(&child, &validity));
+        let changed = child.iter().zip(&invalid).filter(|(a, b)| a != b).count();
+        assert!((1..=3).contains(&changed));
+
+        let mut ga = ga_with(ConstraintMode::Penalty);
+        let mut child = invalid.clone();
+        assert!(!ga.enforce_validity(&mut child));
+        assert_eq!(child, invalid);
+        assert_eq!(ga.penalty(&invalid), 15.0);
+    }
+
+    #[test]
+    fn test_best_breaks_ties_by_lowest_index() {
+        let _guard = global_lock();
+        set_motif(1).unwrap();
 
-fn mutate_inversion(seq: &mut [u8], rng: &mut ThreadRng) {
-    if seq.len() < 3 {
-        return;
+        let mut ga = NeighbourGA::<PeptideProblem>::new(small_cfg(1));
+        ga.fitness = vec![2.0, -1.0, 0.5, -1.0, -1.0];
+        assert_eq!(ga.best(), (1, -1.0));
+        ga.fitness = vec![-3.0; 5];
+        assert_eq!(ga.best(), (0, -3.0));
     }
-    let i = rng.gen_range(0..seq.len() - 1);
-    let j = rng.gen_range(i + 1..seq.len());
-    seq[i..=j].reverse();
 }
@@ -1,176 +1,1081 @@
-use crate::peptide::combined_fitness;
-use crate::peptide::PeptideProblem;
+use crate::crossover::CrossoverOp;
+use crate::hall_of_fame::HallOfFame;
+use crate::mutation::MutationSet;
+use crate::peptide::InitMode;
 use crate::problem::TSProblem;
-use rand::{rngs::StdRng, Rng, SeedableRng};
+use crate::util::best_index;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::SliceRandom;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha12Rng;
+#[cfg(feature = "rayon")]
+use rayon::prelude::*;
+#[cfg(feature = "serde")]
+use std::fs::File;
+#[cfg(feature = "serde")]
+use std::io::{self, BufReader, BufWriter, Write};
+#[cfg(feature = "serde")]
+use std::path::Path;
+use std::time::{Duration, Instant};
 
 #[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
 pub enum Crossover {
     SinglePoint,
+    TwoPoint,
     Uniform,
+    /// Partially-mapped crossover: a segment of the second parent plus the
+    /// first parent's genes, remapped through the segment so that values
+    /// are not doubled. Peptides may repeat residues, so a mapping that
+    /// cannot resolve keeps the first parent's gene instead of insisting on
+    /// a permutation.
+    Pmx,
 }
 
-pub struct GeneticAlgorithm {
+impl std::str::FromStr for Crossover {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "single-point" => Ok(Crossover::SinglePoint),
+            "two-point" => Ok(Crossover::TwoPoint),
+            "uniform" => Ok(Crossover::Uniform),
+            "pmx" => Ok(Crossover::Pmx),
+            _ => Err(format!(
+                "unknown crossover '{}' (expected single-point, two-point, uniform or pmx)",
+                s
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for Crossover {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Crossover::SinglePoint => "single-point",
+            Crossover::TwoPoint => "two-point",
+            Crossover::Uniform => "uniform",
+            Crossover::Pmx => "pmx",
+        };
+        f.write_str(name)
+    }
+}
+
+/// How parents are drawn from the population.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum Selection {
+    /// Best of `k` uniformly drawn individuals.
+    Tournament(usize),
+    /// Fitness-proportional; the minimisation objective is turned into
+    /// weights `max_fit - fit + ROULETTE_EPS`.
+    Roulette,
+    /// Linear in rank: the best of `n` individuals has weight `n`, the worst `1`,
+    /// regardless of how far apart their raw fitness values are.
+    Rank,
+    /// Stochastic universal sampling: roulette weights, but a whole batch of
+    /// parents is drawn in one spin with evenly spaced pointers, so each
+    /// individual is picked within one of its expected count.
+    Sus,
+    /// Softmax over negated fitness: individual `i` is drawn with probability
+    /// proportional to `exp(-f_i / T)`. The temperature starts at
+    /// `temperature` and is multiplied by `cooling` every generation, so
+    /// selection drifts from near-uniform towards always taking the best.
+    Boltzmann { temperature: f64, cooling: f64 },
+}
+
+impl Selection {
+    /// The selection used to breed generation `generation`: a cooled
+    /// Boltzmann temperature, every other variant unchanged. The temperature
+    /// bottoms out at `f64::MIN_POSITIVE` rather than underflowing to 0.
+    pub fn at_generation(self, generation: usize) -> Self {
+        match self {
+            Selection::Boltzmann {
+                temperature,
+                cooling,
+            } => {
+                assert!(temperature > 0.0, "Boltzmann temperature must be positive");
+                assert!(
+                    cooling > 0.0 && cooling <= 1.0,
+                    "cooling factor must be in (0, 1], got {}",
+                    cooling
+                );
+                Selection::Boltzmann {
+                    temperature: (temperature * cooling.powi(generation as i32))
+                        .max(f64::MIN_POSITIVE),
+                    cooling,
+                }
+            }
+            other => other,
+        }
+    }
+}
+
+/// Keeps the worst individual selectable and makes a flat population uniform.
+const ROULETTE_EPS: f64 = 1e-6;
+
+/// Parent sampler built once per generation from the population's fitness.
+enum Selector<'a> {
+    Tournament {
+        k: usize,
+        fitness: &'a [f64],
+    },
+    Roulette(WeightedIndex<f64>),
+    Rank {
+        order: Vec<usize>,
+        dist: WeightedIndex<usize>,
+    },
+    /// Parents left over from the last spin, handed out from the back.
+    Sus {
+        weights: Vec<f64>,
+        parents: Vec<usize>,
+    },
+}
+
+/// Roulette weights `max_fit - fit + ROULETTE_EPS` of a minimisation objective.
+fn roulette_weights(fitness: &[f64]) -> Vec<f64> {
+    let max_fit = fitness.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    fitness
+        .iter()
+        .map(|&f| max_fit - f + ROULETTE_EPS)
+        .collect()
+}
+
+/// Stochastic universal sampling: `n` indices chosen by evenly spaced
+/// pointers over the cumulative `weights`, from a single random offset.
+pub fn sus<R: Rng>(weights: &[f64], n: usize, rng: &mut R) -> Vec<usize> {
+    let total: f64 = weights.iter().sum();
+    let step = total / n as f64;
+    let mut pointer = rng.gen::<f64>() * step;
+    let mut picked = Vec::with_capacity(n);
+    let mut cumulative = 0.0;
+    for (i, &w) in weights.iter().enumerate() {
+        cumulative += w;
+        while picked.len() < n && pointer < cumulative {
+            picked.push(i);
+            pointer += step;
+        }
+    }
+    // rounding can leave the last pointer just past the end
+    while picked.len() < n {
+        picked.push(weights.len() - 1);
+    }
+    picked
+}
+
+impl<'a> Selector<'a> {
+    fn new(selection: Selection, fitness: &'a [f64]) -> Self {
+        match selection {
+            Selection::Tournament(k) => Selector::Tournament { k, fitness },
+            Selection::Roulette => {
+                Selector::Roulette(WeightedIndex::new(roulette_weights(fitness)).unwrap())
+            }
+            Selection::Rank => {
+                // best first; weight of rank r (0-based) is n - r
+                let mut order: Vec<usize> = (0..fitness.len()).collect();
+                order.sort_by(|&a, &b| fitness[a].partial_cmp(&fitness[b]).unwrap());
+                let n = order.len();
+                let dist = WeightedIndex::new((0..n).map(|r| n - r)).unwrap();
+                Selector::Rank { order, dist }
+            }
+            Selection::Sus => Selector::Sus {
+                weights: roulette_weights(fitness),
+                parents: Vec::new(),
+            },
+            Selection::Boltzmann { temperature, .. } => {
+                // shifted by the best fitness so the best weighs exactly 1 and
+                // a cold temperature underflows the others to 0, never all
+                let min_fit = fitness.iter().copied().fold(f64::INFINITY, f64::min);
+                let weights = fitness
+                    .iter()
+                    .map(|&f| (-(f - min_fit) / temperature).exp());
+                Selector::Roulette(WeightedIndex::new(weights).unwrap())
+            }
+        }
+    }
+
+    /// Index of the selected parent.
+    fn pick<R: Rng>(&mut self, rng: &mut R) -> usize {
+        match self {
+            Selector::Tournament { k, fitness } => {
+                let mut best = rng.gen_range(0..fitness.len());
+                for _ in 1..*k {
+                    let idx = rng.gen_range(0..fitness.len());
+                    if fitness[idx] < fitness[best] {
+                        best = idx;
+                    }
+                }
+                best
+            }
+            Selector::Roulette(dist) => dist.sample(rng),
+            Selector::Rank { order, dist } => order[dist.sample(rng)],
+            Selector::Sus { weights, parents } => {
+                if parents.is_empty() {
+                    // one spin per population's worth of parents, shuffled so
+                    // that consecutive picks are not neighbours on the wheel
+                    *parents = sus(weights, weights.len(), rng);
+                    parents.shuffle(rng);
+                }
+                parents.pop().unwrap()
+            }
+        }
+    }
+}
+
+/// Raises the mutation rate while the population lacks diversity.
+///
+/// Diversity is the mean pairwise `TSProblem::distance` over a sample of the
+/// population; for peptides the Hamming distance divided by sequence length
+/// (0 = clones, 1 = all positions differ).
+/// Below `threshold` mutation runs at `high`, otherwise at `low`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct AdaptiveMutation {
+    pub low: f64,
+    pub high: f64,
+    pub threshold: f64,
+}
+
+/// Upper bound on individuals used to estimate diversity.
+const DIVERSITY_SAMPLE: usize = 32;
+
+/// Mean pairwise `P::distance` over an evenly strided sample.
+pub fn diversity<P: TSProblem>(population: &[P::Individ]) -> f64 {
+    let step = population.len().div_ceil(DIVERSITY_SAMPLE).max(1);
+    let sample: Vec<&P::Individ> = population.iter().step_by(step).collect();
+    if sample.len() < 2 {
+        return 0.0;
+    }
+
+    let mut total = 0.0;
+    let mut pairs = 0usize;
+    for i in 0..sample.len() {
+        for j in i + 1..sample.len() {
+            total += P::distance(sample[i], sample[j]);
+            pairs += 1;
+        }
+    }
+    total / pairs as f64
+}
+
+/// Fitness sharing (niching): an individual's selection fitness is worsened
+/// by the number of others around it, so that crowded optima lose out to
+/// sparsely populated ones and several optima can be held at once.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FitnessSharing {
+    /// Niche radius in units of `TSProblem::distance`; for peptides the
+    /// fraction of positions that differ.
+    pub sigma_share: f64,
+    /// Shape of the sharing function `1 - (d / sigma_share)^alpha`.
+    pub alpha: f64,
+}
+
+/// Niche count of every individual: the sharing function summed over the
+/// whole population, so at least `1` from the individual itself.
+pub fn niche_counts<P: TSProblem>(population: &[P::Individ], sharing: FitnessSharing) -> Vec<f64> {
+    population
+        .iter()
+        .map(|a| {
+            population
+                .iter()
+                .map(|b| P::distance(a, b))
+                .filter(|&d| d < sharing.sigma_share)
+                .map(|d| 1.0 - (d / sharing.sigma_share).powf(sharing.alpha))
+                .sum()
+        })
+        .collect()
+}
+
+/// Fitness as seen by selection under sharing. The minimisation objective
+/// is turned into the weight `max_fit - fit + ROULETTE_EPS`, divided by the
+/// niche count and turned back, so lower is still better.
+pub fn shared_fitness<P: TSProblem>(
+    population: &[P::Individ],
+    fitness: &[f64],
+    sharing: FitnessSharing,
+) -> Vec<f64> {
+    let max_fit = fitness.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    roulette_weights(fitness)
+        .iter()
+        .zip(niche_counts::<P>(population, sharing))
+        .map(|(w, m)| max_fit - w / m)
+        .collect()
+}
+
+/// Greedy clustering: the best individual not yet assigned leads a new
+/// cluster that takes in every unassigned individual within `radius` of it.
+/// Returns each cluster's leader and size, best leader first.
+pub fn clusters<P: TSProblem>(
+    population: &[P::Individ],
+    fitness: &[f64],
+    radius: f64,
+) -> Vec<(P::Individ, usize)> {
+    let mut order: Vec<usize> = (0..population.len()).collect();
+    order.sort_by(|&a, &b| fitness[a].partial_cmp(&fitness[b]).unwrap());
+
+    let mut assigned = vec![false; population.len()];
+    let mut found = Vec::new();
+    for &leader in &order {
+        if assigned[leader] {
+            continue;
+        }
+        let mut size = 0;
+        for &i in &order {
+            if !assigned[i] && P::distance(&population[leader], &population[i]) < radius {
+                assigned[i] = true;
+                size += 1;
+            }
+        }
+        found.push((population[leader].clone(), size));
+    }
+    found
+}
+
+/// Random immigrants: once the best-ever fitness has not improved for
+/// `stagnation_limit` generations, the worst `fraction` of the population is
+/// replaced by fresh individuals and the count starts over. Cheap
+/// diversity for runs that converge prematurely.
+#[derive(Debug, Clone, Copy, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct Immigrants {
+    pub stagnation_limit: usize,
+    pub fraction: f64,
+}
+
+impl Immigrants {
+    /// Individuals replaced per injection: `fraction` of the population,
+    /// at least one, and never all of it so the best survives.
+    pub fn count(&self, pop_size: usize) -> usize {
+        let n = (self.fraction * pop_size as f64).round() as usize;
+        n.min(pop_size.saturating_sub(1)).max(1)
+    }
+}
+
+/// Indices of the `n` individuals with the highest (worst) fitness.
+pub(crate) fn worst_indices<F: PartialOrd>(fitness: &[F], n: usize) -> Vec<usize> {
+    let mut order: Vec<usize> = (0..fitness.len()).collect();
+    order.sort_by(|&a, &b| fitness[b].partial_cmp(&fitness[a]).unwrap());
+    order.truncate(n);
+    order
+}
+
+/// Fresh draws tried per duplicate before one is kept even if repeated.
+const DEDUP_ATTEMPTS: usize = 100;
+
+/// Replaces every individual equal to an earlier one by a `fresh` draw
+/// that differs from all earlier ones; returns the replaced indices.
+pub fn dedup<I: PartialEq, R: Rng>(
+    population: &mut [I],
+    rng: &mut R,
+    mut fresh: impl FnMut(&mut R) -> I,
+) -> Vec<usize> {
+    let mut replaced = Vec::new();
+    for i in 1..population.len() {
+        let (seen, rest) = population.split_at_mut(i);
+        if !seen.contains(&rest[0]) {
+            continue;
+        }
+        for _ in 0..DEDUP_ATTEMPTS {
+            rest[0] = fresh(rng);
+            if !seen.contains(&rest[0]) {
+                break;
+            }
+        }
+        replaced.push(i);
+    }
+    replaced
+}
+
+/// Fitness of every individual, in parallel with the `rayon` feature.
+fn evaluate<P: TSProblem>(population: &[P::Individ]) -> Vec<f64>
+where
+    P::Individ: Send + Sync,
+{
+    #[cfg(feature = "rayon")]
+    let iter = population.par_iter();
+    #[cfg(not(feature = "rayon"))]
+    let iter = population.iter();
+
+    iter.map(P::fitness).collect()
+}
+
+/// Child of two sequences under the given crossover scheme; the first of
+/// `recombine_pair`.
+pub fn recombine<T: Copy + PartialEq, R: Rng + ?Sized>(
+    parent1: &[T],
+    parent2: &[T],
+    kind: Crossover,
+    rng: &mut R,
+) -> Vec<T> {
+    recombine_pair(parent1, parent2, kind, rng).0
+}
+
+/// Both children of two sequences under the given crossover scheme: the
+/// first mostly `parent1`, the second its complement from the same cut
+/// points (or coin flips); the operator behind `Crossover`'s `CrossoverOp`.
+pub fn recombine_pair<T: Copy + PartialEq, R: Rng + ?Sized>(
+    parent1: &[T],
+    parent2: &[T],
+    kind: Crossover,
+    rng: &mut R,
+) -> (Vec<T>, Vec<T>) {
+    let len = parent1.len().min(parent2.len());
+    match kind {
+        Crossover::TwoPoint if len >= 3 => {
+            // Two point crossover: parent2 supplies the segment [i, j)
+            let i = rng.gen_range(1..len - 1);
+            let j = rng.gen_range(i + 1..len);
+            let mut child1 = parent1.to_vec();
+            let mut child2 = parent2.to_vec();
+            child1[i..j].copy_from_slice(&parent2[i..j]);
+            child2[i..j].copy_from_slice(&parent1[i..j]);
+            (child1, child2)
+        }
+        Crossover::Pmx if len >= 3 => {
+            let i = rng.gen_range(1..len - 1);
+            let j = rng.gen_range(i + 1..len);
+            (
+                pmx(&parent1[..len], &parent2[..len], i, j),
+                pmx(&parent2[..len], &parent1[..len], i, j),
+            )
+        }
+        Crossover::SinglePoint | Crossover::TwoPoint | Crossover::Pmx => {
+            // Single point crossover
+            let point = rng.gen_range(1..len);
+            let mut child1 = parent1[..point].to_vec();
+            child1.extend_from_slice(&parent2[point..]);
+            let mut child2 = parent2[..point].to_vec();
+            child2.extend_from_slice(&parent1[point..]);
+            (child1, child2)
+        }
+        Crossover::Uniform => {
+            // Uniform crossover with p=0.5
+            let mut child1 = Vec::with_capacity(len);
+            let mut child2 = Vec::with_capacity(len);
+            for i in 0..len {
+                if rng.gen::<f64>() < 0.5 {
+                    child1.push(parent1[i]);
+                    child2.push(parent2[i]);
+                } else {
+                    child1.push(parent2[i]);
+                    child2.push(parent1[i]);
+                }
+            }
+            (child1, child2)
+        }
+    }
+}
+
+/// PMX child of two equally long parents with `parent2[i..j]` as the
+/// mapping segment.
+fn pmx<T: Copy + PartialEq>(parent1: &[T], parent2: &[T], i: usize, j: usize) -> Vec<T> {
+    let mut child = parent1.to_vec();
+    child[i..j].copy_from_slice(&parent2[i..j]);
+    for k in (0..i).chain(j..parent1.len()) {
+        // follow value -> parent1 at the same segment position until the
+        // value is no longer taken; a permutation needs at most j - i hops
+        let mut v = parent1[k];
+        let mut hops = 0;
+        while let Some(m) = (i..j).find(|&m| parent2[m] == v) {
+            if hops == j - i {
+                // repeated values can map in a circle
+                v = parent1[k];
+                break;
+            }
+            v = parent1[m];
+            hops += 1;
+        }
+        child[k] = v;
+    }
+    child
+}
+
+/// Called once per generation with its index, the best fitness and the best
+/// individual (a sequence unless stated otherwise). Lets callers log, draw
+/// progress bars or record state without the library depending on a UI
+/// crate. The closure must not panic: it runs inside the optimisation loop
+/// and a panic aborts the whole run.
+pub type GenerationCallback<I = [u8]> = Box<dyn FnMut(usize, f32, &I) + Send>;
+
+/// Called once per generation with its index and the whole population, e.g.
+/// to track `diversity::position_entropy`. Must not panic either.
+pub type PopulationCallback<I = Vec<u8>> = Box<dyn FnMut(usize, &[I]) + Send>;
+
+/// Per-generation statistics: (generation, min, max, avg) fitness and the
+/// mutation probability used to breed that generation.
+pub type GenStats = (usize, f64, f64, f64, f64);
+
+/// Outcome of `GeneticAlgorithm::run`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GaResult<I = Vec<u8>> {
+    /// Best individual seen over the whole run, which can be better than
+    /// anything left in the final population.
+    pub best: I,
+    pub progress: Vec<GenStats>,
+    /// Best individual of every generation.
+    pub best_per_gen: Vec<I>,
+    /// First generation whose best individual matched the fitness of `best`.
+    pub best_found_at: usize,
+    /// Number of `P::fitness` calls made by the run.
+    pub evaluations: u64,
+    /// Last generation run when early stopping or the time budget ended the
+    /// run, `None` if it ran in full.
+    pub stopped_at: Option<usize>,
+    /// Offspring replaced by `dedup` in every generation (all zero when it
+    /// is off).
+    pub duplicates: Vec<usize>,
+    /// Random immigrants injected after every generation (all zero without
+    /// `immigrants`).
+    pub immigrants: Vec<usize>,
+    /// Clusters of the final population within `sigma_share`, see
+    /// `clusters`; empty without fitness sharing.
+    pub clusters: Vec<(I, usize)>,
+    /// Best distinct individuals of the run with their fitness, best first.
+    pub hall_of_fame: Vec<(I, f64)>,
+    /// The last generation.
+    pub population: Vec<I>,
+}
+
+/// Mid-run state of a `GeneticAlgorithm`: everything `step` needs to carry
+/// on, so a run can be checkpointed to disk and resumed with the same
+/// trajectory.
+#[derive(Debug, Clone)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "I: serde::Serialize",
+        deserialize = "I: serde::de::DeserializeOwned"
+    ))
+)]
+pub struct GaState<I = Vec<u8>> {
+    /// Index of the next generation.
+    pub generation: usize,
+    pub population: Vec<I>,
+    /// Fitness of `population`, index for index.
+    pub(crate) fitnesses: Vec<f64>,
+    rng: ChaCha12Rng,
+    progress: Vec<GenStats>,
+    best_per_gen: Vec<I>,
+    evaluations: u64,
+    best_so_far: f64,
+    stale: usize,
+    stopped_at: Option<usize>,
+    duplicates: Vec<usize>,
+    /// Generations without improvement since the last immigrants arrived.
+    #[cfg_attr(feature = "serde", serde(default))]
+    immigrant_stale: usize,
+    #[cfg_attr(feature = "serde", serde(default))]
+    immigrants: Vec<usize>,
+    hall_of_fame: HallOfFame<I>,
+    done: bool,
+    /// When the time budget started counting; restarts on resume.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    started: Option<Instant>,
+}
+
+impl<I> GaState<I> {
+    /// Whether `GeneticAlgorithm::step` has ended the run.
+    pub fn is_done(&self) -> bool {
+        self.done
+    }
+}
+
+/// Generational GA over any `TSProblem`, which supplies the initial
+/// individuals, fitness, crossover and mutation.
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(bound = ""))]
+pub struct GeneticAlgorithm<P: TSProblem> {
     pub population_size: usize,
     pub generations: usize,
     pub crossover_prob: f64,
-    pub crossover: Crossover,
+    /// Recombination operator, a `Crossover` scheme unless a custom
+    /// `CrossoverOp` is plugged in; reports record its name.
+    #[cfg_attr(feature = "serde", serde(serialize_with = "serialize_op_name"))]
+    pub crossover: Box<dyn CrossoverOp>,
     pub mutation_prob: f64,
-    pub tournament_size: usize,
+    /// Explicit mutation operators, each fired with its own probability on
+    /// every offspring (`TSProblem::mutate_with`). `mutation_prob` and
+    /// `adaptive_mutation` then no longer apply; `None` mutates with
+    /// `TSProblem::mutate` at that rate.
+    pub mutations: Option<MutationSet>,
+    pub selection: Selection,
+    /// When set, overrides `mutation_prob` with a diversity-driven rate.
+    pub adaptive_mutation: Option<AdaptiveMutation>,
+    /// Stop after this many consecutive generations without the best fitness
+    /// improving by more than `min_improvement`; `0` disables early stopping.
+    pub patience: usize,
+    pub min_improvement: f64,
+    /// Wall-clock limit checked before each generation; the best so far is
+    /// returned once it is exceeded.
+    pub time_budget: Option<Duration>,
+    /// How the initial population is drawn.
+    pub init: InitMode,
+    /// Replace repeated offspring by fresh individuals before they are
+    /// scored (see `dedup`).
+    pub dedup: bool,
+    /// Select on shared rather than raw fitness (see `FitnessSharing`).
+    pub sharing: Option<FitnessSharing>,
+    /// Replace the worst individuals on stagnation (see `Immigrants`).
+    pub immigrants: Option<Immigrants>,
+    /// Capacity of the run's `HallOfFame`; at least one entry is always kept.
+    pub hall_of_fame: usize,
+    /// Invoked after every generation, see `GenerationCallback`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub on_generation: Option<GenerationCallback<P::Individ>>,
+    /// Invoked after every generation, see `PopulationCallback`.
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub on_population: Option<PopulationCallback<P::Individ>>,
 }
 
-impl GeneticAlgorithm {
-    pub fn run(&self, seed: u64) -> (Vec<u8>, Vec<(usize, f64, f64, f64)>) {
-        let mut rng = StdRng::seed_from_u64(seed);
-        let mut population = self.initialize_population(&mut rng);
-        let mut progress: Vec<(usize, f64, f64, f64)> = Vec::new();
-
-        for i in 0..self.generations {
-            population = self.evolve(&population, &mut rng);
+impl<P: TSProblem> GeneticAlgorithm<P> {
+    /// Builder starting from the command-line defaults.
+    pub fn builder() -> GeneticAlgorithmBuilder<P> {
+        GeneticAlgorithmBuilder::new()
+    }
+}
 
-            let fitnesses: Vec<f64> = population
-                .iter()
-                .map(|ind| combined_fitness(ind) as f64)
-                .collect();
+/// Chainable construction of a `GeneticAlgorithm`; unset fields keep the
+/// command-line defaults (population 400, 200 generations, single-point
+/// crossover at 0.9, mutation 0.3, tournaments of 3, no early stopping).
+pub struct GeneticAlgorithmBuilder<P: TSProblem> {
+    ga: GeneticAlgorithm<P>,
+}
 
-            let min = *fitnesses
-                .iter()
-                .min_by(|a, b| a.partial_cmp(b).unwrap())
-                .unwrap();
-            let max = *fitnesses
-                .iter()
-                .max_by(|a, b| a.partial_cmp(b).unwrap())
-                .unwrap();
-            let avg = fitnesses.iter().sum::<f64>() / fitnesses.len() as f64;
+impl<P: TSProblem> Default for GeneticAlgorithmBuilder<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
-            progress.push((i, min, max, avg));
+impl<P: TSProblem> GeneticAlgorithmBuilder<P> {
+    pub fn new() -> Self {
+        Self {
+            ga: GeneticAlgorithm {
+                population_size: 400,
+                generations: 200,
+                crossover_prob: 0.9,
+                crossover: Box::new(Crossover::SinglePoint),
+                mutation_prob: 0.3,
+                mutations: None,
+                selection: Selection::Tournament(3),
+                adaptive_mutation: None,
+                patience: 0,
+                min_improvement: 1e-6,
+                time_budget: None,
+                init: InitMode::Uniform,
+                dedup: false,
+                sharing: None,
+                immigrants: None,
+                hall_of_fame: 10,
+                on_generation: None,
+                on_population: None,
+            },
         }
+    }
 
-        let best = self.get_best_solution(&population);
-        (best, progress)
+    pub fn population_size(mut self, population_size: usize) -> Self {
+        self.ga.population_size = population_size;
+        self
     }
 
-    fn initialize_population<R: Rng>(&self, rng: &mut R) -> Vec<Vec<u8>> {
-        (0..self.population_size)
-            .map(|_| PeptideProblem::random_individual(rng))
-            .collect()
+    pub fn generations(mut self, generations: usize) -> Self {
+        self.ga.generations = generations;
+        self
     }
 
-    fn evolve<R: Rng>(&self, population: &Vec<Vec<u8>>, rng: &mut R) -> Vec<Vec<u8>> {
-        let mut new_population = Vec::new();
+    pub fn crossover_prob(mut self, crossover_prob: f64) -> Self {
+        self.ga.crossover_prob = crossover_prob;
+        self
+    }
 
-        while new_population.len() < self.population_size {
-            let parent1 = self.tournament_selection(population, rng);
-            let parent2 = self.tournament_selection(population, rng);
-            let mut offspring = self.crossover(&parent1, &parent2, rng);
-            self.mutate(&mut offspring, rng);
-            new_population.push(offspring);
-        }
+    /// A `Crossover` scheme or any other `CrossoverOp`.
+    pub fn crossover(mut self, crossover: impl CrossoverOp + 'static) -> Self {
+        self.ga.crossover = Box::new(crossover);
+        self
+    }
 
-        new_population
+    pub fn mutation_prob(mut self, mutation_prob: f64) -> Self {
+        self.ga.mutation_prob = mutation_prob;
+        self
+    }
+
+    pub fn mutations(mut self, mutations: MutationSet) -> Self {
+        self.ga.mutations = Some(mutations);
+        self
+    }
+
+    pub fn selection(mut self, selection: Selection) -> Self {
+        self.ga.selection = selection;
+        self
+    }
+
+    pub fn adaptive_mutation(mut self, adaptive_mutation: Option<AdaptiveMutation>) -> Self {
+        self.ga.adaptive_mutation = adaptive_mutation;
+        self
+    }
+
+    /// Early stopping, see `GeneticAlgorithm::patience`.
+    pub fn patience(mut self, patience: usize, min_improvement: f64) -> Self {
+        self.ga.patience = patience;
+        self.ga.min_improvement = min_improvement;
+        self
+    }
+
+    pub fn time_budget(mut self, time_budget: Option<Duration>) -> Self {
+        self.ga.time_budget = time_budget;
+        self
+    }
+
+    pub fn init(mut self, init: InitMode) -> Self {
+        self.ga.init = init;
+        self
+    }
+
+    pub fn dedup(mut self, dedup: bool) -> Self {
+        self.ga.dedup = dedup;
+        self
+    }
+
+    pub fn sharing(mut self, sharing: Option<FitnessSharing>) -> Self {
+        self.ga.sharing = sharing;
+        self
+    }
+
+    pub fn immigrants(mut self, immigrants: Option<Immigrants>) -> Self {
+        self.ga.immigrants = immigrants;
+        self
+    }
+
+    pub fn hall_of_fame(mut self, capacity: usize) -> Self {
+        self.ga.hall_of_fame = capacity;
+        self
+    }
+
+    pub fn on_generation(mut self, cb: GenerationCallback<P::Individ>) -> Self {
+        self.ga.on_generation = Some(cb);
+        self
+    }
+
+    pub fn on_population(mut self, cb: PopulationCallback<P::Individ>) -> Self {
+        self.ga.on_population = Some(cb);
+        self
+    }
+
+    pub fn build(self) -> GeneticAlgorithm<P> {
+        self.ga
+    }
+}
+
+impl<P: TSProblem> GeneticAlgorithm<P>
+where
+    P::Individ: Send + Sync,
+{
+    pub fn run(&mut self, seed: u64) -> GaResult<P::Individ> {
+        let mut state = self.start(seed);
+        while self.step(&mut state) {}
+        self.finish(state)
+    }
+
+    /// The `n` best distinct individuals seen over a whole run, with their
+    /// fitness and sorted best first; fewer if the run saw fewer.
+    pub fn run_top_n(&mut self, seed: u64, n: usize) -> Vec<(P::Individ, f32)> {
+        let capacity = self.hall_of_fame;
+        self.hall_of_fame = n;
+        let result = self.run(seed);
+        self.hall_of_fame = capacity;
+        result
+            .hall_of_fame
+            .into_iter()
+            .take(n)
+            .map(|(ind, f)| (ind, f as f32))
+            .collect()
     }
 
-    fn tournament_selection<R: Rng>(&self, population: &Vec<Vec<u8>>, rng: &mut R) -> Vec<u8> {
-        let mut tournament = Vec::new();
+    /// Initial population of a run; drive it with `step` and `finish`.
+    pub fn start(&self, seed: u64) -> GaState<P::Individ> {
+        let mut rng = ChaCha12Rng::seed_from_u64(seed);
+        let population = self.initialize_population(&mut rng);
+        let fitnesses = evaluate::<P>(&population);
+        let mut hall_of_fame = HallOfFame::new(self.hall_of_fame.max(1));
+        hall_of_fame.update(&population, &fitnesses);
+        GaState {
+            generation: 0,
+            evaluations: fitnesses.len() as u64,
+            best_so_far: fitnesses.iter().copied().fold(f64::INFINITY, f64::min),
+            population,
+            fitnesses,
+            rng,
+            progress: Vec::new(),
+            best_per_gen: Vec::new(),
+            stale: 0,
+            stopped_at: None,
+            duplicates: Vec::new(),
+            immigrant_stale: 0,
+            immigrants: Vec::new(),
+            hall_of_fame,
+            done: false,
+            started: None,
+        }
+    }
 
-        for _ in 0..self.tournament_size {
-            let idx = rng.gen_range(0..population.len());
-            tournament.push(&population[idx]);
+    /// Runs one generation; `false` once the run is over (all generations
+    /// done, early stopping or the time budget), after which `state` no
+    /// longer changes.
+    pub fn step(&mut self, state: &mut GaState<P::Individ>) -> bool {
+        let i = state.generation;
+        if state.done || i >= self.generations {
+            state.done = true;
+            return false;
+        }
+        let start = *state.started.get_or_insert_with(Instant::now);
+        if self.time_budget.is_some_and(|b| start.elapsed() >= b) {
+            state.stopped_at = i.checked_sub(1);
+            state.done = true;
+            return false;
         }
 
-        let best = tournament
-            .iter()
-            .min_by(|a, b| {
-                PeptideProblem::fitness(a)
-                    .partial_cmp(&(combined_fitness(b) as f64))
-                    .unwrap()
+        let mutation_prob = self.mutation_rate(&state.population);
+        state.population = self.evolve(
+            i,
+            &state.population,
+            &state.fitnesses,
+            mutation_prob,
+            &mut state.rng,
+        );
+        let duplicates = if self.dedup {
+            dedup(&mut state.population, &mut state.rng, |rng| {
+                P::initial_individual(rng, self.init)
             })
+            .len()
+        } else {
+            0
+        };
+        state.duplicates.push(duplicates);
+        state.fitnesses = evaluate::<P>(&state.population);
+        state.evaluations += state.fitnesses.len() as u64;
+        state
+            .hall_of_fame
+            .update(&state.population, &state.fitnesses);
+        state.generation += 1;
+
+        let fitnesses = &state.fitnesses;
+        let (min_idx, min) = best_index(fitnesses).unwrap();
+        let max = *fitnesses
+            .iter()
+            .max_by(|a, b| a.partial_cmp(b).unwrap())
             .unwrap();
+        let avg = fitnesses.iter().sum::<f64>() / fitnesses.len() as f64;
 
-        (*best).clone()
-    }
+        state.progress.push((i, min, max, avg, mutation_prob));
+        state.best_per_gen.push(state.population[min_idx].clone());
+        if let Some(cb) = self.on_generation.as_mut() {
+            cb(i, min as f32, &state.population[min_idx]);
+        }
+        if let Some(cb) = self.on_population.as_mut() {
+            cb(i, &state.population);
+        }
 
-    fn crossover<R: Rng>(&self, parent1: &Vec<u8>, parent2: &Vec<u8>, rng: &mut R) -> Vec<u8> {
-        if rng.gen::<f64>() < self.crossover_prob {
-            match self.crossover {
-                Crossover::SinglePoint => {
-                    // Single point crossover
-                    let point = rng.gen_range(1..parent1.len().min(parent2.len()));
-                    let mut child = parent1[..point].to_vec();
-                    child.extend_from_slice(&parent2[point.min(parent2.len())..]);
-                    child
-                }
-                Crossover::Uniform => {
-                    // Uniform crossover with p=0.5
-                    let mut child = Vec::with_capacity(parent1.len());
-                    for i in 0..parent1.len().min(parent2.len()) {
-                        if rng.gen::<f64>() < 0.5 {
-                            child.push(parent1[i]);
-                        } else {
-                            child.push(parent2[i]);
-                        }
-                    }
-                    child
+        // early stopping on a plateau of the best-ever fitness
+        let improved = min < state.best_so_far - self.min_improvement;
+        if improved {
+            state.stale = 0;
+        } else {
+            state.stale += 1;
+        }
+        state.best_so_far = state.best_so_far.min(min);
+        if self.patience > 0 && state.stale >= self.patience {
+            state.stopped_at = Some(i);
+            state.done = true;
+        }
+
+        let injected = match self.immigrants {
+            Some(im) if !state.done => {
+                state.immigrant_stale = if improved {
+                    0
+                } else {
+                    state.immigrant_stale + 1
+                };
+                if state.immigrant_stale >= im.stagnation_limit {
+                    state.immigrant_stale = 0;
+                    self.inject_immigrants(state, im)
+                } else {
+                    0
                 }
             }
-        } else {
-            parent1.clone()
+            _ => 0,
+        };
+        state.immigrants.push(injected);
+        !state.done
+    }
+
+    /// Replaces the worst individuals of `state` by fresh ones; returns how
+    /// many.
+    fn inject_immigrants(&self, state: &mut GaState<P::Individ>, im: Immigrants) -> usize {
+        let worst = worst_indices(&state.fitnesses, im.count(state.population.len()));
+        for &idx in &worst {
+            let fresh = P::initial_individual(&mut state.rng, self.init);
+            state.fitnesses[idx] = P::fitness(&fresh);
+            state.population[idx] = fresh;
+        }
+        state.evaluations += worst.len() as u64;
+        state
+            .hall_of_fame
+            .update(&state.population, &state.fitnesses);
+        worst.len()
+    }
+
+    /// Result of a run that `step` has finished.
+    pub fn finish(&self, state: GaState<P::Individ>) -> GaResult<P::Individ> {
+        let (best, best_f) = state.hall_of_fame.best().cloned().unwrap();
+        let best_found_at = state
+            .progress
+            .iter()
+            .position(|p| p.1 <= best_f)
+            .unwrap_or(0);
+        GaResult {
+            best,
+            progress: state.progress,
+            best_per_gen: state.best_per_gen,
+            best_found_at,
+            evaluations: state.evaluations,
+            stopped_at: state.stopped_at,
+            duplicates: state.duplicates,
+            immigrants: state.immigrants,
+            clusters: self.sharing.map_or_else(Vec::new, |sh| {
+                clusters::<P>(&state.population, &state.fitnesses, sh.sigma_share)
+            }),
+            hall_of_fame: state.hall_of_fame.into_vec(),
+            population: state.population,
         }
     }
 
-    fn mutate<R: Rng>(&self, individual: &mut Vec<u8>, rng: &mut R) {
-        if rng.gen::<f64>() < self.mutation_prob {
-            // Use one of the mutation operations randomly (only fixed-length operations)
-            let r: f64 = rng.gen();
+    fn initialize_population<R: Rng>(&self, rng: &mut R) -> Vec<P::Individ> {
+        (0..self.population_size)
+            .map(|_| P::initial_individual(rng, self.init))
+            .collect()
+    }
 
-            if r < 0.7 {
-                // Substitution mutation
-                let pos = rng.gen_range(0..individual.len());
-                let old = individual[pos];
-                let mut new = rng.gen_range(0..20) as u8;
-                while new == old {
-                    new = rng.gen_range(0..20) as u8;
-                }
-                individual[pos] = new;
-            } else if individual.len() >= 2 {
-                // Swap mutation
-                let p1 = rng.gen_range(0..individual.len());
-                let mut p2 = rng.gen_range(0..individual.len());
-                while p2 == p1 {
-                    p2 = rng.gen_range(0..individual.len());
-                }
-                individual.swap(p1, p2);
+    fn mutation_rate(&self, population: &[P::Individ]) -> f64 {
+        match self.adaptive_mutation {
+            Some(am) if diversity::<P>(population) < am.threshold => am.high,
+            Some(am) => am.low,
+            None => self.mutation_prob,
+        }
+    }
+
+    fn evolve<R: Rng>(
+        &self,
+        generation: usize,
+        population: &[P::Individ],
+        fitness: &[f64],
+        mutation_prob: f64,
+        rng: &mut R,
+    ) -> Vec<P::Individ> {
+        let shared;
+        let fitness = match self.sharing {
+            Some(sharing) => {
+                shared = shared_fitness::<P>(population, fitness, sharing);
+                &shared
             }
+            None => fitness,
+        };
+        let mut new_population = Vec::new();
+        let mut selector = Selector::new(self.selection.at_generation(generation), fitness);
+
+        while new_population.len() < self.population_size {
+            let parent1 = &population[selector.pick(rng)];
+            let parent2 = &population[selector.pick(rng)];
+            let mut offspring = self.crossover(parent1, parent2, rng);
+            self.mutate(&mut offspring, mutation_prob, rng);
+            new_population.push(offspring);
         }
+
+        new_population
     }
 
-    fn get_best_solution(&self, population: &Vec<Vec<u8>>) -> Vec<u8> {
-        population
-            .iter()
-            .min_by(|a, b| {
-                (PeptideProblem::fitness(a) as f64)
-                    .partial_cmp(&(combined_fitness(b) as f64))
-                    .unwrap()
-            })
-            .unwrap()
-            .clone()
+    fn crossover<R: Rng>(
+        &self,
+        parent1: &P::Individ,
+        parent2: &P::Individ,
+        rng: &mut R,
+    ) -> P::Individ {
+        if rng.gen::<f64>() < self.crossover_prob {
+            P::crossover(parent1, parent2, self.crossover.as_ref(), rng)
+        } else {
+            parent1.clone()
+        }
+    }
+
+    fn mutate<R: Rng>(&self, individual: &mut P::Individ, mutation_prob: f64, rng: &mut R) {
+        if let Some(ops) = &self.mutations {
+            P::mutate_with(individual, ops, rng);
+        } else if rng.gen::<f64>() < mutation_prob {
+            P::mutate(individual, rng);
+        }
+    }
+}
+
+// `serialize_with` hands over a reference to the field as declared
+#[cfg(feature = "serde")]
+#[allow(clippy::borrowed_box)]
+fn serialize_op_name<S: serde::Serializer>(
+    op: &Box<dyn CrossoverOp>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(op.name())
+}
+
+#[cfg(feature = "serde")]
+impl<P: TSProblem> GeneticAlgorithm<P>
+where
+    P::Individ: serde::Serialize + serde::de::DeserializeOwned,
+{
+    /// Writes `state` to `path` as JSON. The file is replaced atomically, so
+    /// a crash mid-write keeps the previous checkpoint.
+    pub fn save_checkpoint(&self, state: &GaState<P::Individ>, path: &Path) -> io::Result<()> {
+        let tmp = path.with_extension("tmp");
+        let mut out = BufWriter::new(File::create(&tmp)?);
+        serde_json::to_writer(&mut out, state)?;
+        out.flush()?;
+        drop(out);
+        std::fs::rename(tmp, path)
+    }
+
+    /// Reads a state written by `save_checkpoint`; continue it with `step`.
+    pub fn resume_from_checkpoint(&self, path: &Path) -> io::Result<GaState<P::Individ>> {
+        let state: GaState<P::Individ> =
+            serde_json::from_reader(BufReader::new(File::open(path)?))?;
+        if state.population.len() != self.population_size {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!(
+                    "checkpoint population has {} individuals, expected {}",
+                    state.population.len(),
+                    self.population_size
+                ),
+            ));
+        }
+        Ok(state)
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use rand::SeedableRng;
+    use crate::peptide::PeptideProblem;
+    use rand::rngs::StdRng;
 
     #[test]
     fn test_uniform_crossover() {
-        let ga = GeneticAlgorithm {
-            population_size: 10,
-            generations: 1,
-            crossover_prob: 1.0, // Always do crossover
-            crossover: Crossover::Uniform,
-            mutation_prob: 0.0, // No mutation for testing
-            tournament_size: 2,
-        };
+        let ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(10)
+            .generations(1)
+            .crossover_prob(1.0) // Always do crossover
+            .crossover(Crossover::Uniform)
+            .mutation_prob(0.0) // No mutation for testing
+            .selection(Selection::Tournament(2))
+            .build();
 
         let parent1 = vec![0, 1, 2, 3, 4];
         let parent2 = vec![5, 6, 7, 8, 9];
@@ -192,15 +1097,458 @@ mod tests {
     }
 
     #[test]
-    fn test_single_point_crossover() {
-        let ga = GeneticAlgorithm {
-            population_size: 10,
-            generations: 1,
-            crossover_prob: 1.0, // Always do crossover
-            crossover: Crossover::SinglePoint,
-            mutation_prob: 0.0, // No mutation for testing
-            tournament_size: 2,
+    fn test_two_point_crossover() {
+        let ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(10)
+            .generations(1)
+            .crossover_prob(1.0) // Always do crossover
+            .crossover(Crossover::TwoPoint)
+            .mutation_prob(0.0) // No mutation for testing
+            .selection(Selection::Tournament(2))
+            .build();
+
+        let parent1 = vec![0, 1, 2, 3, 4, 5, 6, 7];
+        let parent2 = vec![10, 11, 12, 13, 14, 15, 16, 17];
+        let mut rng = StdRng::seed_from_u64(42);
+
+        for _ in 0..50 {
+            let child = ga.crossover(&parent1, &parent2, &mut rng);
+            assert_eq!(child.len(), parent1.len());
+
+            // parent2 genes form one contiguous, non-empty, inner segment [i, j)
+            let from_p2: Vec<usize> = (0..child.len())
+                .filter(|&k| child[k] == parent2[k])
+                .collect();
+            let i = from_p2[0];
+            let j = from_p2[from_p2.len() - 1] + 1;
+            assert!(i >= 1 && j > i && j <= child.len());
+            for k in 0..child.len() {
+                if k >= i && k < j {
+                    assert_eq!(child[k], parent2[k]);
+                } else {
+                    assert_eq!(child[k], parent1[k]);
+                }
+            }
+        }
+
+        // Too short for two cuts: falls back to single point
+        let child = ga.crossover(&vec![0, 1], &vec![5, 6], &mut rng);
+        assert_eq!(child, vec![0, 6]);
+    }
+
+    #[test]
+    fn test_pmx_crossover() {
+        let mut rng = StdRng::seed_from_u64(5);
+        let parent1: Vec<u8> = vec![0, 1, 2, 3, 4, 5, 6, 7];
+        let parent2: Vec<u8> = vec![3, 7, 5, 1, 6, 0, 2, 4];
+        for _ in 0..50 {
+            let child = recombine(&parent1, &parent2, Crossover::Pmx, &mut rng);
+            assert_eq!(child.len(), parent1.len());
+            // permutations stay permutations
+            let mut sorted = child.clone();
+            sorted.sort();
+            assert_eq!(sorted, parent1);
+            assert!((0..8).any(|k| child[k] == parent2[k] && child[k] != parent1[k]));
+        }
+        assert_eq!(pmx(&parent1, &parent2, 2, 5), vec![0, 3, 5, 1, 6, 2, 4, 7]);
+
+        // repeated residues: no valid mapping, but children keep their length
+        let peptide1: Vec<u8> = vec![1, 1, 2, 2, 3, 3];
+        let peptide2: Vec<u8> = vec![2, 3, 3, 1, 1, 2];
+        for _ in 0..50 {
+            let child = recombine(&peptide1, &peptide2, Crossover::Pmx, &mut rng);
+            assert_eq!(child.len(), 6);
+            assert!(child.iter().all(|aa| (1..=3).contains(aa)));
+        }
+    }
+
+    #[test]
+    fn test_crossover_from_str() {
+        assert_eq!("two-point".parse::<Crossover>(), Ok(Crossover::TwoPoint));
+        assert_eq!(
+            Crossover::Uniform.to_string().parse::<Crossover>(),
+            Ok(Crossover::Uniform)
+        );
+        assert_eq!("pmx".parse::<Crossover>(), Ok(Crossover::Pmx));
+        assert!("three-point".parse::<Crossover>().is_err());
+    }
+
+    #[test]
+    fn test_selection_picks_minimum_fitness() {
+        let _guard = crate::peptide::global_lock();
+        crate::peptide::set_motif(1).unwrap();
+
+        let ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(4)
+            .generations(1)
+            .crossover_prob(1.0)
+            .mutation_prob(0.0)
+            .selection(Selection::Tournament(64))
+            .build();
+
+        // RGD is motif 1 itself, the others are poor matches
+        let population: Vec<Vec<u8>> = ["WWW", "RGD", "CPC", "AAA"]
+            .iter()
+            .map(|s| crate::peptide::parse_sequence(s).unwrap())
+            .collect();
+        let expected = population
+            .iter()
+            .min_by(|a, b| {
+                crate::peptide::combined_fitness(a)
+                    .partial_cmp(&crate::peptide::combined_fitness(b))
+                    .unwrap()
+            })
+            .unwrap()
+            .clone();
+        assert_eq!(expected, population[1]);
+
+        let fitness: Vec<f64> = population.iter().map(PeptideProblem::fitness).collect();
+        let mut hof = HallOfFame::new(1);
+        hof.update(&population, &fitness);
+        assert_eq!(hof.best().unwrap().0, expected);
+        let mut selector = Selector::new(ga.selection, &fitness);
+        let mut rng = StdRng::seed_from_u64(1);
+        assert_eq!(population[selector.pick(&mut rng)], expected);
+    }
+
+    #[test]
+    fn test_roulette_uniform_on_flat_population() {
+        let fitness = vec![-3.0; 4];
+        let mut selector = Selector::new(Selection::Roulette, &fitness);
+        let mut rng = StdRng::seed_from_u64(5);
+
+        let mut counts = [0usize; 4];
+        for _ in 0..20_000 {
+            counts[selector.pick(&mut rng)] += 1;
+        }
+        for &c in &counts {
+            assert!((4_500..=5_500).contains(&c), "counts: {:?}", counts);
+        }
+    }
+
+    #[test]
+    fn test_roulette_prefers_lower_fitness() {
+        let fitness = vec![-10.0, 0.0, -5.0];
+        let mut selector = Selector::new(Selection::Roulette, &fitness);
+        let mut rng = StdRng::seed_from_u64(5);
+
+        let mut counts = [0usize; 3];
+        for _ in 0..10_000 {
+            counts[selector.pick(&mut rng)] += 1;
+        }
+        assert!(counts[0] > counts[2] && counts[2] > counts[1]);
+    }
+
+    #[test]
+    fn test_rank_favours_best_ranked() {
+        // huge dynamic range: roulette would almost never pick anything but index 2
+        let fitness = vec![-1.0, 0.0, -1000.0, -2.0];
+        let mut selector = Selector::new(Selection::Rank, &fitness);
+        let mut rng = StdRng::seed_from_u64(9);
+
+        let mut counts = [0usize; 4];
+        for _ in 0..20_000 {
+            counts[selector.pick(&mut rng)] += 1;
+        }
+        // expected shares 4:3:2:1 for ranks of indices 2, 3, 0, 1
+        assert!(counts[2] > counts[3] && counts[3] > counts[0] && counts[0] > counts[1]);
+        assert!((7_500..=8_500).contains(&counts[2]), "counts: {:?}", counts);
+    }
+
+    #[test]
+    fn test_boltzmann_cold_picks_best() {
+        let fitness = vec![-1.0, -3.0, 0.0, -2.9];
+        let cold = Selection::Boltzmann {
+            temperature: 1e-3,
+            cooling: 1.0,
+        };
+        let mut selector = Selector::new(cold, &fitness);
+        let mut rng = StdRng::seed_from_u64(3);
+        assert!((0..1_000).all(|_| selector.pick(&mut rng) == 1));
+
+        // cooling alone gets there from a hot start
+        let cooling = Selection::Boltzmann {
+            temperature: 100.0,
+            cooling: 0.5,
+        };
+        let mut selector = Selector::new(cooling.at_generation(30), &fitness);
+        assert!((0..1_000).all(|_| selector.pick(&mut rng) == 1));
+    }
+
+    #[test]
+    fn test_boltzmann_steep_cooling_stays_positive() {
+        let steep = Selection::Boltzmann {
+            temperature: 1.0,
+            cooling: 0.01,
+        };
+        let Selection::Boltzmann { temperature, .. } = steep.at_generation(200) else {
+            unreachable!()
+        };
+        assert!(temperature > 0.0);
+
+        let _guard = crate::peptide::global_lock();
+        crate::peptide::set_motif(0).unwrap();
+        let mut ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(40)
+            .generations(200)
+            .selection(steep)
+            .build();
+        let result = ga.run(1);
+        assert_eq!(result.progress.len(), 200);
+        assert!(result.progress.iter().all(|p| p.1.is_finite()));
+    }
+
+    #[test]
+    fn test_boltzmann_hot_is_uniform() {
+        let fitness = vec![-10.0, 0.0, -5.0, -7.0];
+        let hot = Selection::Boltzmann {
+            temperature: 1e9,
+            cooling: 0.9,
+        };
+        let mut selector = Selector::new(hot, &fitness);
+        let mut rng = StdRng::seed_from_u64(8);
+
+        let mut counts = [0usize; 4];
+        for _ in 0..20_000 {
+            counts[selector.pick(&mut rng)] += 1;
+        }
+        for &c in &counts {
+            assert!((4_500..=5_500).contains(&c), "counts: {:?}", counts);
+        }
+    }
+
+    #[test]
+    fn test_sus_counts_within_one_of_expected() {
+        let weights = [5.0, 1.0, 3.0, 0.5, 0.5];
+        let total: f64 = weights.iter().sum();
+        let mut rng = StdRng::seed_from_u64(4);
+        for _ in 0..200 {
+            let picked = sus(&weights, 10, &mut rng);
+            assert_eq!(picked.len(), 10);
+            for (i, &w) in weights.iter().enumerate() {
+                let count = picked.iter().filter(|&&p| p == i).count() as f64;
+                let expected = 10.0 * w / total;
+                assert!(count >= expected.floor() && count <= expected.ceil());
+            }
+        }
+    }
+
+    #[test]
+    fn test_sus_selector_draws_whole_population() {
+        let fitness = vec![-10.0, 0.0, -5.0, -5.0];
+        let mut selector = Selector::new(Selection::Sus, &fitness);
+        let mut rng = StdRng::seed_from_u64(2);
+
+        // one spin hands out exactly one parent per population slot
+        let mut counts = [0usize; 4];
+        for _ in 0..fitness.len() {
+            counts[selector.pick(&mut rng)] += 1;
+        }
+        assert_eq!(counts.iter().sum::<usize>(), 4);
+        assert!(counts[0] >= 2 && counts[1] == 0, "counts: {:?}", counts);
+    }
+
+    #[test]
+    fn test_best_per_generation() {
+        let _guard = crate::peptide::global_lock();
+        crate::peptide::set_motif(2).unwrap();
+
+        let mut ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(20)
+            .generations(8)
+            .build();
+        let GaResult {
+            best,
+            progress,
+            best_per_gen,
+            ..
+        } = ga.run(11);
+
+        assert_eq!(best_per_gen.len(), progress.len());
+        for (seq, stats) in best_per_gen.iter().zip(&progress) {
+            assert_eq!(PeptideProblem::fitness(seq), stats.1);
+        }
+        // the result is the best ever seen, which can predate the last generation
+        let best_seen = best_per_gen
+            .iter()
+            .map(PeptideProblem::fitness)
+            .fold(f64::INFINITY, f64::min);
+        assert!(PeptideProblem::fitness(&best) <= best_seen);
+    }
+
+    #[test]
+    fn test_evaluation_count() {
+        let _guard = crate::peptide::global_lock();
+        crate::peptide::set_motif(1).unwrap();
+
+        let mut ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(10)
+            .generations(10)
+            .build();
+        // the initial population plus one per generation
+        assert_eq!(ga.run(0).evaluations, 110);
+    }
+
+    #[test]
+    fn test_best_found_at() {
+        let _guard = crate::peptide::global_lock();
+        crate::peptide::set_motif(1).unwrap();
+
+        let mut ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(30)
+            .generations(40)
+            .build();
+        let res = ga.run(6);
+        let best_f = PeptideProblem::fitness(&res.best);
+        assert_eq!(res.progress[res.best_found_at].1, best_f);
+        assert!(res.progress[..res.best_found_at]
+            .iter()
+            .all(|p| p.1 > best_f));
+    }
+
+    #[test]
+    fn test_on_generation_called_every_generation() {
+        use std::sync::{Arc, Mutex};
+
+        let _guard = crate::peptide::global_lock();
+        crate::peptide::set_motif(1).unwrap();
+
+        let calls = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&calls);
+        let mut ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(10)
+            .generations(7)
+            .on_generation(Box::new(move |gen, best, seq: &Vec<u8>| {
+                sink.lock().unwrap().push((gen, best, seq.to_vec()));
+            }))
+            .build();
+        let result = ga.run(4);
+        let (progress, best_per_gen) = (result.progress, result.best_per_gen);
+
+        let calls = calls.lock().unwrap();
+        assert_eq!(calls.len(), 7);
+        for (i, (gen, best, seq)) in calls.iter().enumerate() {
+            assert_eq!(*gen, i);
+            assert_eq!(*best, progress[i].1 as f32);
+            assert_eq!(seq, &best_per_gen[i]);
+        }
+    }
+
+    #[test]
+    fn test_early_stopping() {
+        let _guard = crate::peptide::global_lock();
+        crate::peptide::set_motif(1).unwrap();
+
+        let mut ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(30)
+            .generations(500)
+            .patience(10, 1e-6)
+            .build();
+        let result = ga.run(8);
+
+        // RGD is tiny, the best is found quickly and then plateaus
+        let stop = result.stopped_at.expect("run should stop early");
+        assert!(stop < 499);
+        assert_eq!(result.progress.len(), stop + 1);
+        let tail = &result.progress[stop - 9..];
+        let before = result.progress[..stop - 9]
+            .iter()
+            .map(|p| p.1)
+            .fold(f64::INFINITY, f64::min);
+        assert!(tail.iter().all(|p| p.1 >= before - 1e-6));
+
+        ga.patience = 0;
+        ga.generations = 40;
+        let result = ga.run(8);
+        assert_eq!(result.stopped_at, None);
+        assert_eq!(result.progress.len(), 40);
+    }
+
+    #[test]
+    fn test_time_budget() {
+        let _guard = crate::peptide::global_lock();
+        crate::peptide::set_motif(6).unwrap();
+
+        let mut ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(50)
+            .generations(1_000_000)
+            .time_budget(Some(Duration::from_millis(50)))
+            .build();
+        let start = Instant::now();
+        let result = ga.run(2);
+        assert!(start.elapsed() < Duration::from_secs(5));
+        assert_eq!(result.stopped_at, Some(result.progress.len() - 1));
+        assert_eq!(result.best.len(), 21);
+    }
+
+    #[test]
+    fn test_diversity() {
+        assert_eq!(diversity::<PeptideProblem>(&vec![vec![1, 2, 3]; 10]), 0.0);
+        assert_eq!(diversity::<PeptideProblem>(&[vec![1, 2], vec![3, 4]]), 1.0);
+        assert_eq!(diversity::<PeptideProblem>(&[vec![1, 2], vec![1, 4]]), 0.5);
+    }
+
+    #[test]
+    fn test_adaptive_mutation_schedule_logged() {
+        let _guard = crate::peptide::global_lock();
+        crate::peptide::set_motif(1).unwrap();
+
+        let adaptive = AdaptiveMutation {
+            low: 0.1,
+            high: 0.9,
+            threshold: 0.5,
         };
+        let mut ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(30)
+            .generations(30)
+            .selection(Selection::Tournament(5))
+            .adaptive_mutation(Some(adaptive))
+            .build();
+        let progress = ga.run(3).progress;
+
+        assert!(progress
+            .iter()
+            .all(|p| p.4 == adaptive.low || p.4 == adaptive.high));
+        // a random start is diverse, strong selection then collapses it
+        assert_eq!(progress[0].4, adaptive.low);
+        assert!(progress.iter().any(|p| p.4 == adaptive.high));
+
+        ga.adaptive_mutation = None;
+        let progress = ga.run(3).progress;
+        assert!(progress.iter().all(|p| p.4 == 0.3));
+    }
+
+    #[test]
+    fn test_builder() {
+        let ga = GeneticAlgorithm::<PeptideProblem>::builder().build();
+        assert_eq!(ga.population_size, 400);
+        assert_eq!(ga.generations, 200);
+        assert_eq!(ga.selection, Selection::Tournament(3));
+        assert_eq!(ga.min_improvement, 1e-6);
+
+        let _guard = crate::peptide::global_lock();
+        crate::peptide::set_motif(1).unwrap();
+        let mut ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(10)
+            .generations(4)
+            .crossover(Crossover::Uniform)
+            .selection(Selection::Rank)
+            .patience(2, 0.0)
+            .build();
+        assert_eq!((ga.population_size, ga.patience), (10, 2));
+        assert_eq!(ga.crossover.name(), "uniform");
+        assert!(ga.run(1).progress.len() <= 4);
+    }
+
+    #[test]
+    fn test_single_point_crossover() {
+        let ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(10)
+            .generations(1)
+            .crossover_prob(1.0) // Always do crossover
+            .mutation_prob(0.0) // No mutation for testing
+            .selection(Selection::Tournament(2))
+            .build();
 
         let parent1 = vec![0, 1, 2, 3, 4];
         let parent2 = vec![5, 6, 7, 8, 9];
@@ -215,4 +1563,290 @@ mod tests {
         println!("Parent2: {:?}", parent2);
         println!("Child:   {:?}", child);
     }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_resume_matches_uninterrupted_run() {
+        let _guard = crate::peptide::global_lock();
+        crate::peptide::set_motif(5).unwrap();
+
+        let mut ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(20)
+            .generations(8)
+            .build();
+        let uninterrupted = ga.run(9);
+
+        let path = std::env::temp_dir().join(format!(
+            "peptide_opt_ga_checkpoint_{}.json",
+            std::process::id()
+        ));
+        let mut state = ga.start(9);
+        for _ in 0..3 {
+            assert!(ga.step(&mut state));
+        }
+        ga.save_checkpoint(&state, &path).unwrap();
+        drop(state);
+
+        let mut fresh = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(20)
+            .generations(8)
+            .build();
+        let mut resumed = fresh.resume_from_checkpoint(&path).unwrap();
+        assert_eq!(resumed.generation, 3);
+        while fresh.step(&mut resumed) {}
+        assert_eq!(fresh.finish(resumed), uninterrupted);
+
+        // a checkpoint of another population size is rejected
+        let other = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(10)
+            .build();
+        assert!(other.resume_from_checkpoint(&path).is_err());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_dedup_leaves_unique_genomes() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let mut population = vec![vec![1u8, 2, 3]; 6];
+        population[2] = vec![4, 5, 6];
+        let replaced = dedup(&mut population, &mut rng, |rng| {
+            PeptideProblem::random_individual(rng)
+        });
+        assert_eq!(replaced, vec![1, 3, 4, 5]);
+        assert_eq!(population[0], vec![1, 2, 3]);
+        assert_eq!(population[2], vec![4, 5, 6]);
+        for (i, a) in population.iter().enumerate() {
+            assert!(population[..i].iter().all(|b| b != a));
+        }
+    }
+
+    #[test]
+    fn test_ga_dedup_reports_duplicates() {
+        let _guard = crate::peptide::global_lock();
+        crate::peptide::set_motif(1).unwrap();
+
+        // a 3-residue motif converges fast, so offspring repeat
+        let build = |dedup| {
+            GeneticAlgorithm::<PeptideProblem>::builder()
+                .population_size(30)
+                .generations(15)
+                .dedup(dedup)
+                .build()
+        };
+        let plain = build(false).run(2);
+        assert!(plain.duplicates.iter().all(|&d| d == 0));
+        let res = build(true).run(2);
+        assert_eq!(res.duplicates.len(), 15);
+        assert!(res.duplicates.iter().sum::<usize>() > 0);
+    }
+
+    #[test]
+    fn test_immigrants_after_stagnation() {
+        let _guard = crate::peptide::global_lock();
+        crate::peptide::set_motif(1).unwrap();
+
+        // without crossover or mutation offspring are copies, so nothing
+        // improves until the immigrants arrive
+        let res = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(20)
+            .generations(10)
+            .crossover_prob(0.0)
+            .mutation_prob(0.0)
+            .immigrants(Some(Immigrants {
+                stagnation_limit: 3,
+                fraction: 0.25,
+            }))
+            .build()
+            .run(4);
+        assert_eq!(res.immigrants.len(), 10);
+        assert_eq!(res.immigrants[..3], [0, 0, 5]);
+        assert_eq!(
+            res.evaluations,
+            20 * 11 + res.immigrants.iter().sum::<usize>() as u64
+        );
+
+        assert_eq!(
+            Immigrants {
+                stagnation_limit: 1,
+                fraction: 1.0
+            }
+            .count(20),
+            19
+        );
+        assert_eq!(
+            Immigrants {
+                stagnation_limit: 1,
+                fraction: 0.01
+            }
+            .count(20),
+            1
+        );
+    }
+
+    #[test]
+    fn test_population_callback_reports_entropy() {
+        let _guard = crate::peptide::global_lock();
+        crate::peptide::set_motif(2).unwrap();
+
+        let sink = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        let log = sink.clone();
+        GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(30)
+            .generations(10)
+            .on_population(Box::new(move |gen, pop| {
+                log.lock()
+                    .unwrap()
+                    .push((gen, crate::diversity::mean_entropy(pop)));
+            }))
+            .build()
+            .run(1);
+
+        let entropy = sink.lock().unwrap();
+        assert_eq!(
+            entropy.iter().map(|e| e.0).collect::<Vec<_>>(),
+            (0..10).collect::<Vec<_>>()
+        );
+        // selection concentrates residues: the last generation is less diverse
+        assert!(entropy[9].1 < entropy[0].1);
+    }
+
+    /// Bit strings with two equally good optima, all zeros and all ones.
+    struct TwoPeaks;
+
+    const PEAK_BITS: usize = 12;
+
+    impl TSProblem for TwoPeaks {
+        type Individ = Vec<u8>;
+        type Move = usize;
+
+        fn random_individual<R: Rng>(rng: &mut R) -> Vec<u8> {
+            (0..PEAK_BITS).map(|_| rng.gen_range(0..2)).collect()
+        }
+
+        fn fitness(ind: &Vec<u8>) -> f64 {
+            let ones = ind.iter().filter(|&&b| b == 1).count();
+            ones.min(PEAK_BITS - ones) as f64
+        }
+
+        fn neighbourhood<R: Rng>(rng: &mut R, ind: &Vec<u8>, size: usize) -> Vec<(Vec<u8>, usize)> {
+            (0..size)
+                .map(|_| {
+                    let pos = rng.gen_range(0..ind.len());
+                    let mut n = ind.clone();
+                    Self::apply_move(&mut n, &pos);
+                    (n, pos)
+                })
+                .collect()
+        }
+
+        fn apply_move(ind: &mut Vec<u8>, pos: &usize) {
+            ind[*pos] ^= 1;
+        }
+
+        fn distance(a: &Vec<u8>, b: &Vec<u8>) -> f64 {
+            a.iter().zip(b).filter(|(x, y)| x != y).count() as f64 / PEAK_BITS as f64
+        }
+    }
+
+    #[test]
+    fn test_shared_fitness_penalises_crowding() {
+        let pop = vec![vec![0; PEAK_BITS], vec![0; PEAK_BITS], vec![1; PEAK_BITS]];
+        let fitness = vec![0.0; 3];
+        let sharing = FitnessSharing {
+            sigma_share: 0.5,
+            alpha: 1.0,
+        };
+        assert_eq!(niche_counts::<TwoPeaks>(&pop, sharing), vec![2.0, 2.0, 1.0]);
+        let shared = shared_fitness::<TwoPeaks>(&pop, &fitness, sharing);
+        // the lone optimum now looks better than the crowded one
+        assert!(shared[2] < shared[0]);
+        assert_eq!(shared[0], shared[1]);
+    }
+
+    #[test]
+    fn test_sharing_keeps_both_peaks() {
+        let sharing = FitnessSharing {
+            sigma_share: 0.5,
+            alpha: 1.0,
+        };
+        let build = |sharing| {
+            GeneticAlgorithm::<TwoPeaks>::builder()
+                .population_size(40)
+                .generations(60)
+                .mutation_prob(0.5)
+                .sharing(sharing)
+                .build()
+        };
+
+        let res = build(Some(sharing)).run(3);
+        assert!(res.clusters.len() > 1);
+        assert_eq!(res.clusters.iter().map(|c| c.1).sum::<usize>(), 40);
+        // both optima are still represented
+        let leaders: Vec<_> = res.clusters.iter().map(|c| c.0.clone()).collect();
+        assert!(leaders
+            .iter()
+            .any(|l| l.iter().filter(|&&b| b == 1).count() < 3));
+        assert!(leaders
+            .iter()
+            .any(|l| l.iter().filter(|&&b| b == 0).count() < 3));
+
+        assert!(build(None).run(3).clusters.is_empty());
+    }
+
+    #[test]
+    fn test_run_top_n() {
+        let _guard = crate::peptide::global_lock();
+        crate::peptide::set_motif(4).unwrap();
+
+        let top = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(30)
+            .generations(10)
+            .build()
+            .run_top_n(6, 3);
+        assert_eq!(top.len(), 3);
+        assert!(top[0].0 != top[1].0 && top[1].0 != top[2].0 && top[0].0 != top[2].0);
+        assert!(top.windows(2).all(|w| w[0].1 <= w[1].1));
+        for (seq, f) in &top {
+            assert_eq!(PeptideProblem::fitness(seq) as f32, *f);
+        }
+    }
+
+    /// Counter that every mutation makes worse.
+    struct Decay;
+
+    impl TSProblem for Decay {
+        type Individ = u32;
+        type Move = ();
+
+        fn random_individual<R: Rng>(_rng: &mut R) -> u32 {
+            0
+        }
+
+        fn fitness(ind: &u32) -> f64 {
+            *ind as f64
+        }
+
+        fn neighbourhood<R: Rng>(_rng: &mut R, ind: &u32, size: usize) -> Vec<(u32, ())> {
+            vec![(ind + 1, ()); size]
+        }
+
+        fn apply_move(ind: &mut u32, _mv: &()) {
+            *ind += 1;
+        }
+    }
+
+    #[test]
+    fn test_hall_of_fame_beats_final_population() {
+        let mut ga = GeneticAlgorithm::<Decay>::builder()
+            .population_size(5)
+            .generations(4)
+            .mutation_prob(1.0)
+            .hall_of_fame(3)
+            .build();
+        let res = ga.run(1);
+        // every generation is one step worse than the last
+        assert_eq!(res.progress.last().unwrap().1, 4.0);
+        assert_eq!(res.best, 0);
+        assert_eq!(res.hall_of_fame, vec![(0, 0.0), (1, 1.0), (2, 2.0)]);
+    }
 }
@@ -0,0 +1,94 @@
+//! Bounded record of the best distinct individuals seen over a run.
+
+/// Up to `capacity` distinct individuals with their fitness, best (lowest)
+/// first. Unlike a final population it never loses an entry to a worse one,
+/// so its best fitness cannot regress.
+#[derive(Debug, Clone, PartialEq)]
+#[cfg_attr(
+    feature = "serde",
+    derive(serde::Serialize, serde::Deserialize),
+    serde(bound(
+        serialize = "I: serde::Serialize, F: serde::Serialize",
+        deserialize = "I: serde::de::DeserializeOwned, F: serde::de::DeserializeOwned"
+    ))
+)]
+pub struct HallOfFame<I, F = f64> {
+    capacity: usize,
+    entries: Vec<(I, F)>,
+}
+
+impl<I: Clone + PartialEq, F: PartialOrd + Copy> HallOfFame<I, F> {
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: Vec::with_capacity(capacity + 1),
+        }
+    }
+
+    /// Admits every individual of a scored population that beats the
+    /// current worst entry (or fills a free slot) and is not already in.
+    pub fn update(&mut self, population: &[I], fitness: &[F]) {
+        if self.capacity == 0 {
+            return;
+        }
+        for (ind, &f) in population.iter().zip(fitness) {
+            let full = self.entries.len() == self.capacity;
+            if full && self.entries.last().is_some_and(|worst| f >= worst.1) {
+                continue;
+            }
+            if self.entries.iter().any(|(seen, _)| seen == ind) {
+                continue;
+            }
+            let at = self.entries.partition_point(|(_, g)| *g <= f);
+            self.entries.insert(at, (ind.clone(), f));
+            self.entries.truncate(self.capacity);
+        }
+    }
+
+    /// Best individual ever admitted.
+    pub fn best(&self) -> Option<&(I, F)> {
+        self.entries.first()
+    }
+
+    pub fn entries(&self) -> &[(I, F)] {
+        &self.entries
+    }
+
+    pub fn into_vec(self) -> Vec<(I, F)> {
+        self.entries
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keeps_best_distinct() {
+        let mut hof = HallOfFame::new(3);
+        hof.update(&[5, 3, 5, 9], &[5.0, 3.0, 5.0, 9.0]);
+        assert_eq!(hof.entries(), &[(3, 3.0), (5, 5.0), (9, 9.0)]);
+        hof.update(&[1, 3, 7], &[1.0, 3.0, 7.0]);
+        assert_eq!(hof.entries(), &[(1, 1.0), (3, 3.0), (5, 5.0)]);
+        // a worse population changes nothing
+        hof.update(&[8, 9], &[8.0, 9.0]);
+        assert_eq!(hof.best(), Some(&(1, 1.0)));
+        assert_eq!(hof.len(), 3);
+
+        let mut off = HallOfFame::<u8>::new(0);
+        off.update(&[1], &[1.0]);
+        assert!(off.is_empty());
+    }
+}
@@ -0,0 +1,149 @@
+//! Hill climbing: move to an improving neighbour until a sampled
+//! neighbourhood has none left.
+
+use crate::problem::TSProblem;
+use rand::{Rng, SeedableRng};
+use std::time::{Duration, Instant};
+
+/// Which improving neighbour a climb step takes.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum ClimbMode {
+    /// The first candidate that beats the current solution; cheaper per step.
+    First,
+    /// The best candidate of the whole neighbourhood.
+    #[default]
+    Best,
+}
+
+/// Outcome of `HillClimb::run` / `HillClimb::climb`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClimbResult<I> {
+    pub best: I,
+    /// Fitness after every accepted move, starting with the initial one.
+    pub trace: Vec<(usize, f64)>,
+    /// Number of moves taken; the best is the solution after the last one.
+    pub best_found_at: usize,
+    /// Number of `P::fitness` calls made by the run.
+    pub evaluations: u64,
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(bound = ""))]
+pub struct HillClimb<P: TSProblem> {
+    /// Candidates sampled from `P::neighbourhood` per step.
+    pub neigh_size: usize,
+    /// Upper bound on the number of moves.
+    pub max_steps: usize,
+    pub mode: ClimbMode,
+    /// Wall-clock limit checked before each step; the best so far is
+    /// returned once it is exceeded.
+    pub time_budget: Option<Duration>,
+    #[cfg_attr(feature = "serde", serde(skip))]
+    pub(crate) _phantom: std::marker::PhantomData<P>,
+}
+
+impl<P: TSProblem> HillClimb<P> {
+    pub fn new(neigh_size: usize, max_steps: usize) -> Self {
+        Self {
+            neigh_size,
+            max_steps,
+            mode: ClimbMode::Best,
+            time_budget: None,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    /// Climbs from a random individual.
+    pub fn run(&self, seed: u64) -> ClimbResult<P::Individ> {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
+        let start = P::random_individual(&mut rng);
+        self.climb(start, &mut rng)
+    }
+
+    /// Climbs from `start`; only strict improvements are taken, so the
+    /// result is never worse than the start.
+    pub fn climb<R: Rng>(&self, start: P::Individ, rng: &mut R) -> ClimbResult<P::Individ> {
+        let mut curr_f = P::fitness(&start);
+        let mut curr = start;
+        let mut evaluations = 1;
+        let mut trace = vec![(0, curr_f)];
+        let started = Instant::now();
+
+        for step in 1..=self.max_steps {
+            if self.time_budget.is_some_and(|b| started.elapsed() >= b) {
+                break;
+            }
+
+            let mut next = None;
+            for (cand, _) in P::neighbourhood(rng, &curr, self.neigh_size) {
+                let f = P::fitness(&cand);
+                evaluations += 1;
+                let best_f = next.as_ref().map_or(curr_f, |&(_, f)| f);
+                if f < best_f {
+                    next = Some((cand, f));
+                    if self.mode == ClimbMode::First {
+                        break;
+                    }
+                }
+            }
+
+            // local optimum with respect to the sampled neighbourhood
+            let Some((cand, f)) = next else { break };
+            curr = cand;
+            curr_f = f;
+            trace.push((step, curr_f));
+        }
+
+        ClimbResult {
+            best: curr,
+            best_found_at: trace.len() - 1,
+            trace,
+            evaluations,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peptide::{global_lock, set_motif, PeptideProblem};
+
+    #[test]
+    fn test_never_worsens_start() {
+        let _guard = global_lock();
+        set_motif(5).unwrap();
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(2);
+        for mode in [ClimbMode::First, ClimbMode::Best] {
+            let mut hc = HillClimb::<PeptideProblem>::new(20, 200);
+            hc.mode = mode;
+            for _ in 0..5 {
+                let start = PeptideProblem::random_individual(&mut rng);
+                let start_f = PeptideProblem::fitness(&start);
+                let res = hc.climb(start, &mut rng);
+                assert!(PeptideProblem::fitness(&res.best) <= start_f);
+                // every accepted move strictly improves
+                assert!(res.trace.windows(2).all(|w| w[1].1 < w[0].1));
+                assert_eq!(
+                    res.trace.last().unwrap().1,
+                    PeptideProblem::fitness(&res.best)
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_first_improvement_scores_fewer_candidates() {
+        let _guard = global_lock();
+        set_motif(5).unwrap();
+
+        let best = HillClimb::<PeptideProblem>::new(50, 1);
+        let mut first = HillClimb::<PeptideProblem>::new(50, 1);
+        first.mode = ClimbMode::First;
+        // a random start is far from optimal, so some early candidate improves
+        let res_first = first.run(4);
+        let res_best = best.run(4);
+        assert_eq!(res_best.evaluations, 51);
+        assert!(res_first.evaluations < res_best.evaluations);
+    }
+}
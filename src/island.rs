@@ -0,0 +1,205 @@
+//! Island model: several GA sub-populations evolving in parallel threads,
+//! with the best individuals migrating around a ring.
+
+use crate::genetic::{GaResult, GaState, GeneticAlgorithm};
+use crate::problem::TSProblem;
+use crate::util::best_index;
+
+/// Outcome of `IslandGA::run`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IslandResult<I = Vec<u8>> {
+    /// Best individual over all islands.
+    pub best: I,
+    /// Island that holds `best`.
+    pub best_island: usize,
+    /// Every island's own result.
+    pub islands: Vec<GaResult<I>>,
+    /// Individuals moved between islands over the run.
+    pub migrations: usize,
+}
+
+impl<I> IslandResult<I> {
+    /// Fitness evaluations of all islands together.
+    pub fn evaluations(&self) -> u64 {
+        self.islands.iter().map(|r| r.evaluations).sum()
+    }
+}
+
+/// Runs one `GeneticAlgorithm` per island. Every `migration_interval`
+/// generations each island sends copies of its best `migrants` individuals
+/// to the next island in the ring, where they replace the worst ones.
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(bound = ""))]
+pub struct IslandGA<P: TSProblem> {
+    pub islands: Vec<GeneticAlgorithm<P>>,
+    pub migration_interval: usize,
+    pub migrants: usize,
+}
+
+impl<P> IslandGA<P>
+where
+    P: TSProblem + Send,
+    P::Individ: Send + Sync,
+{
+    pub fn new(
+        islands: Vec<GeneticAlgorithm<P>>,
+        migration_interval: usize,
+        migrants: usize,
+    ) -> Self {
+        Self {
+            islands,
+            migration_interval,
+            migrants,
+        }
+    }
+
+    /// Island `i` is seeded with `seed + i` (wrapping).
+    pub fn run(&mut self, seed: u64) -> IslandResult<P::Individ> {
+        assert!(!self.islands.is_empty(), "island model needs an island");
+        let mut states: Vec<_> = self
+            .islands
+            .iter()
+            .enumerate()
+            .map(|(i, ga)| ga.start(seed.wrapping_add(i as u64)))
+            .collect();
+        let interval = self.migration_interval.max(1);
+        let mut migrations = 0;
+
+        while states.iter().any(|s| !s.is_done()) {
+            std::thread::scope(|scope| {
+                for (ga, state) in self.islands.iter_mut().zip(states.iter_mut()) {
+                    scope.spawn(move || {
+                        for _ in 0..interval {
+                            if !ga.step(state) {
+                                break;
+                            }
+                        }
+                    });
+                }
+            });
+            migrations += migrate(&mut states, self.migrants);
+        }
+
+        let islands: Vec<_> = self
+            .islands
+            .iter()
+            .zip(states)
+            .map(|(ga, state)| ga.finish(state))
+            .collect();
+        let fitness: Vec<f64> = islands.iter().map(|r| P::fitness(&r.best)).collect();
+        let (best_island, _) = best_index(&fitness).unwrap();
+        IslandResult {
+            best: islands[best_island].best.clone(),
+            best_island,
+            islands,
+            migrations,
+        }
+    }
+}
+
+/// Ring migration: copies of the best `migrants` of every island replace
+/// the worst of the next one. Islands that have finished neither send nor
+/// receive. Returns the number of individuals moved.
+fn migrate<I: Clone>(states: &mut [GaState<I>], migrants: usize) -> usize {
+    let k = states.len();
+    if k < 2 || migrants == 0 {
+        return 0;
+    }
+    let by_fitness = |s: &GaState<I>| {
+        let mut order: Vec<usize> = (0..s.population.len()).collect();
+        order.sort_by(|&a, &b| s.fitnesses[a].partial_cmp(&s.fitnesses[b]).unwrap());
+        order
+    };
+
+    // pick every island's emigrants before anyone receives
+    let emigrants: Vec<Vec<(I, f64)>> = states
+        .iter()
+        .map(|s| {
+            if s.is_done() {
+                return Vec::new();
+            }
+            by_fitness(s)
+                .into_iter()
+                .take(migrants)
+                .map(|i| (s.population[i].clone(), s.fitnesses[i]))
+                .collect()
+        })
+        .collect();
+
+    let mut moved = 0;
+    for (from, group) in emigrants.into_iter().enumerate() {
+        let to = &mut states[(from + 1) % k];
+        if to.is_done() {
+            continue;
+        }
+        let worst = by_fitness(to).into_iter().rev();
+        for (slot, (ind, f)) in worst.zip(group) {
+            to.population[slot] = ind;
+            to.fitnesses[slot] = f;
+            moved += 1;
+        }
+    }
+    moved
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peptide::{global_lock, set_motif, PeptideProblem};
+
+    fn island(generations: usize) -> GeneticAlgorithm<PeptideProblem> {
+        GeneticAlgorithm::builder()
+            .population_size(20)
+            .generations(generations)
+            .build()
+    }
+
+    #[test]
+    fn test_migration_transfers_best_individuals() {
+        let _guard = global_lock();
+        set_motif(7).unwrap();
+
+        let ga = island(10);
+        let mut states = vec![ga.start(1), ga.start(2)];
+        let best_of = |s: &GaState| {
+            let (i, _) = s
+                .fitnesses
+                .iter()
+                .enumerate()
+                .min_by(|a, b| a.1.partial_cmp(b.1).unwrap())
+                .unwrap();
+            s.population[i].clone()
+        };
+        let (best0, best1) = (best_of(&states[0]), best_of(&states[1]));
+        assert!(!states[1].population.contains(&best0));
+
+        assert_eq!(migrate(&mut states, 2), 4);
+        assert!(states[1].population.contains(&best0));
+        assert!(states[0].population.contains(&best1));
+        for s in &states {
+            assert_eq!(s.population.len(), 20);
+            // fitness moved along with the individuals
+            for (ind, &f) in s.population.iter().zip(&s.fitnesses) {
+                assert_eq!(PeptideProblem::fitness(ind), f);
+            }
+        }
+    }
+
+    #[test]
+    fn test_island_run() {
+        let _guard = global_lock();
+        set_motif(7).unwrap();
+
+        let mut model = IslandGA::new(vec![island(12), island(12)], 4, 2);
+        let res = model.run(5);
+        assert_eq!(res.islands.len(), 2);
+        assert!(res.islands.iter().all(|r| r.progress.len() == 12));
+        // migrations after generations 4, 8 and 12; an island only reports
+        // done on the step after its last generation
+        assert_eq!(res.migrations, 12);
+        assert_eq!(res.best, res.islands[res.best_island].best);
+        assert_eq!(res.evaluations(), 2 * 20 * 13);
+        // deterministic despite the threads
+        let again = IslandGA::new(vec![island(12), island(12)], 4, 2).run(5);
+        assert_eq!(again, res);
+    }
+}
@@ -0,0 +1,27 @@
+//! Peptide design by metaheuristic search: a plain and a neighbour-aware
+//! genetic algorithm plus tabu search, simulated annealing and hill
+//! climbing, scored against a target motif with BLOSUM62 similarity and
+//! NEPRE neighbour-pair energy.
+
+pub mod align;
+pub mod annealing;
+pub mod crossover;
+pub mod data;
+pub mod diversity;
+pub mod fasta;
+pub mod fitness;
+pub mod ga_neighbour;
+pub mod genetic;
+pub mod hall_of_fame;
+pub mod hill_climb;
+pub mod island;
+pub mod mutation;
+pub mod nepre;
+pub mod output;
+pub mod parallel;
+pub mod peptide;
+pub mod problem;
+pub mod pssm;
+pub mod stats;
+pub mod tabu;
+mod util;
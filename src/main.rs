@@ -1,19 +1,177 @@
-mod data;
-mod ga_neighbour;
-mod genetic;
-mod nepre;
-mod peptide;
-mod problem;
-mod tabu;
-
 use clap::Parser;
-use ga_neighbour::{NeighCfg, NeighbourGA};
-use genetic::GeneticAlgorithm;
-use peptide::combined_fitness;
-use peptide::PeptideProblem;
+use peptide_opt::annealing::SimulatedAnnealing;
+use peptide_opt::ga_neighbour::{ConstraintMode, LocalSearchMode, NeighCfg, NeighbourGA};
+use peptide_opt::genetic::{self, GeneticAlgorithm};
+use peptide_opt::hill_climb::{ClimbMode, HillClimb};
+use peptide_opt::island::IslandGA;
+use peptide_opt::output::CsvWriter;
+use peptide_opt::peptide::{self, combined_fitness, PeptideProblem};
+use peptide_opt::tabu::{AspirationMode, TabuMode, TabuSearch};
+use peptide_opt::{diversity, fasta, nepre, pssm, stats};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// How much a comparison run prints (`--quiet` / `--verbose`).
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Verbosity {
+    /// Only the final summary table.
+    Quiet,
+    /// Per-motif results and winners, then the overall statistics.
+    Normal,
+    /// Also the timing and fitness breakdown of every algorithm.
+    Verbose,
+}
+
+static VERBOSITY: AtomicU8 = AtomicU8::new(Verbosity::Normal as u8);
+
+fn set_verbosity(level: Verbosity) {
+    VERBOSITY.store(level as u8, Ordering::Relaxed);
+}
+
+fn verbosity() -> Verbosity {
+    match VERBOSITY.load(Ordering::Relaxed) {
+        0 => Verbosity::Quiet,
+        1 => Verbosity::Normal,
+        _ => Verbosity::Verbose,
+    }
+}
+
+/// `println!` that only prints at verbosity `$level` or above.
+macro_rules! say {
+    ($level:ident) => {
+        if verbosity() >= Verbosity::$level {
+            println!();
+        }
+    };
+    ($level:ident, $($arg:tt)*) => {
+        if verbosity() >= Verbosity::$level {
+            println!($($arg)*);
+        }
+    };
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum SelectionArg {
+    Tournament,
+    Roulette,
+    Rank,
+    Sus,
+    Boltzmann,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum InitArg {
+    Uniform,
+    Background,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ConstraintArg {
+    Reject,
+    Repair,
+    Penalty,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ClimbArg {
+    First,
+    Best,
+}
+
+#[derive(Clone, Copy, clap::ValueEnum)]
+enum ScoringArg {
+    Positional,
+    Global,
+    Local,
+}
 
 #[derive(Parser)]
-struct Args {
+#[command(args_conflicts_with_subcommands = true)]
+struct Cli {
+    #[command(subcommand)]
+    command: Option<Command>,
+
+    /// without a subcommand, the flags of `run`
+    #[command(flatten)]
+    run: RunArgs,
+}
+
+#[derive(clap::Subcommand)]
+enum Command {
+    /// compare the optimisers on the selected motif(s) (the default)
+    Run(Box<RunArgs>),
+    /// list available motifs
+    List,
+    /// score one sequence against the selected motif(s)
+    Evaluate(EvaluateArgs),
+    /// time fitness evaluations of random peptides
+    Bench(BenchArgs),
+    /// rank every peptide in a FASTA file against the selected motif(s)
+    Score(ScoreArgs),
+}
+
+// Motif selection and fitness function, shared by every subcommand that scores
+#[derive(clap::Args)]
+struct ScoringArgs {
+    /// use only the chosen motif (index in MOTIFS)
+    #[arg(long)]
+    motif: Option<usize>,
+
+    /// optimise toward a custom motif given as one-letter codes, x for any
+    /// residue (e.g. RGDxP)
+    #[arg(long, conflicts_with = "motif")]
+    motif_seq: Option<String>,
+
+    /// score against a position-specific scoring matrix instead of a motif:
+    /// one row of 20 residue preferences per position (e.g. from MEME)
+    #[arg(long, conflicts_with_all = ["motif", "motif_seq", "scoring", "best_motif"])]
+    pssm_file: Option<String>,
+
+    /// load the NEPRE pair potential from this 20x20 matrix file instead of the embedded one
+    #[arg(long)]
+    nepre_file: Option<String>,
+
+    /// also score residue triplets with this 400-row table (row 20*a+b holds t[a][b][c])
+    #[arg(long)]
+    nepre_triplet_file: Option<String>,
+
+    /// average the NEPRE matrix with its transpose so pair energies ignore direction
+    #[arg(long, default_value_t = false)]
+    nepre_symmetric: bool,
+
+    /// weight of the BLOSUM62 motif-similarity term
+    #[arg(long, default_value_t = 1.0)]
+    blosum_weight: f32,
+
+    /// weight of the NEPRE neighbour-pair term
+    #[arg(long, default_value_t = 0.2)]
+    nepre_weight: f32,
+
+    /// how peptides are matched against the motif
+    #[arg(long, value_enum, default_value_t = ScoringArg::Positional)]
+    scoring: ScoringArg,
+
+    /// per-position gap penalty of the alignment scoring modes
+    #[arg(long, default_value_t = 4)]
+    gap_penalty: i32,
+
+    /// score against whichever motif a peptide matches best and report it
+    #[arg(long, default_value_t = false)]
+    best_motif: bool,
+
+    /// penalise distance of the net charge from this value
+    #[arg(long, allow_hyphen_values = true)]
+    target_charge: Option<f32>,
+
+    /// weight of the net-charge term (only with --target-charge)
+    #[arg(long, default_value_t = 1.0)]
+    charge_weight: f32,
+}
+
+#[derive(clap::Args)]
+struct RunArgs {
     /// Run every algorithm on every motif
     #[arg(long, default_value_t = false)]
     all: bool,
@@ -22,6 +180,10 @@ struct Args {
     #[arg(long, default_value_t = 0)]
     seed: u64,
 
+    /// repeat every run with seeds seed..seed+N and report statistics over them
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    runs: u64,
+
     /// default number of iterations / generations
     #[arg(long, default_value_t = 200)]
     generations: usize,
@@ -34,213 +196,1494 @@ struct Args {
     #[arg(long, default_value_t = 0.9)]
     crossover_prob: f64,
 
+    /// crossover operator of the normal GA: single-point, two-point, uniform or pmx
+    #[arg(long, default_value_t = genetic::Crossover::SinglePoint)]
+    crossover: genetic::Crossover,
+
     /// mutation probability
     #[arg(long, default_value_t = 0.3)]
     mutation_prob: f64,
 
+    /// adapt the normal GA's mutation rate to population diversity
+    /// (uses --mutation-prob while diverse, --mutation-high below --diversity-threshold)
+    #[arg(long, default_value_t = false)]
+    adaptive_mutation: bool,
+
+    /// mutation probability used when diversity is low (with --adaptive-mutation)
+    #[arg(long, default_value_t = 0.8)]
+    mutation_high: f64,
+
+    /// normalised Hamming diversity below which --mutation-high kicks in
+    #[arg(long, default_value_t = 0.2)]
+    diversity_threshold: f64,
+
+    /// residue distribution of the initial GA populations
+    #[arg(long, value_enum, default_value_t = InitArg::Uniform)]
+    init: InitArg,
+
+    /// NeighbourGA substitutions favour residues similar to the motif's
+    #[arg(long, default_value_t = false)]
+    guided_mutation: bool,
+
+    /// replace repeated offspring of both GAs by fresh individuals
+    #[arg(long, default_value_t = false)]
+    dedup: bool,
+
+    /// normal GA selects on fitness shared within this niche radius (fraction
+    /// of differing positions) and reports the final population's clusters
+    #[arg(long)]
+    sigma_share: Option<f64>,
+
+    /// exponent of the fitness-sharing function 1 - (d / sigma)^alpha
+    #[arg(long, default_value_t = 1.0)]
+    sharing_alpha: f64,
+
+    /// after this many generations without improvement both GAs replace their
+    /// worst individuals by random immigrants
+    #[arg(long, value_name = "N")]
+    stagnation_limit: Option<usize>,
+
+    /// share of the population replaced by immigrants (with --stagnation-limit)
+    #[arg(long, default_value_t = 0.2)]
+    immigrant_fraction: f64,
+
+    /// also print the consensus of each GA's final population with the share
+    /// of individuals agreeing on it at every position
+    #[arg(long, default_value_t = false)]
+    consensus: bool,
+
+    /// also list the N best distinct peptides each GA saw over its run
+    #[arg(long, value_name = "N")]
+    top: Option<usize>,
+
+    /// what NeighbourGA does with implausible offspring: replace them, repair
+    /// them or keep them with a fitness penalty
+    #[arg(long, value_enum, default_value_t = ConstraintArg::Reject)]
+    constraint_mode: ConstraintArg,
+
+    /// fitness added per broken plausibility rule with --constraint-mode penalty
+    #[arg(long, default_value_t = 5.0)]
+    violation_penalty: f32,
+
+    /// share of the NeighbourGA population replaced per generation; below 1
+    /// the offspring replace only the worst individuals (steady-state)
+    #[arg(long, default_value_t = 1.0)]
+    replacement_fraction: f32,
+
+    /// NeighbourGA selects on hill-climbed fitness but keeps the original genomes
+    #[arg(long, default_value_t = false)]
+    baldwinian: bool,
+
+    /// parent selection of the normal GA
+    #[arg(long, value_enum, default_value_t = SelectionArg::Tournament)]
+    selection: SelectionArg,
+
     /// tournament size (GA)
     #[arg(long, default_value_t = 3)]
     tournament_size: usize,
 
-    /// run only the chosen motif (index in MOTIFS)
+    /// starting temperature of --selection boltzmann
+    #[arg(long, default_value_t = 5.0)]
+    boltzmann_temperature: f64,
+
+    /// factor the Boltzmann temperature is multiplied by every generation
+    #[arg(long, default_value_t = 0.97)]
+    boltzmann_cooling: f64,
+
+    /// stop a GA after this many generations without improvement (0 = never)
+    #[arg(long, default_value_t = 0)]
+    patience: usize,
+
+    /// smallest fitness decrease that counts as an improvement for --patience
+    #[arg(long, default_value_t = 1e-6)]
+    min_improvement: f64,
+
+    /// wall-clock limit in seconds for each optimisation run
     #[arg(long)]
-    motif: Option<usize>,
+    max_seconds: Option<f64>,
 
-    /// list available motifs and exit
+    /// memoise fitness evaluations in NeighbourGA
+    #[arg(long, default_value_t = false)]
+    fitness_cache: bool,
+
+    /// also run tabu search on every motif
+    #[arg(long, default_value_t = false)]
+    tabu: bool,
+
+    /// number of tabu search iterations
+    #[arg(long, default_value_t = 2000)]
+    tabu_iters: usize,
+
+    /// neighbourhood size sampled per tabu iteration / hill-climbing step
+    #[arg(long, default_value_t = 50)]
+    neigh_size: usize,
+
+    /// tabu list length
+    #[arg(long, default_value_t = 20)]
+    tabu_len: usize,
+
+    /// make the tabu list remember visited solutions instead of moves
+    #[arg(long, default_value_t = false)]
+    tabu_state: bool,
+
+    /// clear the tabu list every N iterations (never by default)
     #[arg(long)]
-    list_motifs: bool,
+    reheat_every: Option<usize>,
+
+    /// penalise over-used (position, residue) assignments once tabu search stalls
+    #[arg(long, default_value_t = false)]
+    tabu_diversify: bool,
+
+    /// iterations without improvement before diversification starts
+    #[arg(long, default_value_t = 100)]
+    diversify_after: usize,
+
+    /// take a tabu move only if it beats the current solution by more than
+    /// this margin, instead of whenever it beats the best so far
+    #[arg(long, value_name = "MARGIN")]
+    aspiration_margin: Option<f64>,
+
+    /// adapt the tabu tenure to how often solutions repeat (reactive tabu search)
+    #[arg(long, default_value_t = false)]
+    reactive_tabu: bool,
+
+    /// lower bound of the reactive tabu tenure
+    #[arg(long, default_value_t = 2)]
+    tabu_min_len: usize,
+
+    /// upper bound of the reactive tabu tenure
+    #[arg(long, default_value_t = 100)]
+    tabu_max_len: usize,
+
+    /// also run simulated annealing on every motif
+    #[arg(long, default_value_t = false)]
+    sa: bool,
+
+    /// starting temperature of simulated annealing
+    #[arg(long, default_value_t = 10.0)]
+    sa_t_start: f64,
+
+    /// temperature at which simulated annealing stops
+    #[arg(long, default_value_t = 0.01)]
+    sa_t_end: f64,
+
+    /// cooling factor applied to the temperature after every annealing step
+    #[arg(long, default_value_t = 0.995)]
+    sa_alpha: f64,
+
+    /// also run hill climbing on every motif (samples --neigh-size neighbours per step)
+    #[arg(long, default_value_t = false)]
+    hill_climb: bool,
+
+    /// maximum number of hill-climbing moves
+    #[arg(long, default_value_t = 2000)]
+    climb_steps: usize,
+
+    /// take the first improving move or the best one, in hill climbing and in
+    /// NeighbourGA's local search
+    #[arg(long, value_enum, default_value_t = ClimbArg::Best)]
+    climb_mode: ClimbArg,
+
+    /// repeat NeighbourGA's local-search sweep until a pass changes nothing
+    #[arg(long, default_value_t = false)]
+    climb_until_stable: bool,
+
+    /// chance that a NeighbourGA offspring is considered for local search
+    #[arg(long, default_value_t = 0.20)]
+    local_search_prob: f32,
+
+    /// chance that a considered offspring of at most --short-pep-len residues is climbed
+    #[arg(long, default_value_t = 0.60)]
+    short_pep_prob: f32,
+
+    /// chance that a considered longer offspring is climbed
+    #[arg(long, default_value_t = 0.20)]
+    long_pep_prob: f32,
+
+    /// length up to which --short-pep-prob applies
+    #[arg(long, default_value_t = 5)]
+    short_pep_len: usize,
+
+    /// also run an island-model GA with this many islands of --pop-size / N each
+    #[arg(long, default_value_t = 1, value_parser = clap::value_parser!(u64).range(1..))]
+    islands: u64,
+
+    /// generations between migrations in the island-model GA
+    #[arg(long, default_value_t = 10)]
+    migration_interval: usize,
+
+    /// best individuals each island sends to the next one on every migration
+    #[arg(long, default_value_t = 2)]
+    migrants: usize,
+
+    /// design peptides of this many residues instead of the motif's length
+    /// (positional scoring then falls back to local alignment)
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u64).range(1..))]
+    length: Option<u64>,
+
+    /// relative frequency of substitution moves in neighbourhoods and GA mutation
+    #[arg(long, default_value_t = 0.7)]
+    subst_weight: f32,
+
+    /// relative frequency of swap moves in neighbourhoods and GA mutation
+    #[arg(long, default_value_t = 0.3)]
+    swap_weight: f32,
+
+    /// relative frequency of segment-reversal moves in neighbourhoods
+    #[arg(long, default_value_t = 0.1)]
+    invert_weight: f32,
+
+    /// relative frequency of block-relocation moves in neighbourhoods
+    #[arg(long, default_value_t = 0.0)]
+    relocate_weight: f32,
+
+    /// relative frequency of insert moves (with --variable-length)
+    #[arg(long, default_value_t = 0.15)]
+    insert_weight: f32,
+
+    /// relative frequency of delete moves (with --variable-length)
+    #[arg(long, default_value_t = 0.15)]
+    delete_weight: f32,
+
+    /// let tabu search insert and delete residues (variable-length peptides)
+    #[arg(long, default_value_t = false)]
+    variable_length: bool,
+
+    /// minimum peptide length with --variable-length
+    #[arg(long, default_value_t = 1)]
+    min_len: usize,
+
+    /// maximum peptide length with --variable-length
+    #[arg(long, default_value_t = 40)]
+    max_len: usize,
+
+    /// write per-generation progress of every algorithm and motif to this CSV file
+    #[arg(long)]
+    csv: Option<PathBuf>,
+
+    /// write the final results and configuration of every run to this JSON file
+    #[cfg(feature = "serde")]
+    #[arg(long)]
+    json: Option<PathBuf>,
+
+    /// save the normal GA's state after every generation to PATH-motif<ID>-seed<SEED>.json
+    #[cfg(feature = "serde")]
+    #[arg(long, value_name = "PATH")]
+    checkpoint: Option<PathBuf>,
+
+    /// continue the normal GA from existing --checkpoint files
+    #[cfg(feature = "serde")]
+    #[arg(long, default_value_t = false, requires = "checkpoint")]
+    resume: bool,
+
+    /// write each motif's best peptides to this FASTA file
+    #[arg(long)]
+    fasta_out: Option<PathBuf>,
+
+    /// worker threads for parallel fitness evaluation (0 = one per core);
+    /// no effect unless built with the rayon feature
+    #[arg(long, value_name = "N", default_value_t = 0)]
+    threads: usize,
+
+    /// print only the final summary table
+    #[arg(long, default_value_t = false, conflicts_with = "verbose")]
+    quiet: bool,
+
+    /// also print every algorithm's timing and fitness breakdown
+    #[arg(long, default_value_t = false)]
+    verbose: bool,
+
+    /// print the per-term fitness split of each motif's winning sequence
+    #[arg(long, default_value_t = false)]
+    breakdown: bool,
+
+    /// also print each algorithm's fitness per residue in the summary
+    #[arg(long, default_value_t = false)]
+    normalized: bool,
+
+    /// also reject peptides with an instability index above 40
+    #[arg(long, default_value_t = false)]
+    check_instability: bool,
+
+    /// also reject peptides with an N-glycosylation sequon (N, any residue but P, S or T)
+    #[arg(long, default_value_t = false)]
+    avoid_glycosylation: bool,
+
+    /// also reject peptides a protease would cut: `trypsin` or the residues
+    /// cleaved after, optionally `!` and the residues that block it (e.g.
+    /// FWY!P); repeatable
+    #[arg(long = "cleavage-site", value_name = "PATTERN", value_parser = peptide::CleavageSite::parse)]
+    cleavage_sites: Vec<peptide::CleavageSite>,
+
+    #[command(flatten)]
+    scoring: ScoringArgs,
 }
 
-fn main() {
-    let args = Args::parse();
+#[derive(clap::Args)]
+struct EvaluateArgs {
+    /// peptide in one-letter codes
+    sequence: String,
 
-    // Handle listing motifs
-    if args.list_motifs {
-        println!("Available motifs:");
-        for (i, motif) in peptide::MOTIFS.iter().enumerate() {
-            println!(
-                "{}: {}",
-                i,
-                std::str::from_utf8(motif).unwrap_or("Invalid UTF-8")
-            );
+    #[command(flatten)]
+    scoring: ScoringArgs,
+}
+
+#[derive(clap::Args)]
+struct ScoreArgs {
+    /// FASTA file of candidate peptides
+    fasta: PathBuf,
+
+    #[command(flatten)]
+    scoring: ScoringArgs,
+}
+
+#[derive(clap::Args)]
+struct BenchArgs {
+    /// fitness evaluations timed per motif
+    #[arg(long, default_value_t = 100_000, value_parser = clap::value_parser!(u64).range(1..))]
+    evaluations: u64,
+
+    /// RNG seed of the random peptides
+    #[arg(long, default_value_t = 0)]
+    seed: u64,
+
+    #[command(flatten)]
+    scoring: ScoringArgs,
+}
+
+fn main() {
+    let cli = Cli::parse();
+    match cli.command {
+        Some(Command::Run(args)) => run(&args),
+        None => run(&cli.run),
+        Some(Command::List) => {
+            println!("Available motifs:");
+            for (i, motif) in peptide::list_motifs() {
+                println!("{}: {}", i, motif);
+            }
+        }
+        Some(Command::Evaluate(args)) => evaluate(&args),
+        Some(Command::Bench(args)) => bench(&args),
+        Some(Command::Score(args)) => {
+            let motif_range = configure_scoring(&args.scoring);
+            let text = std::fs::read_to_string(&args.fasta).unwrap_or_else(|e| {
+                eprintln!("error: {}: {}", args.fasta.display(), e);
+                std::process::exit(1);
+            });
+            let records = fasta::parse(&text);
+            score_fasta(&records, &motif_range, args.scoring.gap_penalty);
         }
-        return;
     }
+}
 
-    // Run both GA algorithms on all motifs for comparison
-    println!("=== COMPARATIVE ANALYSIS: Normal GA vs NeighbourGA ===");
-    println!("Running on all {} motifs\n", peptide::MOTIFS.len());
+/// Loads the energy tables, sets the fitness function up and selects the
+/// motif(s); returns the indices of the motifs to work on.
+fn configure_scoring(args: &ScoringArgs) -> Vec<usize> {
+    if let Some(path) = &args.nepre_file {
+        if let Err(e) = nepre::set_matrix(path) {
+            eprintln!("error: --nepre-file: {}", e);
+            std::process::exit(1);
+        }
+    }
+    if let Some(path) = &args.nepre_triplet_file {
+        if let Err(e) = nepre::set_triplet_matrix(path) {
+            eprintln!("error: --nepre-triplet-file: {}", e);
+            std::process::exit(1);
+        }
+    }
+    if args.nepre_symmetric {
+        nepre::symmetrize();
+    } else if nepre::active_asymmetry() > nepre::ASYMMETRY_TOLERANCE {
+        eprintln!(
+            "warning: NEPRE matrix is asymmetric (max |M[a][b] - M[b][a]| = {:.3}); \
+             pair energies depend on direction, see --nepre-symmetric",
+            nepre::active_asymmetry()
+        );
+    }
 
-    // Results storage
-    let mut normal_ga_results = Vec::new();
-    let mut neighbour_ga_results = Vec::new();
+    peptide::set_use_best_motif(args.best_motif);
+    PeptideProblem::set_scoring_mode(match args.scoring {
+        ScoringArg::Positional => peptide::ScoringMode::Positional,
+        ScoringArg::Global => peptide::ScoringMode::Global {
+            gap: args.gap_penalty,
+        },
+        ScoringArg::Local => peptide::ScoringMode::LocalAlignment {
+            gap: args.gap_penalty,
+        },
+    });
+    let weights = [args.blosum_weight, args.nepre_weight, args.charge_weight];
+    if weights.iter().any(|w| !w.is_finite()) || args.target_charge.is_some_and(|c| !c.is_finite())
+    {
+        eprintln!(
+            "error: --blosum-weight, --nepre-weight, --charge-weight and --target-charge must be finite"
+        );
+        std::process::exit(1);
+    }
+    peptide::set_fitness_weights(peptide::FitnessWeights {
+        blosum: args.blosum_weight,
+        nepre: args.nepre_weight,
+        charge: args.charge_weight,
+        target_charge: args.target_charge,
+    });
 
-    let motif_range: Vec<usize> = if let Some(m) = args.motif {
+    if let Some(path) = &args.pssm_file {
+        let pssm = pssm::load(path).unwrap_or_else(|e| {
+            eprintln!("error: --pssm-file: {}", e);
+            std::process::exit(1);
+        });
+        // the consensus stands in for the motif in design length and reports
+        let consensus = peptide_opt::output::sequence_string(&pssm::consensus(&pssm));
+        PeptideProblem::set_scoring_mode(peptide::ScoringMode::Pssm(pssm));
+        let idx = peptide::add_motif(&consensus).expect("consensus is a valid sequence");
+        vec![idx]
+    } else if let Some(seq) = &args.motif_seq {
+        match peptide::add_motif(seq) {
+            Ok(idx) => vec![idx],
+            Err(e) => {
+                eprintln!("error: --motif-seq: {}", e);
+                std::process::exit(1);
+            }
+        }
+    } else if let Some(m) = args.motif {
+        if let Err(e) = peptide::set_motif(m) {
+            eprintln!("error: --motif: {}", e);
+            std::process::exit(1);
+        }
         vec![m]
     } else {
         (0..peptide::MOTIFS.len()).collect()
+    }
+}
+
+/// Scores one sequence against the selected motif(s).
+fn evaluate(args: &EvaluateArgs) {
+    let motif_range = configure_scoring(&args.scoring);
+    let seq = peptide::parse_sequence(&args.sequence).unwrap_or_else(|e| {
+        match e {
+            peptide::MotifError::Empty => eprintln!("error: peptide sequence is empty"),
+            e => eprintln!("error: {}", e),
+        }
+        std::process::exit(1);
+    });
+    for &motif_idx in &motif_range {
+        peptide::set_motif(motif_idx).expect("motif index checked");
+        let b = peptide::combined_fitness_breakdown(&seq);
+        println!(
+            "Motif {} ({}): fitness {:.4} = BLOSUM {:.4} + NEPRE {:.4} + charge {:.4}",
+            motif_idx,
+            peptide::motif_string(motif_idx),
+            b.total,
+            b.blosum,
+            b.nepre,
+            b.charge
+        );
+    }
+}
+
+/// Times `combined_fitness` on random peptides of each selected motif's length.
+fn bench(args: &BenchArgs) {
+    use peptide_opt::problem::TSProblem;
+    use rand::SeedableRng;
+
+    let motif_range = configure_scoring(&args.scoring);
+    println!(
+        "{:<3} {:<25} {:>12} {:>14}",
+        "ID", "Motif", "Time (s)", "Evals/s"
+    );
+    for motif_idx in motif_range {
+        peptide::set_motif(motif_idx).expect("motif index checked");
+        let mut rng = rand::rngs::StdRng::seed_from_u64(args.seed);
+        let peptides: Vec<Vec<u8>> = (0..args.evaluations.min(1000))
+            .map(|_| PeptideProblem::random_individual(&mut rng))
+            .collect();
+
+        let started = std::time::Instant::now();
+        let mut total = 0.0;
+        for i in 0..args.evaluations as usize {
+            total += combined_fitness(&peptides[i % peptides.len()]);
+        }
+        let seconds = started.elapsed().as_secs_f64();
+        // keep the evaluations from being optimised away
+        std::hint::black_box(total);
+
+        println!(
+            "{:<3} {:<25} {:>12.4} {:>14.0}",
+            motif_idx,
+            peptide::motif_string(motif_idx),
+            seconds,
+            args.evaluations as f64 / seconds
+        );
+    }
+}
+
+/// Runs the selected optimisers on every selected motif and compares them.
+fn run(args: &RunArgs) {
+    if let Err(e) = peptide_opt::parallel::set_threads(args.threads) {
+        eprintln!("error: --threads: {}", e);
+        std::process::exit(1);
+    }
+    set_verbosity(if args.quiet {
+        Verbosity::Quiet
+    } else if args.verbose {
+        Verbosity::Verbose
+    } else {
+        Verbosity::Normal
+    });
+    let motif_range = configure_scoring(&args.scoring);
+
+    let weights = [
+        args.subst_weight,
+        args.swap_weight,
+        args.invert_weight,
+        args.relocate_weight,
+        args.insert_weight,
+        args.delete_weight,
+    ];
+    if weights.iter().any(|w| !w.is_finite() || *w < 0.0)
+        || args.subst_weight + args.swap_weight <= 0.0
+    {
+        eprintln!(
+            "error: move weights must be non-negative, with --subst-weight or --swap-weight positive"
+        );
+        std::process::exit(1);
+    }
+    peptide::set_neighbourhood_weights(peptide::NeighbourhoodWeights {
+        subst: args.subst_weight,
+        swap: args.swap_weight,
+        invert: args.invert_weight,
+        relocate: args.relocate_weight,
+        insert: args.insert_weight,
+        delete: args.delete_weight,
+    });
+
+    if args.stagnation_limit == Some(0)
+        || !(args.immigrant_fraction > 0.0 && args.immigrant_fraction <= 1.0)
+    {
+        eprintln!("error: --stagnation-limit must be positive and --immigrant-fraction in (0, 1]");
+        std::process::exit(1);
+    }
+    if args
+        .aspiration_margin
+        .is_some_and(|m| !m.is_finite() || m < 0.0)
+    {
+        eprintln!("error: --aspiration-margin must be non-negative");
+        std::process::exit(1);
+    }
+    if args.max_seconds.is_some_and(|s| !s.is_finite() || s < 0.0) {
+        eprintln!("error: --max-seconds must be a non-negative number");
+        std::process::exit(1);
+    }
+    if !(0.0..=1.0).contains(&args.mutation_prob) {
+        eprintln!("error: --mutation-prob must be in [0, 1]");
+        std::process::exit(1);
+    }
+    if !(args.replacement_fraction > 0.0 && args.replacement_fraction <= 1.0) {
+        eprintln!("error: --replacement-fraction must be in (0, 1]");
+        std::process::exit(1);
+    }
+    let temperature_ok = args.boltzmann_temperature.is_finite() && args.boltzmann_temperature > 0.0;
+    let cooling_ok = args.boltzmann_cooling > 0.0 && args.boltzmann_cooling <= 1.0;
+    if !temperature_ok || !cooling_ok {
+        eprintln!(
+            "error: --boltzmann-temperature must be positive and --boltzmann-cooling in (0, 1]"
+        );
+        std::process::exit(1);
+    }
+
+    if args.islands > 1 && args.pop_size / (args.islands as usize) < 2 {
+        eprintln!("error: --pop-size must give every one of the --islands at least 2 individuals");
+        std::process::exit(1);
+    }
+    let alpha_ok = args.sa_alpha > 0.0 && args.sa_alpha < 1.0;
+    let t_end_ok = args.sa_t_end.is_finite() && args.sa_t_end > 0.0;
+    if args.sa && (!alpha_ok || !t_end_ok || args.sa_t_start < args.sa_t_end) {
+        eprintln!(
+            "error: --sa-alpha must be in (0, 1), --sa-t-end positive and --sa-t-start at least --sa-t-end"
+        );
+        std::process::exit(1);
+    }
+    if args.reactive_tabu && args.tabu_min_len > args.tabu_max_len {
+        eprintln!("error: --tabu-min-len must be at most --tabu-max-len");
+        std::process::exit(1);
+    }
+
+    if let Some(len) = args.length {
+        peptide::set_target_len(Some(len as usize));
+        // cyclic positional matching is meaningless against a shorter motif
+        if PeptideProblem::scoring_mode() == peptide::ScoringMode::Positional {
+            eprintln!("note: --length given, using local alignment scoring");
+            PeptideProblem::set_scoring_mode(peptide::ScoringMode::LocalAlignment {
+                gap: args.scoring.gap_penalty,
+            });
+        }
+    }
+    if args.variable_length {
+        if args.min_len == 0 || args.min_len > args.max_len {
+            eprintln!("error: --min-len must be positive and at most --max-len");
+            std::process::exit(1);
+        }
+        peptide::set_variable_length(true);
+        peptide::set_length_bounds(args.min_len, args.max_len);
+    }
+
+    // Run both GA algorithms on all motifs for comparison
+    say!(
+        Normal,
+        "=== COMPARATIVE ANALYSIS: Normal GA vs NeighbourGA ==="
+    );
+    say!(Normal, "Running on all {} motifs\n", peptide::MOTIFS.len());
+
+    let mut csv = args.csv.as_ref().map(|path| {
+        CsvWriter::create(path).unwrap_or_else(|e| {
+            eprintln!("error: --csv {}: {}", path.display(), e);
+            std::process::exit(1);
+        })
+    });
+
+    let mut fasta_out = args.fasta_out.as_ref().map(|path| {
+        File::create(path).map(BufWriter::new).unwrap_or_else(|e| {
+            eprintln!("error: --fasta-out {}: {}", path.display(), e);
+            std::process::exit(1);
+        })
+    });
+
+    #[cfg(feature = "serde")]
+    let mut report = peptide_opt::output::RunReport {
+        seed: args.seed,
+        weights: serde_json::to_value(peptide::fitness_weights()).unwrap(),
+        motifs: Vec::new(),
     };
+    #[cfg(feature = "serde")]
+    let mut reports = Vec::new();
+
+    // Results storage: per motif, the algorithms' results of every run
+    let mut results: Vec<(usize, String, Vec<Vec<AlgoRun>>)> = Vec::new();
 
     for motif_idx in motif_range {
-        peptide::set_motif(motif_idx);
-        let motif_str = std::str::from_utf8(peptide::MOTIFS[motif_idx]).unwrap_or("Invalid UTF-8");
-
-        println!("=== MOTIF {}: {} ===", motif_idx, motif_str);
-
-        // ============= NORMAL GA =============
-        let ga = GeneticAlgorithm {
-            population_size: args.pop_size,
-            generations: args.generations,
-            crossover_prob: args.crossover_prob,
-            crossover: genetic::Crossover::SinglePoint,
-            mutation_prob: args.mutation_prob,
-            tournament_size: args.tournament_size,
-        };
+        peptide::set_motif(motif_idx).expect("motif index checked");
+        let motif_str = peptide::motif_string(motif_idx);
 
-        let start_time = std::time::Instant::now();
-        let (normal_best, _normal_progress) = ga.run(args.seed + motif_idx as u64);
-        let normal_time = start_time.elapsed();
-        let normal_fitness = combined_fitness(&normal_best);
-
-        // ============= NEIGHBOUR GA =============
-        let problem = PeptideProblem {};
-        let neigh_cfg = NeighCfg {
-            pop_size: args.pop_size,
-            crossover_p: args.crossover_prob as f32,
-            mutation_p: args.mutation_prob as f32,
-            smart_xover: true,
-            max_gens: args.generations,
+        say!(Normal, "=== MOTIF {}: {} ===", motif_idx, motif_str);
+
+        let mut motif_runs = Vec::new();
+        for run in 0..args.runs {
+            let seed = args.seed.wrapping_add(run).wrapping_add(motif_idx as u64);
+            // tell repeated runs apart in the per-run outputs
+            let label = |name: &str| {
+                if args.runs > 1 {
+                    format!("{} (seed {})", name, seed)
+                } else {
+                    name.to_string()
+                }
+            };
+            if args.runs > 1 {
+                say!(
+                    Normal,
+                    "--- Run {}/{} (seed {}) ---",
+                    run + 1,
+                    args.runs,
+                    seed
+                );
+            }
+
+            let algo_runs = run_algorithms(args, motif_idx, seed, &mut csv, &label);
+
+            // ============= RESULTS =============
+            for r in &algo_runs {
+                say!(Normal, "{}:", r.name);
+                say!(
+                    Normal,
+                    "  Best sequence (fitness={:.4}): {}  (pI: {:.2})",
+                    r.fitness,
+                    peptide_opt::output::sequence_string(&r.best),
+                    peptide::isoelectric_point(&r.best)
+                );
+                say!(
+                    Verbose,
+                    "  Time: {:.2}s, found at {} {}, {} fitness evaluations",
+                    r.time.as_secs_f32(),
+                    r.unit,
+                    r.found_at,
+                    r.evaluations
+                );
+                if verbosity() >= Verbosity::Verbose {
+                    let b = peptide::combined_fitness_breakdown(&r.best);
+                    println!(
+                        "  Breakdown: BLOSUM {:.4} + NEPRE {:.4} + charge {:.4} = {:.4}",
+                        b.blosum, b.nepre, b.charge, b.total
+                    );
+                }
+                if let Some(n) = r.duplicates {
+                    say!(Normal, "  Duplicates replaced: {}", n);
+                }
+                if let Some(n) = r.immigrants {
+                    say!(Normal, "  Immigrants injected: {}", n);
+                }
+                if let Some(n) = r.plateau {
+                    say!(Normal, "  Plateau: {} generations without improvement", n);
+                }
+                if let Some((seq, share)) = &r.consensus {
+                    let share: Vec<String> = share.iter().map(|s| format!("{:.2}", s)).collect();
+                    say!(
+                        Normal,
+                        "  Consensus: {}  (agreement {})",
+                        peptide_opt::output::sequence_string(seq),
+                        share.join(" ")
+                    );
+                }
+                if !r.clusters.is_empty() {
+                    say!(
+                        Normal,
+                        "  Clusters in final population: {}",
+                        r.clusters.len()
+                    );
+                    for (leader, size) in &r.clusters {
+                        say!(
+                            Normal,
+                            "    {:<24} {:>10.4}  ({} individuals)",
+                            peptide_opt::output::sequence_string(leader),
+                            combined_fitness(leader),
+                            size
+                        );
+                    }
+                }
+                if !r.top.is_empty() {
+                    say!(Normal, "  Top {} distinct:", r.top.len());
+                    for (rank, (seq, f)) in r.top.iter().enumerate() {
+                        say!(
+                            Normal,
+                            "    {:>2}. {:<24} {:>10.4}",
+                            rank + 1,
+                            peptide_opt::output::sequence_string(seq),
+                            f
+                        );
+                    }
+                }
+                let report = peptide::validity_report_with(&r.best, &validity_config(args));
+                if !report.is_valid() {
+                    say!(Normal, "  Implausible: {}", report);
+                }
+                if args.scoring.best_motif {
+                    print_closest_motif(&r.best);
+                }
+            }
+
+            // Performance comparison (lower fitness = better)
+            let scores: Vec<_> = algo_runs.iter().map(|r| (r.name, r.fitness)).collect();
+            let win = winner(&scores);
+            let improvement = match win {
+                Some(name) => {
+                    let best = scores.iter().find(|(n, _)| *n == name).unwrap().1;
+                    let runner_up = scores
+                        .iter()
+                        .filter(|(n, _)| *n != name)
+                        .map(|&(_, f)| f)
+                        .fold(f32::INFINITY, f32::min);
+                    format!("{} (lower by {:.4})", name, runner_up - best)
+                }
+                None => "Tie".to_string(),
+            };
+            say!(Normal, "  Winner: {}", improvement);
+            // verbose output already broke down every algorithm's result
+            if args.breakdown && verbosity() < Verbosity::Verbose {
+                if let Some(r) = win.and_then(|name| algo_runs.iter().find(|r| r.name == name)) {
+                    let b = peptide::combined_fitness_breakdown(&r.best);
+                    say!(
+                        Normal,
+                        "  Breakdown: BLOSUM {:.4} + NEPRE {:.4} + charge {:.4} = {:.4}",
+                        b.blosum,
+                        b.nepre,
+                        b.charge,
+                        b.total
+                    );
+                }
+            }
+            say!(Normal);
+
+            if let Some(out) = &mut fasta_out {
+                let written = algo_runs.iter().try_for_each(|r| {
+                    let header = fasta::design_header(motif_idx, &label(r.name), r.fitness);
+                    fasta::write_record(out, &header, &r.best)
+                });
+                if let Err(e) = written.and_then(|_| out.flush()) {
+                    eprintln!("error: --fasta-out: {}", e);
+                    std::process::exit(1);
+                }
+            }
+
+            #[cfg(feature = "serde")]
+            reports.extend(algo_runs.iter().map(|r| algorithm_report(r, seed)));
+
+            motif_runs.push(algo_runs);
+        }
+
+        #[cfg(feature = "serde")]
+        report.motifs.push(peptide_opt::output::MotifReport {
+            motif: motif_idx,
+            sequence: motif_str.to_string(),
+            results: std::mem::take(&mut reports),
+        });
+
+        results.push((motif_idx, motif_str.to_string(), motif_runs));
+    }
+
+    #[cfg(feature = "serde")]
+    if let Some(path) = &args.json {
+        if let Err(e) = report.write(path) {
+            eprintln!("error: --json {}: {}", path.display(), e);
+            std::process::exit(1);
+        }
+    }
+
+    // ============= SUMMARY =============
+    // every motif and run has the same algorithms, in the same order
+    let names: Vec<&str> = results[0].2[0].iter().map(|r| r.name).collect();
+    // per motif and algorithm, the final fitness and time of every run
+    let fitness_of = |runs: &[Vec<AlgoRun>], j: usize| -> Vec<f64> {
+        runs.iter().map(|algos| algos[j].fitness as f64).collect()
+    };
+    let normalized_of = |runs: &[Vec<AlgoRun>], j: usize| -> Vec<f64> {
+        runs.iter()
+            .map(|algos| algos[j].normalized as f64)
+            .collect()
+    };
+    let seconds_of = |runs: &[Vec<AlgoRun>], j: usize| -> Vec<f64> {
+        runs.iter()
+            .map(|algos| algos[j].time.as_secs_f64())
+            .collect()
+    };
+
+    println!("=== SUMMARY ===");
+    if args.runs > 1 {
+        println!("(mean final fitness over {} runs)", args.runs);
+    }
+    let mut header = format!("{:<3} {:<25} ", "ID", "Motif");
+    for name in &names {
+        header += &format!("{:<15} ", name);
+        if args.normalized {
+            header += &format!("{:<15} ", format!("{}/res", name));
+        }
+    }
+    let columns = if args.normalized { 2 } else { 1 } * names.len();
+    println!("{}{:<20}", header, "Better (min)");
+    println!("{}", "-".repeat(48 + 16 * columns));
+
+    let mut wins = vec![0; names.len()];
+    let mut ties = 0;
+
+    for (motif_idx, motif_name, runs) in &results {
+        let means: Vec<f32> = (0..names.len())
+            .map(|j| stats::mean(&fitness_of(runs, j)) as f32)
+            .collect();
+        let scores: Vec<_> = names.iter().copied().zip(means.iter().copied()).collect();
+
+        let winner = match winner(&scores) {
+            Some(name) => {
+                wins[names.iter().position(|&n| n == name).unwrap()] += 1;
+                name
+            }
+            None => {
+                ties += 1;
+                "Tie"
+            }
         };
 
-        let start_time = std::time::Instant::now();
-        let mut neigh_ga = NeighbourGA::new(&problem, neigh_cfg);
-        let neighbour_best = neigh_ga.run();
-        let neighbour_time = start_time.elapsed();
-        let neighbour_fitness = combined_fitness(&neighbour_best);
-
-        // ============= RESULTS =============
-        println!("Normal GA:");
-        print!("  Best sequence (fitness={:.4}): ", normal_fitness);
-        for &aa in normal_best.iter() {
-            print!("{}", data::AA_LETTERS[aa as usize] as char);
-        }
-        println!("  (Time: {:.2}s)", normal_time.as_secs_f32());
-
-        println!("NeighbourGA:");
-        print!("  Best sequence (fitness={:.4}): ", neighbour_fitness);
-        for &aa in neighbour_best.iter() {
-            print!("{}", data::AA_LETTERS[aa as usize] as char);
-        }
-        println!("  (Time: {:.2}s)", neighbour_time.as_secs_f32());
-
-        // Performance comparison
-        // Performance comparison (lower fitness = better)
-        let improvement = if normal_fitness < neighbour_fitness {
-            format!(
-                "Normal GA (lower by {:.4})",
-                neighbour_fitness - normal_fitness
-            )
-        } else if neighbour_fitness < normal_fitness {
-            format!(
-                "NeighbourGA (lower by {:.4})",
-                normal_fitness - neighbour_fitness
-            )
+        let motif_name = if motif_name.len() > 24 {
+            &motif_name[..24]
         } else {
-            "Tie".to_string()
+            motif_name
         };
-        println!("  Winner: {}\n", improvement);
+        let mut row = format!("{:<3} {:<25} ", motif_idx, motif_name);
+        for (j, fit) in means.iter().enumerate() {
+            row += &format!("{:<15.4} ", fit);
+            if args.normalized {
+                row += &format!("{:<15.4} ", stats::mean(&normalized_of(runs, j)));
+            }
+        }
+        println!("{}{:<20}", row, winner);
+    }
 
-        // Store results for summary
-        normal_ga_results.push((
-            motif_idx,
-            motif_str.to_string(),
-            normal_fitness,
-            normal_time,
-        ));
-        neighbour_ga_results.push((
+    say!(Normal, "\n=== OVERALL STATISTICS ===");
+    for (name, wins) in names.iter().zip(&wins) {
+        say!(Normal, "{:<19}{}", format!("{} wins:", name), wins);
+    }
+    say!(Normal, "Ties:              {}", ties);
+
+    for (j, name) in names.iter().enumerate() {
+        let seconds: Vec<f64> = results
+            .iter()
+            .flat_map(|(_, _, runs)| seconds_of(runs, j))
+            .collect();
+        say!(
+            Verbose,
+            "{:<27}{:.2}s",
+            format!("Average {} time:", name),
+            stats::mean(&seconds) as f32
+        );
+    }
+
+    // Best overall fitness for each algorithm
+    say!(Normal);
+    for (j, name) in names.iter().enumerate() {
+        let (motif_idx, motif_name, r) = results
+            .iter()
+            .flat_map(|(idx, motif, runs)| runs.iter().map(move |algos| (idx, motif, &algos[j])))
+            .min_by(|a, b| a.2.fitness.partial_cmp(&b.2.fitness).unwrap())
+            .unwrap();
+        say!(
+            Normal,
+            "Best {} result: Motif {} ({}) with fitness {:.4}, {} of {} positions off the motif",
+            name,
             motif_idx,
-            motif_str.to_string(),
-            neighbour_fitness,
-            neighbour_time,
-        ));
+            motif_name,
+            r.fitness,
+            r.hamming,
+            peptide::strip_blanks(&r.best).len()
+        );
     }
 
-    // ============= SUMMARY =============
-    println!("=== SUMMARY ===");
-    println!(
-        "{:<3} {:<25} {:<15} {:<15} {:<20}",
-        "ID", "Motif", "Normal GA", "NeighbourGA", "Better (min)"
+    // Is NeighbourGA's edge over the normal GA more than noise? Pair their
+    // final fitness by motif and seed.
+    let paired = |j: usize| -> Vec<f64> {
+        results
+            .iter()
+            .flat_map(|(_, _, runs)| fitness_of(runs, j))
+            .collect()
+    };
+    let (normal, neighbour) = (paired(0), paired(1));
+    say!(
+        Normal,
+        "\nNeighbourGA vs Normal GA: Wilcoxon signed-rank p = {:.4} over {} paired runs",
+        stats::wilcoxon_signed_rank(&neighbour, &normal),
+        normal.len()
     );
-    println!("{}", "-".repeat(80));
 
-    let mut normal_wins = 0;
-    let mut neighbour_wins = 0;
-    let mut ties = 0;
+    if args.runs > 1 {
+        say!(Normal, "\n=== STATISTICS OVER {} RUNS ===", args.runs);
+        say!(
+            Normal,
+            "{:<3} {:<15} {:>10} {:>10} {:>10} {:>10} {:>10}",
+            "ID",
+            "Algorithm",
+            "Mean",
+            "Std",
+            "Best",
+            "Worst",
+            "Time (s)"
+        );
+        say!(Normal, "{}", "-".repeat(73));
+        for (motif_idx, _, runs) in &results {
+            for (j, name) in names.iter().enumerate() {
+                let s = stats::summarize(&fitness_of(runs, j)).unwrap();
+                say!(
+                    Normal,
+                    "{:<3} {:<15} {:>10.4} {:>10.4} {:>10.4} {:>10.4} {:>10.2}",
+                    motif_idx,
+                    name,
+                    s.mean,
+                    s.std_dev,
+                    s.best,
+                    s.worst,
+                    stats::mean(&seconds_of(runs, j))
+                );
+            }
+        }
+    }
+}
 
-    for i in 0..normal_ga_results.len() {
-        let (motif_idx, motif_name, normal_fit, _normal_time) = &normal_ga_results[i];
-        let (_, _, neighbour_fit, _neighbour_time) = &neighbour_ga_results[i];
+/// Runs every selected algorithm once on the current motif.
+fn run_algorithms(
+    args: &RunArgs,
+    motif_idx: usize,
+    seed: u64,
+    csv: &mut Option<CsvWriter<BufWriter<File>>>,
+    label: &impl Fn(&str) -> String,
+) -> Vec<AlgoRun> {
+    let init = match args.init {
+        InitArg::Uniform => peptide::InitMode::Uniform,
+        InitArg::Background => peptide::InitMode::Background,
+    };
+    let time_budget = args.max_seconds.map(std::time::Duration::from_secs_f64);
+    let climb_mode = match args.climb_mode {
+        ClimbArg::First => ClimbMode::First,
+        ClimbArg::Best => ClimbMode::Best,
+    };
+    let mut runs = Vec::new();
+
+    // ============= NORMAL GA =============
+    let immigrants = args
+        .stagnation_limit
+        .map(|stagnation_limit| genetic::Immigrants {
+            stagnation_limit,
+            fraction: args.immigrant_fraction,
+        });
+    let build_ga = |pop_size| {
+        GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(pop_size)
+            .generations(args.generations)
+            .crossover_prob(args.crossover_prob)
+            .crossover(args.crossover)
+            .mutation_prob(args.mutation_prob)
+            .selection(match args.selection {
+                SelectionArg::Tournament => genetic::Selection::Tournament(args.tournament_size),
+                SelectionArg::Roulette => genetic::Selection::Roulette,
+                SelectionArg::Rank => genetic::Selection::Rank,
+                SelectionArg::Sus => genetic::Selection::Sus,
+                SelectionArg::Boltzmann => genetic::Selection::Boltzmann {
+                    temperature: args.boltzmann_temperature,
+                    cooling: args.boltzmann_cooling,
+                },
+            })
+            .adaptive_mutation(args.adaptive_mutation.then_some(genetic::AdaptiveMutation {
+                low: args.mutation_prob,
+                high: args.mutation_high,
+                threshold: args.diversity_threshold,
+            }))
+            .patience(args.patience, args.min_improvement)
+            .time_budget(time_budget)
+            .init(init)
+            .dedup(args.dedup)
+            .hall_of_fame(args.top.unwrap_or(1))
+            .sharing(args.sigma_share.map(|sigma_share| genetic::FitnessSharing {
+                sigma_share,
+                alpha: args.sharing_alpha,
+            }))
+            .immigrants(immigrants)
+            .build()
+    };
+    let mut ga = build_ga(args.pop_size);
+
+    let start_time = std::time::Instant::now();
+    #[cfg(not(feature = "serde"))]
+    let normal_run = ga.run(seed);
+    #[cfg(feature = "serde")]
+    let normal_run = match &args.checkpoint {
+        Some(prefix) => run_checkpointed(&mut ga, seed, prefix, motif_idx, args.resume),
+        None => ga.run(seed),
+    };
+    let time = start_time.elapsed();
+    write_csv(csv, |w| {
+        w.write_ga(&label("Normal GA"), motif_idx, &normal_run)
+    });
+    let duplicates = normal_run.duplicates.iter().sum();
+    let top = match args.top {
+        Some(_) => normal_run
+            .hall_of_fame
+            .iter()
+            .map(|(s, f)| (s.clone(), *f as f32))
+            .collect(),
+        None => Vec::new(),
+    };
+    let immigrant_total = normal_run.immigrants.iter().sum();
+    runs.push(AlgoRun {
+        duplicates: args.dedup.then_some(duplicates),
+        immigrants: immigrants.map(|_| immigrant_total),
+        plateau: Some(ga_plateau(&normal_run.progress)),
+        consensus: args
+            .consensus
+            .then(|| diversity::consensus(&normal_run.population)),
+        clusters: normal_run.clusters,
+        top,
+        ..AlgoRun::new(
+            "Normal GA",
+            normal_run.best,
+            time,
+            ("generation", normal_run.best_found_at),
+            normal_run.evaluations,
+            #[cfg(feature = "serde")]
+            serde_json::to_value(&ga).unwrap(),
+        )
+    });
 
-        let winner = if normal_fit < neighbour_fit {
-            normal_wins += 1;
-            "Normal GA"
-        } else if neighbour_fit < normal_fit {
-            neighbour_wins += 1;
-            "NeighbourGA"
+    // ============= NEIGHBOUR GA =============
+    let neigh_cfg = NeighCfg {
+        pop_size: args.pop_size,
+        crossover_p: args.crossover_prob as f32,
+        mutation_p: args.mutation_prob as f32,
+        smart_xover: true,
+        max_gens: args.generations,
+        seed,
+        fitness_cache: args.fitness_cache,
+        patience: args.patience,
+        min_improvement: args.min_improvement as f32,
+        time_budget,
+        validity: validity_config(args),
+        constraint_mode: match args.constraint_mode {
+            ConstraintArg::Reject => ConstraintMode::Reject,
+            ConstraintArg::Repair => ConstraintMode::Repair,
+            ConstraintArg::Penalty => ConstraintMode::Penalty,
+        },
+        violation_penalty: args.violation_penalty,
+        init,
+        guided_mutation: args.guided_mutation,
+        dedup: args.dedup,
+        local_search: if args.baldwinian {
+            LocalSearchMode::Baldwinian
         } else {
-            ties += 1;
-            "Tie"
-        };
+            LocalSearchMode::Lamarckian
+        },
+        climb_mode,
+        climb_until_stable: args.climb_until_stable,
+        local_search_prob: args.local_search_prob,
+        short_pep_prob: args.short_pep_prob,
+        long_pep_prob: args.long_pep_prob,
+        short_pep_len: args.short_pep_len,
+        hall_of_fame: args.top.unwrap_or(1),
+        replacement_fraction: args.replacement_fraction,
+        immigrants,
+    };
+
+    let start_time = std::time::Instant::now();
+    let mut neigh_ga = NeighbourGA::<PeptideProblem>::new(neigh_cfg.clone());
+    let neighbour_run = neigh_ga.run();
+    let time = start_time.elapsed();
+    write_csv(csv, |w| {
+        w.write_neighbour(&label("NeighbourGA"), motif_idx, &neighbour_run)
+    });
+    let duplicates = neighbour_run.duplicates.iter().sum();
+    let top = match args.top {
+        Some(_) => neighbour_run.hall_of_fame.clone(),
+        None => Vec::new(),
+    };
+    let immigrant_total = neighbour_run.immigrants.iter().sum();
+    runs.push(AlgoRun {
+        duplicates: args.dedup.then_some(duplicates),
+        immigrants: immigrants.map(|_| immigrant_total),
+        plateau: Some(stats::plateau_length(&neighbour_run.progress)),
+        consensus: args
+            .consensus
+            .then(|| diversity::consensus(neigh_ga.population())),
+        top,
+        ..AlgoRun::new(
+            "NeighbourGA",
+            neighbour_run.best,
+            time,
+            ("generation", neighbour_run.best_found_at),
+            neighbour_run.evaluations,
+            #[cfg(feature = "serde")]
+            serde_json::to_value(&neigh_cfg).unwrap(),
+        )
+    });
+
+    // ============= ISLAND GA =============
+    if args.islands > 1 {
+        let k = args.islands as usize;
+        let mut model = IslandGA::new(
+            (0..k).map(|_| build_ga(args.pop_size / k)).collect(),
+            args.migration_interval,
+            args.migrants,
+        );
+
+        let start_time = std::time::Instant::now();
+        let island_run = model.run(seed);
+        let time = start_time.elapsed();
+        for (i, run) in island_run.islands.iter().enumerate() {
+            let name = label(&format!("Island GA {}", i + 1));
+            write_csv(csv, |w| w.write_ga(&name, motif_idx, run));
+        }
+        let best_island = &island_run.islands[island_run.best_island];
+        runs.push(AlgoRun {
+            plateau: Some(ga_plateau(&best_island.progress)),
+            consensus: args.consensus.then(|| {
+                let population: Vec<Vec<u8>> = island_run
+                    .islands
+                    .iter()
+                    .flat_map(|r| r.population.iter().cloned())
+                    .collect();
+                diversity::consensus(&population)
+            }),
+            ..AlgoRun::new(
+                "Island GA",
+                island_run.best.clone(),
+                time,
+                ("generation", best_island.best_found_at),
+                island_run.evaluations(),
+                #[cfg(feature = "serde")]
+                serde_json::to_value(&model).unwrap(),
+            )
+        });
+    }
+
+    // ============= SINGLE-SOLUTION SEARCHES =============
+    if args.tabu {
+        let mut ts =
+            TabuSearch::<PeptideProblem>::new(args.tabu_iters, args.neigh_size, args.tabu_len);
+        ts.time_budget = time_budget;
+        ts.reactive = args.reactive_tabu;
+        ts.min_len = args.tabu_min_len;
+        ts.max_len = args.tabu_max_len;
+        ts.reheat_every = args.reheat_every;
+        ts.diversify = args.tabu_diversify;
+        ts.diversify_after = args.diversify_after;
+        if args.tabu_state {
+            ts.mode = TabuMode::State;
+        }
+        if let Some(margin) = args.aspiration_margin {
+            ts.aspiration = AspirationMode::BetterThanCurrent(margin);
+        }
+
+        let start_time = std::time::Instant::now();
+        let tabu_run = ts.run(seed);
+        let time = start_time.elapsed();
+        write_csv(csv, |w| w.write_tabu(&label("Tabu"), motif_idx, &tabu_run));
+        runs.push(AlgoRun::new(
+            "Tabu",
+            tabu_run.best,
+            time,
+            ("iteration", tabu_run.best_found_at),
+            tabu_run.evaluations,
+            #[cfg(feature = "serde")]
+            serde_json::to_value(&ts).unwrap(),
+        ));
+    }
+    if args.sa {
+        let mut sa = SimulatedAnnealing::<PeptideProblem>::new(
+            args.sa_t_start,
+            args.sa_t_end,
+            args.sa_alpha,
+        );
+        sa.time_budget = time_budget;
+
+        let start_time = std::time::Instant::now();
+        let sa_run = sa.run(seed);
+        let time = start_time.elapsed();
+        write_csv(csv, |w| {
+            w.write_annealing(&label("Annealing"), motif_idx, &sa_run)
+        });
+        runs.push(AlgoRun::new(
+            "Annealing",
+            sa_run.best,
+            time,
+            ("iteration", sa_run.best_found_at),
+            sa_run.evaluations,
+            #[cfg(feature = "serde")]
+            serde_json::to_value(&sa).unwrap(),
+        ));
+    }
+    if args.hill_climb {
+        let mut hc = HillClimb::<PeptideProblem>::new(args.neigh_size, args.climb_steps);
+        hc.mode = climb_mode;
+        hc.time_budget = time_budget;
+
+        let start_time = std::time::Instant::now();
+        let hc_run = hc.run(seed);
+        let time = start_time.elapsed();
+        write_csv(csv, |w| {
+            w.write_hill_climb(&label("HillClimb"), motif_idx, &hc_run)
+        });
+        runs.push(AlgoRun::new(
+            "HillClimb",
+            hc_run.best,
+            time,
+            ("iteration", hc_run.best_found_at),
+            hc_run.evaluations,
+            #[cfg(feature = "serde")]
+            serde_json::to_value(&hc).unwrap(),
+        ));
+    }
+    runs
+}
+
+fn score_fasta(records: &[fasta::FastaRecord], motif_range: &[usize], gap_penalty: i32) {
+    for &motif_idx in motif_range {
+        peptide::set_motif(motif_idx).expect("motif index checked");
+        let motif_len = peptide::current_motif_len();
+
+        // positional scoring is meaningless for peptides of another length
+        let mismatched = records.iter().any(|r| r.sequence.len() != motif_len);
+        if mismatched && PeptideProblem::scoring_mode() == peptide::ScoringMode::Positional {
+            eprintln!(
+                "note: motif {}: lengths differ from the motif, using global alignment scoring",
+                motif_idx
+            );
+            PeptideProblem::set_scoring_mode(peptide::ScoringMode::Global { gap: gap_penalty });
+        }
+
+        let (scored, rejected) = fasta::score_records(records);
+        for (id, e) in &rejected {
+            eprintln!("warning: skipping {}: {}", id, e);
+        }
 
         println!(
-            "{:<3} {:<25} {:<15.4} {:<15.4} {:<20}",
+            "=== MOTIF {}: {} ===",
             motif_idx,
-            if motif_name.len() > 24 {
-                &motif_name[..24]
-            } else {
-                motif_name
-            },
-            normal_fit,
-            neighbour_fit,
-            winner
+            peptide::motif_string(motif_idx)
         );
+        println!(
+            "{:<5} {:<30} {:>12} {:<6}",
+            "Rank", "Id", "Fitness", "Valid"
+        );
+        for (rank, r) in scored.iter().enumerate() {
+            println!(
+                "{:<5} {:<30} {:>12.4} {:<6}",
+                rank + 1,
+                r.id,
+                r.fitness,
+                if r.valid { "yes" } else { "no" }
+            );
+        }
+        println!();
     }
+}
 
-    println!("\n=== OVERALL STATISTICS ===");
-    println!("Normal GA wins:    {}", normal_wins);
-    println!("NeighbourGA wins:  {}", neighbour_wins);
-    println!("Ties:              {}", ties);
+fn write_csv(
+    csv: &mut Option<CsvWriter<BufWriter<File>>>,
+    write: impl FnOnce(&mut CsvWriter<BufWriter<File>>) -> std::io::Result<()>,
+) {
+    if let Some(w) = csv {
+        if let Err(e) = write(w).and_then(|_| w.flush()) {
+            eprintln!("error: --csv: {}", e);
+            std::process::exit(1);
+        }
+    }
+}
 
-    let avg_normal_time: f32 = normal_ga_results
-        .iter()
-        .map(|(_, _, _, t)| t.as_secs_f32())
-        .sum::<f32>()
-        / normal_ga_results.len() as f32;
-    let avg_neighbour_time: f32 = neighbour_ga_results
-        .iter()
-        .map(|(_, _, _, t)| t.as_secs_f32())
-        .sum::<f32>()
-        / neighbour_ga_results.len() as f32;
+/// Runs the normal GA, saving its state after every generation; with
+/// `resume`, an existing checkpoint is continued instead of starting over.
+#[cfg(feature = "serde")]
+fn run_checkpointed(
+    ga: &mut GeneticAlgorithm<PeptideProblem>,
+    seed: u64,
+    prefix: &std::path::Path,
+    motif_idx: usize,
+    resume: bool,
+) -> genetic::GaResult {
+    let mut name = prefix.as_os_str().to_owned();
+    name.push(format!("-motif{}-seed{}.json", motif_idx, seed));
+    let path = PathBuf::from(name);
+    let fail = |e: std::io::Error| -> ! {
+        eprintln!("error: --checkpoint {}: {}", path.display(), e);
+        std::process::exit(1);
+    };
 
-    println!("Average Normal GA time:    {:.2}s", avg_normal_time);
-    println!("Average NeighbourGA time:  {:.2}s", avg_neighbour_time);
+    let mut state = if resume && path.exists() {
+        ga.resume_from_checkpoint(&path).unwrap_or_else(|e| fail(e))
+    } else {
+        ga.start(seed)
+    };
+    while ga.step(&mut state) {
+        ga.save_checkpoint(&state, &path)
+            .unwrap_or_else(|e| fail(e));
+    }
+    ga.finish(state)
+}
 
-    // Best overall fitness for each algorithm
-    let best_normal = normal_ga_results
-        .iter()
-        .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap())
-        .unwrap();
-    let best_neighbour = neighbour_ga_results
+#[cfg(feature = "serde")]
+fn algorithm_report(run: &AlgoRun, seed: u64) -> peptide_opt::output::AlgorithmReport {
+    peptide_opt::output::AlgorithmReport {
+        algorithm: run.name.to_string(),
+        seed,
+        best: peptide_opt::output::sequence_string(&run.best),
+        fitness: run.fitness,
+        seconds: run.time.as_secs_f64(),
+        best_found_at: run.found_at,
+        evaluations: run.evaluations,
+        config: run.config.clone(),
+    }
+}
+
+/// Outcome of one algorithm on one motif.
+struct AlgoRun {
+    name: &'static str,
+    best: Vec<u8>,
+    fitness: f32,
+    /// `fitness` per residue of `best`.
+    normalized: f32,
+    /// Positions of `best` that differ from the motif.
+    hamming: usize,
+    time: std::time::Duration,
+    /// What the algorithm counts ("generation", "iteration") and at which
+    /// one `best` was found.
+    unit: &'static str,
+    found_at: usize,
+    evaluations: u64,
+    /// Offspring replaced by `--dedup` over the whole run (GAs only).
+    duplicates: Option<usize>,
+    /// Random immigrants injected over the whole run (`--stagnation-limit`).
+    immigrants: Option<usize>,
+    /// Trailing generations without improvement (GAs only).
+    plateau: Option<usize>,
+    /// Consensus of the final population and its per-position share
+    /// (`--consensus`).
+    consensus: Option<(Vec<u8>, Vec<f32>)>,
+    /// Leaders and sizes of the final population's niches (`--sigma-share`).
+    clusters: Vec<(Vec<u8>, usize)>,
+    /// Best distinct peptides of the run with their fitness (`--top`).
+    top: Vec<(Vec<u8>, f32)>,
+    #[cfg(feature = "serde")]
+    config: serde_json::Value,
+}
+
+impl AlgoRun {
+    fn new(
+        name: &'static str,
+        best: Vec<u8>,
+        time: std::time::Duration,
+        (unit, found_at): (&'static str, usize),
+        evaluations: u64,
+        #[cfg(feature = "serde")] config: serde_json::Value,
+    ) -> Self {
+        Self {
+            name,
+            fitness: combined_fitness(&best),
+            normalized: peptide::combined_fitness_normalized(&best),
+            hamming: peptide::hamming_to_motif(&best),
+            best,
+            time,
+            unit,
+            found_at,
+            evaluations,
+            duplicates: None,
+            immigrants: None,
+            plateau: None,
+            consensus: None,
+            clusters: Vec::new(),
+            top: Vec::new(),
+            #[cfg(feature = "serde")]
+            config,
+        }
+    }
+}
+
+/// `stats::plateau_length` of a normal GA's progress.
+fn ga_plateau(progress: &[genetic::GenStats]) -> usize {
+    let progress: Vec<_> = progress
         .iter()
-        .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap())
-        .unwrap();
+        .map(|&(g, min, max, avg, _)| (g, min as f32, max as f32, avg as f32))
+        .collect();
+    stats::plateau_length(&progress)
+}
 
-    println!(
-        "\nBest Normal GA result: Motif {} ({}) with fitness {:.4}",
-        best_normal.0, best_normal.1, best_normal.2
-    );
-    println!(
-        "Best NeighbourGA result: Motif {} ({}) with fitness {:.4}",
-        best_neighbour.0, best_neighbour.1, best_neighbour.2
+/// Plausibility rules the Neighbour GA enforces and results are checked against.
+fn validity_config(args: &RunArgs) -> peptide::ValidityConfig {
+    peptide::ValidityConfig {
+        max_instability: args
+            .check_instability
+            .then_some(peptide::INSTABILITY_THRESHOLD),
+        avoid_glycosylation: args.avoid_glycosylation,
+        cleavage_sites: args.cleavage_sites.clone(),
+        ..Default::default()
+    }
+}
+
+fn print_closest_motif(seq: &[u8]) {
+    let (_, idx) = PeptideProblem::energy_best_motif_with_index(seq);
+    say!(
+        Normal,
+        "  Closest motif: {} ({})",
+        idx,
+        peptide::motif_string(idx)
     );
 }
+
+/// Name of the algorithm with the strictly lowest fitness, `None` on a tie for first place.
+fn winner<'a>(scores: &[(&'a str, f32)]) -> Option<&'a str> {
+    let best = scores.iter().map(|&(_, f)| f).fold(f32::INFINITY, f32::min);
+    let mut leaders = scores.iter().filter(|&&(_, f)| f == best);
+    let first = leaders.next()?;
+    if leaders.next().is_some() {
+        None
+    } else {
+        Some(first.0)
+    }
+}
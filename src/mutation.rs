@@ -0,0 +1,231 @@
+//! Pluggable mutation: a `MutationOp` edits a sequence in place, and a
+//! `MutationSet` applies several of them, each with its own probability.
+
+use crate::problem::SequenceProblem;
+use rand::{Rng, RngCore};
+use std::marker::PhantomData;
+
+/// In-place edit of a sequence that keeps its length.
+pub trait MutationOp: Send + Sync {
+    fn mutate(&self, seq: &mut [u8], rng: &mut dyn RngCore);
+
+    /// Name shown in reports.
+    fn name(&self) -> &str {
+        "custom"
+    }
+}
+
+/// One position set to a different symbol of `0..alphabet`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Substitution {
+    pub alphabet: usize,
+}
+
+impl MutationOp for Substitution {
+    fn mutate(&self, seq: &mut [u8], rng: &mut dyn RngCore) {
+        if seq.is_empty() || self.alphabet < 2 {
+            return;
+        }
+        let pos = rng.gen_range(0..seq.len());
+        let old = seq[pos];
+        let mut new = rng.gen_range(0..self.alphabet) as u8;
+        while new == old {
+            new = rng.gen_range(0..self.alphabet) as u8;
+        }
+        seq[pos] = new;
+    }
+
+    fn name(&self) -> &str {
+        "substitution"
+    }
+}
+
+/// One position set to `P::guided_gene`, which may keep the old symbol.
+pub struct GuidedSubstitution<P>(PhantomData<fn() -> P>);
+
+impl<P> GuidedSubstitution<P> {
+    pub fn new() -> Self {
+        Self(PhantomData)
+    }
+}
+
+impl<P> Default for GuidedSubstitution<P> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<P: SequenceProblem> MutationOp for GuidedSubstitution<P> {
+    fn mutate(&self, seq: &mut [u8], mut rng: &mut dyn RngCore) {
+        if seq.is_empty() {
+            return;
+        }
+        let pos = rng.gen_range(0..seq.len());
+        seq[pos] = P::guided_gene(&mut rng, pos);
+    }
+
+    fn name(&self) -> &str {
+        "guided-substitution"
+    }
+}
+
+/// Two distinct positions exchanged.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Swap;
+
+impl MutationOp for Swap {
+    fn mutate(&self, seq: &mut [u8], rng: &mut dyn RngCore) {
+        if seq.len() < 2 {
+            return;
+        }
+        let p1 = rng.gen_range(0..seq.len());
+        let mut p2 = rng.gen_range(0..seq.len());
+        while p2 == p1 {
+            p2 = rng.gen_range(0..seq.len());
+        }
+        seq.swap(p1, p2);
+    }
+
+    fn name(&self) -> &str {
+        "swap"
+    }
+}
+
+/// `seq[i..=j]` reversed for random `i < j`; sequences shorter than three
+/// are left alone, where this would only be a swap.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Inversion;
+
+impl MutationOp for Inversion {
+    fn mutate(&self, seq: &mut [u8], rng: &mut dyn RngCore) {
+        if seq.len() < 3 {
+            return;
+        }
+        let i = rng.gen_range(0..seq.len() - 1);
+        let j = rng.gen_range(i + 1..seq.len());
+        seq[i..=j].reverse();
+    }
+
+    fn name(&self) -> &str {
+        "inversion"
+    }
+}
+
+/// Mutation operators applied in order, each independently with its own
+/// probability.
+#[derive(Default)]
+pub struct MutationSet {
+    ops: Vec<(Box<dyn MutationOp>, f64)>,
+}
+
+impl MutationSet {
+    /// Empty set; applying it changes nothing.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds `op`, fired with probability `p`.
+    pub fn with(self, op: impl MutationOp + 'static, p: f64) -> Self {
+        self.with_boxed(Box::new(op), p)
+    }
+
+    pub fn with_boxed(mut self, op: Box<dyn MutationOp>, p: f64) -> Self {
+        assert!(
+            (0.0..=1.0).contains(&p),
+            "probability of {} must be in [0, 1], got {}",
+            op.name(),
+            p
+        );
+        self.ops.push((op, p));
+        self
+    }
+
+    /// Runs every operator that fires on `seq`; returns how many did.
+    pub fn apply<R: Rng>(&self, seq: &mut [u8], rng: &mut R) -> usize {
+        let mut fired = 0;
+        for (op, p) in &self.ops {
+            if rng.gen::<f64>() < *p {
+                op.mutate(seq, rng);
+                fired += 1;
+            }
+        }
+        fired
+    }
+
+    /// Operator names and probabilities in application order.
+    pub fn ops(&self) -> impl Iterator<Item = (&str, f64)> {
+        self.ops.iter().map(|(op, p)| (op.name(), *p))
+    }
+
+    pub fn len(&self) -> usize {
+        self.ops.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.ops.is_empty()
+    }
+}
+
+#[cfg(feature = "serde")]
+impl serde::Serialize for MutationSet {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_seq(self.ops())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_inversion_with_seeded_rng() {
+        let mut seq = vec![0, 1, 2, 3, 4, 5, 6, 7];
+        Inversion.mutate(&mut seq, &mut StdRng::seed_from_u64(7));
+
+        // replay the two draws the operator makes
+        let mut rng = StdRng::seed_from_u64(7);
+        let i = rng.gen_range(0..7);
+        let j = rng.gen_range(i + 1..8);
+        let mut expected: Vec<u8> = (0..8).collect();
+        expected[i..=j].reverse();
+        assert_eq!(seq, expected);
+    }
+
+    #[test]
+    fn test_operators_keep_length_and_change_sequence() {
+        let mut rng = StdRng::seed_from_u64(3);
+        let start = vec![0, 1, 2, 3, 4];
+        for _ in 0..50 {
+            let mut seq = start.clone();
+            Substitution { alphabet: 20 }.mutate(&mut seq, &mut rng);
+            let changed = seq.iter().zip(&start).filter(|(a, b)| a != b).count();
+            assert_eq!(changed, 1);
+
+            let mut seq = start.clone();
+            Swap.mutate(&mut seq, &mut rng);
+            let mut sorted = seq.clone();
+            sorted.sort();
+            assert_eq!(sorted, start);
+            assert_ne!(seq, start);
+        }
+    }
+
+    #[test]
+    fn test_set_applies_by_probability() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let set = MutationSet::new()
+            .with(Swap, 1.0)
+            .with(Substitution { alphabet: 20 }, 0.0);
+        assert_eq!(
+            set.ops().collect::<Vec<_>>(),
+            [("swap", 1.0), ("substitution", 0.0)]
+        );
+
+        let mut seq = vec![0, 1];
+        assert_eq!(set.apply(&mut seq, &mut rng), 1);
+        assert_eq!(seq, [1, 0]);
+        assert_eq!(MutationSet::new().apply(&mut seq, &mut rng), 0);
+    }
+}
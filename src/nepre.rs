@@ -1,18 +1,350 @@
 // src/nepre.rs
+use crate::data::ALPHABET_SIZE;
 use once_cell::sync::Lazy;
-pub static NEPRE_F6: Lazy<[[f32; 20]; 20]> = Lazy::new(|| {
-    let txt = include_str!("../data/nepre_f6_example.txt");
-    let mut m = [[0.0; 20]; 20];
-    for (i, line) in txt.lines().filter(|l| !l.starts_with('#')).enumerate() {
-        for (j, val) in line.split_whitespace().enumerate() {
-            m[i][j] = val.parse::<f32>().unwrap();
+use std::sync::RwLock;
+
+/// Number of residue types the NEPRE matrix is indexed by.
+const N: usize = ALPHABET_SIZE;
+
+/// Errors raised while parsing a NEPRE pair-potential matrix.
+#[derive(Debug, Clone, PartialEq)]
+pub enum NepreError {
+    /// Token at `line`/`column` (both 1-based) is not a number.
+    Parse {
+        line: usize,
+        column: usize,
+        token: String,
+    },
+    /// Row on `line` does not have exactly 20 values.
+    RowLength { line: usize, found: usize },
+    /// The file does not have the expected number of data rows (20 for a
+    /// pair matrix, 400 for a triplet table).
+    RowCount { expected: usize, found: usize },
+    /// The matrix file could not be read.
+    Io { path: String, message: String },
+}
+
+impl std::fmt::Display for NepreError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NepreError::Parse {
+                line,
+                column,
+                token,
+            } => write!(
+                f,
+                "line {}, column {}: '{}' is not a number",
+                line, column, token
+            ),
+            NepreError::RowLength { line, found } => {
+                write!(f, "line {}: expected {} values, found {}", line, N, found)
+            }
+            NepreError::RowCount { expected, found } => {
+                write!(f, "expected {} rows, found {}", expected, found)
+            }
+            NepreError::Io { path, message } => write!(f, "{}: {}", path, message),
+        }
+    }
+}
+
+impl std::error::Error for NepreError {}
+
+/// Parses a 20×20 whitespace-separated matrix; `#` lines and blank lines are skipped.
+pub fn load_nepre(txt: &str) -> Result<[[f32; N]; N], NepreError> {
+    let mut m = [[0.0; N]; N];
+    m.copy_from_slice(&parse_rows(txt, N)?);
+    Ok(m)
+}
+
+/// Table of window-3 energies, indexed `[a][b][c]`.
+pub type TripletTable = [[[f32; N]; N]; N];
+
+/// Parses a triplet table: 400 rows of 20 values, row `20 * a + b` holding
+/// `t[a][b][c]` for `c` in column order. Same comment and blank-line rules
+/// as `load_nepre`.
+pub fn load_triplet(txt: &str) -> Result<Box<TripletTable>, NepreError> {
+    let rows = parse_rows(txt, N * N)?;
+    let mut t = Box::new([[[0.0; N]; N]; N]);
+    for (i, row) in rows.into_iter().enumerate() {
+        t[i / N][i % N] = row;
+    }
+    Ok(t)
+}
+
+// Data rows of a whitespace-separated table with `N` columns
+fn parse_rows(txt: &str, expected: usize) -> Result<Vec<[f32; N]>, NepreError> {
+    let m = parse_table(txt)?;
+    if m.len() != expected {
+        return Err(NepreError::RowCount {
+            expected,
+            found: m.len(),
+        });
+    }
+    Ok(m)
+}
+
+// Every data row of a whitespace-separated table with `N` columns; `#`
+// lines and blank lines are skipped
+pub(crate) fn parse_table(txt: &str) -> Result<Vec<[f32; N]>, NepreError> {
+    let mut m = Vec::new();
+    for (line_no, line) in txt.lines().enumerate() {
+        let line_no = line_no + 1;
+        if line.starts_with('#') || line.trim().is_empty() {
+            continue;
+        }
+        let values: Vec<&str> = line.split_whitespace().collect();
+        if values.len() != N {
+            return Err(NepreError::RowLength {
+                line: line_no,
+                found: values.len(),
+            });
         }
+        let mut row = [0.0; N];
+        for (j, tok) in values.iter().enumerate() {
+            row[j] = tok.parse::<f32>().map_err(|_| NepreError::Parse {
+                line: line_no,
+                column: j + 1,
+                token: tok.to_string(),
+            })?;
+        }
+        m.push(row);
     }
-    m
+    Ok(m)
+}
+
+pub static NEPRE_F6: Lazy<[[f32; N]; N]> = Lazy::new(|| {
+    load_nepre(include_str!("../data/nepre_f6_example.txt"))
+        .unwrap_or_else(|e| panic!("embedded NEPRE matrix is malformed: {}", e))
 });
+
+// Matrix used by `pair`; starts as the embedded one, replaced by `set_matrix`
+static ACTIVE: Lazy<RwLock<[[f32; N]; N]>> = Lazy::new(|| RwLock::new(*NEPRE_F6));
+
+/// Loads a 20×20 matrix from `path` and makes it the one `pair` reads from.
+pub fn set_matrix(path: &str) -> Result<(), NepreError> {
+    let txt = std::fs::read_to_string(path).map_err(|e| NepreError::Io {
+        path: path.to_string(),
+        message: e.to_string(),
+    })?;
+    let m = load_nepre(&txt)?;
+    *ACTIVE.write().unwrap() = m;
+    Ok(())
+}
+
+// Optional triplet table read by `triplet`; off unless `set_triplet_matrix` loads one
+static TRIPLET: RwLock<Option<Box<TripletTable>>> = RwLock::new(None);
+
+/// Loads a triplet table (see `load_triplet`) from `path` and turns on the
+/// window-3 contributions of the NEPRE term.
+pub fn set_triplet_matrix(path: &str) -> Result<(), NepreError> {
+    let txt = std::fs::read_to_string(path).map_err(|e| NepreError::Io {
+        path: path.to_string(),
+        message: e.to_string(),
+    })?;
+    let t = load_triplet(&txt)?;
+    *TRIPLET.write().unwrap() = Some(t);
+    Ok(())
+}
+
+/// Drops the triplet table, back to pair-only scoring.
+pub fn clear_triplet_matrix() {
+    *TRIPLET.write().unwrap() = None;
+}
+
+/// Whether a triplet table is loaded.
+pub fn has_triplet() -> bool {
+    TRIPLET.read().unwrap().is_some()
+}
+
+/// Energy of the residue window `a b c` (indices); `0.0` without a table.
+pub fn triplet(a: u8, b: u8, c: u8) -> f32 {
+    TRIPLET
+        .read()
+        .unwrap()
+        .as_ref()
+        .map_or(0.0, |t| t[a as usize][b as usize][c as usize])
+}
+
+/// Summed window-3 energies of `seq`; `0.0` without a table.
+pub fn triplet_energy(seq: &[u8]) -> f32 {
+    match TRIPLET.read().unwrap().as_ref() {
+        Some(t) => seq
+            .windows(3)
+            .map(|w| t[w[0] as usize][w[1] as usize][w[2] as usize])
+            .sum(),
+        None => 0.0,
+    }
+}
+
+/// Asymmetry above which a loaded matrix is worth reporting.
+pub const ASYMMETRY_TOLERANCE: f32 = 1e-3;
+
+/// Largest `|m[a][b] - m[b][a]|`; `0.0` for a symmetric matrix.
+pub fn asymmetry(m: &[[f32; N]; N]) -> f32 {
+    (0..N)
+        .flat_map(|a| (a + 1..N).map(move |b| (a, b)))
+        .map(|(a, b)| (m[a][b] - m[b][a]).abs())
+        .fold(0.0, f32::max)
+}
+
+/// `m` with every `m[a][b]` and `m[b][a]` replaced by their mean.
+pub fn symmetrized(m: &[[f32; N]; N]) -> [[f32; N]; N] {
+    let mut out = *m;
+    for a in 0..N {
+        for b in 0..N {
+            out[a][b] = 0.5 * (m[a][b] + m[b][a]);
+        }
+    }
+    out
+}
+
+/// `asymmetry` of the matrix `pair` reads from.
+pub fn active_asymmetry() -> f32 {
+    asymmetry(&ACTIVE.read().unwrap())
+}
+
+/// Makes the matrix `pair` reads from symmetric, averaging both directions.
+pub fn symmetrize() {
+    let mut active = ACTIVE.write().unwrap();
+    *active = symmetrized(&active);
+}
+
 pub fn pair(a: u8, b: u8) -> f32 {
     // a and b are already indices (0-19), not letters
     let ia = a as usize;
     let ib = b as usize;
-    NEPRE_F6[ia][ib]
+    ACTIVE.read().unwrap()[ia][ib]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EMBEDDED: &str = include_str!("../data/nepre_f6_example.txt");
+
+    #[test]
+    fn test_embedded_matrix_loads() {
+        let m = load_nepre(EMBEDDED).unwrap();
+        assert_eq!(m[0][0], 2.0);
+        assert_eq!(m[2][3], -0.5);
+    }
+
+    #[test]
+    fn test_truncated_file() {
+        let truncated: String = EMBEDDED.lines().take(15).collect::<Vec<_>>().join("\n");
+        assert_eq!(
+            load_nepre(&truncated),
+            Err(NepreError::RowCount {
+                expected: 20,
+                found: 14
+            })
+        );
+
+        let short_row = EMBEDDED.replacen(" 1.50 1.50\n", "\n", 1);
+        assert!(matches!(
+            load_nepre(&short_row),
+            Err(NepreError::RowLength { line: 2, found: 18 })
+        ));
+    }
+
+    #[test]
+    fn test_set_matrix_from_file() {
+        let _guard = crate::peptide::global_lock();
+
+        let path = std::env::temp_dir().join("peptide_opt_nepre_ones.txt");
+        let ones = vec![vec!["1.0"; 20].join(" "); 20].join("\n");
+        std::fs::write(&path, ones).unwrap();
+
+        set_matrix(path.to_str().unwrap()).unwrap();
+        assert_eq!(pair(2, 3), 1.0);
+        *ACTIVE.write().unwrap() = *NEPRE_F6;
+        assert_eq!(pair(2, 3), -0.5);
+
+        assert!(matches!(
+            set_matrix("/nonexistent/nepre.txt"),
+            Err(NepreError::Io { .. })
+        ));
+        assert_eq!(pair(2, 3), -0.5);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_asymmetric_fixture() {
+        let _guard = crate::peptide::global_lock();
+
+        assert_eq!(asymmetry(&load_nepre(EMBEDDED).unwrap()), 0.0);
+        let m = load_nepre(include_str!("../data/nepre_asymmetric_example.txt")).unwrap();
+        assert_eq!(asymmetry(&m), 2.0);
+        assert!(asymmetry(&m) > ASYMMETRY_TOLERANCE);
+
+        let sym = symmetrized(&m);
+        assert_eq!(asymmetry(&sym), 0.0);
+        assert_eq!((sym[2][3], sym[3][2]), (0.5, 0.5));
+        assert_eq!((sym[0][1], sym[1][0]), (1.0, 1.0));
+        assert_eq!(sym[0][0], m[0][0]);
+
+        *ACTIVE.write().unwrap() = m;
+        assert_ne!(pair(2, 3), pair(3, 2));
+        symmetrize();
+        assert_eq!(active_asymmetry(), 0.0);
+        assert_eq!(pair(2, 3), 0.5);
+        *ACTIVE.write().unwrap() = *NEPRE_F6;
+    }
+
+    #[test]
+    fn test_triplet_table() {
+        let _guard = crate::peptide::global_lock();
+
+        // t[a][b][c] = a + b / 100 + c / 10_000, distinct for every window
+        let rows: Vec<String> = (0..N * N)
+            .map(|i| {
+                (0..N)
+                    .map(|c| {
+                        format!(
+                            "{}",
+                            (i / N) as f32 + (i % N) as f32 / 100.0 + c as f32 / 1e4
+                        )
+                    })
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        let txt = rows.join("\n");
+        let t = load_triplet(&txt).unwrap();
+        assert_eq!(t[3][5][7], 3.0 + 0.05 + 0.0007);
+        assert_eq!(
+            load_triplet(EMBEDDED).map(|_| ()),
+            Err(NepreError::RowCount {
+                expected: 400,
+                found: 20
+            })
+        );
+
+        assert!(!has_triplet());
+        assert_eq!(triplet(3, 5, 7), 0.0);
+        assert_eq!(triplet_energy(&[3, 5, 7, 1]), 0.0);
+
+        let path = std::env::temp_dir().join("peptide_opt_nepre_triplet.txt");
+        std::fs::write(&path, &txt).unwrap();
+        set_triplet_matrix(path.to_str().unwrap()).unwrap();
+        assert!(has_triplet());
+        assert_eq!(triplet(3, 5, 7), t[3][5][7]);
+        assert_eq!(triplet_energy(&[3, 5, 7, 1]), t[3][5][7] + t[5][7][1]);
+        clear_triplet_matrix();
+        assert!(!has_triplet());
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn test_non_numeric_token() {
+        let bad = EMBEDDED.replacen("-0.50", "abc", 1);
+        assert_eq!(
+            load_nepre(&bad),
+            Err(NepreError::Parse {
+                line: 4,
+                column: 4,
+                token: "abc".to_string()
+            })
+        );
+    }
 }
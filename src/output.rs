@@ -0,0 +1,314 @@
+//! Writers for exporting run results to files.
+
+use crate::annealing::AnnealResult;
+use crate::data::AA_LETTERS;
+use crate::ga_neighbour::NeighResult;
+use crate::genetic::GaResult;
+use crate::hill_climb::ClimbResult;
+use crate::tabu::TabuResult;
+use std::borrow::Cow;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+
+/// Residue indices as a one-letter string.
+pub fn sequence_string(seq: &[u8]) -> String {
+    seq.iter()
+        .map(|&aa| AA_LETTERS[aa as usize] as char)
+        .collect()
+}
+
+// Quote a field if it contains a separator, quote or line break
+fn escape(field: &str) -> Cow<'_, str> {
+    if field.contains([',', '"', '\n', '\r']) {
+        Cow::Owned(format!("\"{}\"", field.replace('"', "\"\"")))
+    } else {
+        Cow::Borrowed(field)
+    }
+}
+
+/// One line of convergence data. Fitness is `f32` like `combined_fitness`,
+/// which keeps the printed values free of widening noise. The
+/// single-solution searches only track the best fitness so far, so they
+/// leave `max` and `avg` empty.
+pub struct ProgressRow<'a> {
+    pub algorithm: &'a str,
+    pub motif: usize,
+    pub generation: usize,
+    pub min: f32,
+    pub max: Option<f32>,
+    pub avg: Option<f32>,
+    pub best: Option<&'a [u8]>,
+}
+
+/// Per-generation progress of every algorithm and motif as CSV.
+pub struct CsvWriter<W: Write> {
+    out: W,
+}
+
+impl CsvWriter<BufWriter<File>> {
+    pub fn create(path: &Path) -> io::Result<Self> {
+        Self::new(BufWriter::new(File::create(path)?))
+    }
+}
+
+impl<W: Write> CsvWriter<W> {
+    pub const HEADER: &'static str = "algorithm,motif,generation,min,max,avg,best";
+
+    /// Wraps `out` and writes the header line.
+    pub fn new(mut out: W) -> io::Result<Self> {
+        writeln!(out, "{}", Self::HEADER)?;
+        Ok(Self { out })
+    }
+
+    pub fn write_row(&mut self, row: &ProgressRow) -> io::Result<()> {
+        let opt = |v: Option<f32>| v.map(|v| v.to_string()).unwrap_or_default();
+        let best = row.best.map(sequence_string).unwrap_or_default();
+        writeln!(
+            self.out,
+            "{},{},{},{},{},{},{}",
+            escape(row.algorithm),
+            row.motif,
+            row.generation,
+            row.min,
+            opt(row.max),
+            opt(row.avg),
+            escape(&best)
+        )
+    }
+
+    pub fn write_ga(&mut self, algorithm: &str, motif: usize, res: &GaResult) -> io::Result<()> {
+        for (i, &(generation, min, max, avg, _)) in res.progress.iter().enumerate() {
+            self.write_row(&ProgressRow {
+                algorithm,
+                motif,
+                generation,
+                min: min as f32,
+                max: Some(max as f32),
+                avg: Some(avg as f32),
+                best: res.best_per_gen.get(i).map(Vec::as_slice),
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn write_neighbour(
+        &mut self,
+        algorithm: &str,
+        motif: usize,
+        res: &NeighResult,
+    ) -> io::Result<()> {
+        for &(generation, min, max, avg) in &res.progress {
+            self.write_row(&ProgressRow {
+                algorithm,
+                motif,
+                generation,
+                min,
+                max: Some(max),
+                avg: Some(avg),
+                best: None,
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn write_tabu(
+        &mut self,
+        algorithm: &str,
+        motif: usize,
+        res: &TabuResult<Vec<u8>>,
+    ) -> io::Result<()> {
+        self.write_trace(algorithm, motif, &res.trace)
+    }
+
+    pub fn write_annealing(
+        &mut self,
+        algorithm: &str,
+        motif: usize,
+        res: &AnnealResult<Vec<u8>>,
+    ) -> io::Result<()> {
+        self.write_trace(algorithm, motif, &res.trace)
+    }
+
+    pub fn write_hill_climb(
+        &mut self,
+        algorithm: &str,
+        motif: usize,
+        res: &ClimbResult<Vec<u8>>,
+    ) -> io::Result<()> {
+        self.write_trace(algorithm, motif, &res.trace)
+    }
+
+    // Best-so-far trace of a single-solution search
+    fn write_trace(
+        &mut self,
+        algorithm: &str,
+        motif: usize,
+        trace: &[(usize, f64)],
+    ) -> io::Result<()> {
+        for &(generation, best) in trace {
+            self.write_row(&ProgressRow {
+                algorithm,
+                motif,
+                generation,
+                min: best as f32,
+                max: None,
+                avg: None,
+                best: None,
+            })?;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> io::Result<()> {
+        self.out.flush()
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+/// Final result of one algorithm on one motif, for `--json`.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct AlgorithmReport {
+    pub algorithm: String,
+    /// Seed of the run (differs between `--runs` repetitions).
+    pub seed: u64,
+    /// Best sequence as one-letter codes.
+    pub best: String,
+    pub fitness: f32,
+    pub seconds: f64,
+    /// Generation / iteration at which `best` was first reached.
+    pub best_found_at: usize,
+    /// Fitness evaluations spent by the run.
+    pub evaluations: u64,
+    /// The algorithm's configuration struct, serialised as is.
+    pub config: serde_json::Value,
+}
+
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct MotifReport {
+    pub motif: usize,
+    pub sequence: String,
+    pub results: Vec<AlgorithmReport>,
+}
+
+/// Everything a run produced, written as one JSON document.
+#[cfg(feature = "serde")]
+#[derive(Debug, Clone, PartialEq, serde::Serialize, serde::Deserialize)]
+pub struct RunReport {
+    pub seed: u64,
+    pub weights: serde_json::Value,
+    pub motifs: Vec<MotifReport>,
+}
+
+#[cfg(feature = "serde")]
+impl RunReport {
+    pub fn write(&self, path: &Path) -> io::Result<()> {
+        let mut out = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(&mut out, self)?;
+        writeln!(out)?;
+        out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genetic::GeneticAlgorithm;
+    use crate::peptide::{global_lock, set_motif, PeptideProblem};
+
+    #[test]
+    fn test_escape() {
+        assert_eq!(escape("RGD"), "RGD");
+        assert_eq!(escape("a,b"), "\"a,b\"");
+        assert_eq!(escape("say \"hi\""), "\"say \"\"hi\"\"\"");
+    }
+
+    #[test]
+    fn test_one_row_per_generation() {
+        let _guard = global_lock();
+        set_motif(1).unwrap();
+
+        let mut ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(20)
+            .generations(12)
+            .crossover_prob(0.8)
+            .mutation_prob(0.1)
+            .build();
+        let res = ga.run(4);
+
+        let mut csv = CsvWriter::new(Vec::new()).unwrap();
+        csv.write_ga("Normal GA", 1, &res).unwrap();
+        let text = String::from_utf8(csv.into_inner()).unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+
+        assert_eq!(lines[0], CsvWriter::<Vec<u8>>::HEADER);
+        assert_eq!(lines.len() - 1, 12);
+        assert!(lines[1].starts_with("Normal GA,1,0,"));
+        // best sequence is the last column
+        let last = lines[12].rsplit(',').next().unwrap();
+        assert_eq!(last, sequence_string(&res.best_per_gen[11]));
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn test_json_report_round_trip() {
+        use crate::ga_neighbour::NeighCfg;
+        use crate::genetic::{Crossover, Selection};
+        use crate::peptide::{combined_fitness, fitness_weights};
+
+        let _guard = global_lock();
+        set_motif(1).unwrap();
+        let mut ga = GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(10)
+            .generations(3)
+            .crossover_prob(0.8)
+            .crossover(Crossover::Uniform)
+            .mutation_prob(0.1)
+            .selection(Selection::Roulette)
+            .build();
+        let run = ga.run(1);
+        let best = run.best;
+
+        let report = RunReport {
+            seed: 1,
+            weights: serde_json::to_value(fitness_weights()).unwrap(),
+            motifs: vec![MotifReport {
+                motif: 1,
+                sequence: "RGD".to_string(),
+                results: vec![
+                    AlgorithmReport {
+                        algorithm: "Normal GA".to_string(),
+                        seed: 1,
+                        best: sequence_string(&best),
+                        fitness: combined_fitness(&best),
+                        seconds: 0.25,
+                        best_found_at: run.best_found_at,
+                        evaluations: run.evaluations,
+                        config: serde_json::to_value(&ga).unwrap(),
+                    },
+                    AlgorithmReport {
+                        algorithm: "NeighbourGA".to_string(),
+                        seed: 1,
+                        best: "RGD".to_string(),
+                        fitness: -17.0,
+                        seconds: 0.5,
+                        best_found_at: 0,
+                        evaluations: 0,
+                        config: serde_json::to_value(NeighCfg::default()).unwrap(),
+                    },
+                ],
+            }],
+        };
+
+        let text = serde_json::to_string_pretty(&report).unwrap();
+        let back: RunReport = serde_json::from_str(&text).unwrap();
+        assert_eq!(back, report);
+        assert_eq!(back.motifs[0].results[0].config["population_size"], 10);
+        assert_eq!(back.motifs[0].results[1].config["pop_size"], 400);
+    }
+}
@@ -0,0 +1,61 @@
+//! Size of the `rayon` thread pool that evaluates populations. Without the
+//! `rayon` feature evaluation is sequential and `set_threads` does nothing.
+
+#[cfg(feature = "rayon")]
+pub use rayon::ThreadPoolBuildError;
+
+/// `threads`, or the number of cores for `0`.
+#[cfg(feature = "rayon")]
+fn resolve(threads: usize) -> usize {
+    if threads > 0 {
+        return threads;
+    }
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+/// Pool of `threads` workers, one per core for `0`.
+#[cfg(feature = "rayon")]
+pub fn thread_pool(threads: usize) -> Result<rayon::ThreadPool, ThreadPoolBuildError> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(resolve(threads))
+        .build()
+}
+
+/// Sizes the global pool used for parallel evaluation to `threads` workers,
+/// one per core for `0`. The global pool is built once, on first use, so this
+/// has to run before anything is evaluated and fails if called twice.
+#[cfg(feature = "rayon")]
+pub fn set_threads(threads: usize) -> Result<(), ThreadPoolBuildError> {
+    rayon::ThreadPoolBuilder::new()
+        .num_threads(resolve(threads))
+        .build_global()
+}
+
+/// No-op: evaluation is sequential without the `rayon` feature.
+#[cfg(not(feature = "rayon"))]
+pub fn set_threads(_threads: usize) -> Result<(), std::convert::Infallible> {
+    Ok(())
+}
+
+/// Workers parallel evaluation runs on; 1 without the `rayon` feature.
+pub fn current_threads() -> usize {
+    #[cfg(feature = "rayon")]
+    let threads = rayon::current_num_threads();
+    #[cfg(not(feature = "rayon"))]
+    let threads = 1;
+    threads
+}
+
+#[cfg(all(test, feature = "rayon"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pool_size() {
+        assert_eq!(thread_pool(3).unwrap().current_num_threads(), 3);
+        let cores = std::thread::available_parallelism().unwrap().get();
+        let pool = thread_pool(0).unwrap();
+        assert_eq!(pool.current_num_threads(), cores);
+        assert_eq!(pool.install(current_threads), cores);
+    }
+}
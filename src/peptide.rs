@@ -1,19 +1,152 @@
+use crate::align;
 use crate::nepre;
+use crate::pssm;
 use crate::{
-    data::{AA_LETTERS, BLOSUM62},
-    problem::TSProblem,
+    crossover::CrossoverOp,
+    data::{AA_LETTERS, ALPHABET_SIZE, BACKGROUND_FREQ, BLOSUM62, INSTABILITY_DIWV},
+    fitness::{BlosumTerm, ChargeTerm, CompositeFitness, FitnessTerm, NepreTerm},
+    mutation::{MutationOp, MutationSet, Substitution, Swap},
+    problem::{SequenceProblem, TSProblem},
 };
 use lazy_static::lazy_static;
+use once_cell::sync::Lazy;
+use rand::distributions::{Distribution, WeightedIndex};
+use rand::seq::SliceRandom;
 use rand::Rng;
+use std::borrow::Cow;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::RwLock;
 
 // muszę wrzucić GA i dedykowany GA dla tego problemu
 // w genetycznym wstawiamy blanki, które później do oceny usuwamy. z nimi się łatwiej crossuje i mutuje
 
+/// Placeholder gene of a blank-padded genome. Blanks line up sequences of
+/// different lengths position by position for crossover, stay in place
+/// under mutation and are removed before scoring (`combined_fitness`) and
+/// by `repair`.
+pub const BLANK: u8 = u8::MAX;
+
+/// Motif position that matches any residue, written `x` as in PROSITE
+/// patterns. It scores 0 against every residue (see `motif_score`).
+pub const WILDCARD: u8 = u8::MAX - 1;
+
+/// BLOSUM62 score of residue `aa` against motif residue `m`; 0 at a
+/// `WILDCARD`.
+pub fn motif_score(aa: u8, m: u8) -> i32 {
+    if m == WILDCARD {
+        return 0;
+    }
+    BLOSUM62[aa as usize][m as usize] as i32
+}
+
+/// `seq` without its blanks; borrowed when it has none.
+pub fn strip_blanks(seq: &[u8]) -> Cow<'_, [u8]> {
+    if seq.contains(&BLANK) {
+        Cow::Owned(seq.iter().copied().filter(|&aa| aa != BLANK).collect())
+    } else {
+        Cow::Borrowed(seq)
+    }
+}
+
+/// Runs `edit` on the residues of a blank-padded genome and writes them back
+/// into the same slots, so mutation never moves, fills or creates blanks.
+fn mutate_residues(seq: &mut [u8], edit: impl FnOnce(&mut [u8])) {
+    if !seq.contains(&BLANK) {
+        edit(seq);
+        return;
+    }
+    let slots: Vec<usize> = (0..seq.len()).filter(|&i| seq[i] != BLANK).collect();
+    let mut residues: Vec<u8> = slots.iter().map(|&i| seq[i]).collect();
+    edit(&mut residues);
+    for (&i, aa) in slots.iter().zip(residues) {
+        seq[i] = aa;
+    }
+}
+
+/// `seq` with blanks inserted at random positions up to `len` genes; the
+/// residues keep their order.
+pub fn pad_with_blanks<R: Rng>(rng: &mut R, seq: &[u8], len: usize) -> Vec<u8> {
+    let mut padded = seq.to_vec();
+    while padded.len() < len {
+        let pos = rng.gen_range(0..=padded.len());
+        padded.insert(pos, BLANK);
+    }
+    padded
+}
+
 pub fn aa_index(letter: u8) -> usize {
-    AA_LETTERS
-        .iter()
-        .position(|&c| c == letter)
-        .expect("undefined amino acid")
+    try_aa_index(letter).expect("undefined amino acid")
+}
+
+// Like `aa_index`, but `None` for letters outside `AA_LETTERS`
+pub fn try_aa_index(letter: u8) -> Option<usize> {
+    AA_LETTERS.iter().position(|&c| c == letter)
+}
+
+/// Errors raised when selecting or defining a motif.
+#[derive(Debug, Clone, PartialEq)]
+pub enum MotifError {
+    /// Character at `pos` is not one of the 20 standard amino acids.
+    InvalidResidue { ch: char, pos: usize },
+    /// The motif string is empty.
+    Empty,
+    /// No motif has this index; `count` motifs are available.
+    OutOfRange { index: usize, count: usize },
+}
+
+impl std::fmt::Display for MotifError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            MotifError::InvalidResidue { ch, pos } => write!(
+                f,
+                "invalid amino acid '{}' at position {} (expected one of {})",
+                ch,
+                pos,
+                std::str::from_utf8(AA_LETTERS).unwrap()
+            ),
+            MotifError::Empty => write!(f, "motif sequence is empty"),
+            MotifError::OutOfRange { index, count } => write!(
+                f,
+                "motif index {} out of range (expected 0..{})",
+                index, count
+            ),
+        }
+    }
+}
+
+impl std::error::Error for MotifError {}
+
+/// Converts a one-letter sequence (case-insensitive) into amino acid indices.
+pub fn parse_sequence(seq: &str) -> Result<Vec<u8>, MotifError> {
+    if seq.is_empty() {
+        return Err(MotifError::Empty);
+    }
+    seq.chars()
+        .enumerate()
+        .map(|(pos, ch)| {
+            u8::try_from(ch.to_ascii_uppercase())
+                .ok()
+                .and_then(try_aa_index)
+                .map(|i| i as u8)
+                .ok_or(MotifError::InvalidResidue { ch, pos })
+        })
+        .collect()
+}
+
+/// Like `parse_sequence`, but `x` (either case) is accepted as a `WILDCARD`.
+pub fn parse_motif(seq: &str) -> Result<Vec<u8>, MotifError> {
+    if seq.is_empty() {
+        return Err(MotifError::Empty);
+    }
+    seq.chars()
+        .enumerate()
+        .map(|(pos, ch)| match ch {
+            'x' | 'X' => Ok(WILDCARD),
+            _ => parse_sequence(ch.encode_utf8(&mut [0; 4]))
+                .map(|aa| aa[0])
+                .map_err(|_| MotifError::InvalidResidue { ch, pos }),
+        })
+        .collect()
 }
 
 // Multiple motifs for testing
@@ -33,111 +166,974 @@ pub const MOTIFS: [&[u8]; 13] = [
     b"KWRWKRWKK",              // Cell-penetrating peptide
 ];
 
+/// Kyte–Doolittle hydropathy. Entries must follow `AA_LETTERS` order
+/// (A C D E F G H I K L M N P Q R S T V W Y), since residues index it directly.
 const HYDROPATHY: [f32; 20] = [
     1.8, 2.5, -3.5, -3.5, 2.8, -0.4, -3.2, 4.5, -3.9, 3.8, 1.9, -3.5, -1.6, -3.5, -4.5, -0.8, -0.7,
     4.2, -0.9, -1.3,
 ];
+
+// `try_aa_index` for constant evaluation
+const fn const_aa_index(letter: u8) -> usize {
+    let mut i = 0;
+    while i < AA_LETTERS.len() {
+        if AA_LETTERS[i] == letter {
+            return i;
+        }
+        i += 1;
+    }
+    panic!("undefined amino acid")
+}
+
+// Reordering `AA_LETTERS` without `HYDROPATHY` fails the build here
+const _: () = {
+    assert!(HYDROPATHY.len() == ALPHABET_SIZE);
+    assert!(HYDROPATHY[const_aa_index(b'I')] == 4.5);
+    assert!(HYDROPATHY[const_aa_index(b'R')] == -4.5);
+    assert!(HYDROPATHY[const_aa_index(b'G')] == -0.4);
+    assert!(HYDROPATHY[const_aa_index(b'W')] == -0.9);
+};
 const NEPRE_WEIGHT: f32 = 0.20;
 
+/// Side-chain charge at pH ~7.4: Lys/Arg +1, Asp/Glu -1 and His +0.1
+/// (its imidazole is only ~10% protonated). Termini are ignored.
+const CHARGE: [f32; 20] = [
+    0.0, 0.0, -1.0, -1.0, 0.0, 0.0, 0.1, 0.0, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0,
+    0.0, 0.0,
+];
+
+/// Net charge of the peptide at physiological pH.
+pub fn net_charge(seq: &[u8]) -> f32 {
+    seq.iter().map(|&aa| CHARGE[aa as usize]).sum()
+}
+
+/// Thresholds of the plausibility heuristics in `is_biologically_valid_with`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct ValidityConfig {
+    /// Allowed window of the average Kyte–Doolittle hydropathy.
+    pub hydro_min: f32,
+    pub hydro_max: f32,
+    /// Adjacent residue pairs (as indices into `AA_LETTERS`) that are rejected.
+    pub forbidden_pairs: Vec<(u8, u8)>,
+    /// Protease cleavage sites that are rejected.
+    pub cleavage_sites: Vec<CleavageSite>,
+    /// Longest allowed run of one identical residue.
+    pub max_run: usize,
+    /// Reject peptides whose instability index exceeds this; `None` skips it.
+    pub max_instability: Option<f32>,
+    /// Reject N-glycosylation sequons (Asn, any residue but Pro, Ser/Thr).
+    pub avoid_glycosylation: bool,
+}
+
+impl Default for ValidityConfig {
+    fn default() -> Self {
+        Self {
+            hydro_min: -1.5,
+            hydro_max: 3.0,
+            // C = index 1, P = index 12 in AA_LETTERS
+            forbidden_pairs: vec![(1, 1), (12, 12)],
+            cleavage_sites: Vec::new(),
+            max_run: 3,
+            max_instability: None,
+            avoid_glycosylation: false,
+        }
+    }
+}
+
+/// Protease recognition site: the bond after any residue of `after` is cut
+/// unless the next residue is one of `unless_before`.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct CleavageSite {
+    pub name: String,
+    pub after: Vec<u8>,
+    pub unless_before: Vec<u8>,
+}
+
+/// Errors raised when parsing a `CleavageSite` pattern.
+#[derive(Debug, Clone, PartialEq)]
+pub enum CleavageSiteError {
+    /// Character at `pos` of the pattern is not one of the 20 amino acids.
+    InvalidResidue { ch: char, pos: usize },
+    /// No residues to cut after.
+    Empty,
+    /// Cuts after every residue with nothing blocking it, so no peptide of
+    /// two or more residues could avoid the site.
+    CutsEverything,
+}
+
+impl std::fmt::Display for CleavageSiteError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            CleavageSiteError::InvalidResidue { ch, pos } => write!(
+                f,
+                "invalid amino acid '{}' at position {} (expected one of {})",
+                ch,
+                pos,
+                std::str::from_utf8(AA_LETTERS).unwrap()
+            ),
+            CleavageSiteError::Empty => write!(f, "no residues to cleave after"),
+            CleavageSiteError::CutsEverything => write!(
+                f,
+                "site cleaves after every residue, no peptide can avoid it"
+            ),
+        }
+    }
+}
+
+impl std::error::Error for CleavageSiteError {}
+
+impl CleavageSite {
+    /// Trypsin: after Lys or Arg, but not before Pro.
+    pub fn trypsin() -> Self {
+        Self::parse("KR!P")
+            .map(|site| Self {
+                name: "trypsin".to_string(),
+                ..site
+            })
+            .unwrap()
+    }
+
+    /// Parses `trypsin` or a pattern of the residues cleaved after,
+    /// optionally followed by `!` and the residues that block cleavage when
+    /// they come next, e.g. `FWY!P` for chymotrypsin. A site that cuts
+    /// after every residue and has no blockers is rejected.
+    pub fn parse(pattern: &str) -> Result<Self, CleavageSiteError> {
+        if pattern.eq_ignore_ascii_case("trypsin") {
+            return Ok(Self::trypsin());
+        }
+        let (after, unless) = pattern.split_once('!').unwrap_or((pattern, ""));
+        // residues of `part`, which starts at `offset` in the pattern
+        let residues = |part: &str, offset: usize| {
+            if part.is_empty() {
+                return Ok(Vec::new());
+            }
+            parse_sequence(part).map_err(|e| match e {
+                MotifError::InvalidResidue { ch, pos } => CleavageSiteError::InvalidResidue {
+                    ch,
+                    pos: pos + offset,
+                },
+                _ => CleavageSiteError::Empty,
+            })
+        };
+        let site = Self {
+            name: pattern.to_string(),
+            after: residues(after, 0)?,
+            unless_before: residues(unless, after.len() + 1)?,
+        };
+        if site.after.is_empty() {
+            return Err(CleavageSiteError::Empty);
+        }
+        let cuts_all = (0..ALPHABET_SIZE as u8).all(|aa| site.after.contains(&aa));
+        if cuts_all && site.unless_before.is_empty() {
+            return Err(CleavageSiteError::CutsEverything);
+        }
+        Ok(site)
+    }
+
+    /// Whether the bond between `p1` and the following `next` is cut.
+    pub fn cleaves(&self, p1: u8, next: u8) -> bool {
+        self.after.contains(&p1) && !self.unless_before.contains(&next)
+    }
+}
+
 /// Returns `true` iff the peptide passes a few fast heuristics
 /// that make it resemble a viable, soluble biological sequence.
 ///
-/// Current rules (easy to tweak):
+/// Uses the default `ValidityConfig`:
 /// 1. Average hydropathy must be in -1.5 … +3.0  
 /// 2. No forbidden adjacent pairs  (“CC” or “PP”)  
 /// 3. No homopolymer run ≥ 4 identical residues
 pub fn is_biologically_valid(seq: &[u8]) -> bool {
+    is_biologically_valid_with(seq, &ValidityConfig::default())
+}
+
+/// `is_biologically_valid` with explicit thresholds.
+pub fn is_biologically_valid_with(seq: &[u8], cfg: &ValidityConfig) -> bool {
+    validity_report_with(seq, cfg).is_valid()
+}
+
+/// One plausibility rule a sequence breaks. Positions are 0-based indices
+/// into the sequence.
+#[derive(Clone, Debug, PartialEq)]
+pub enum Violation {
+    Empty,
+    /// Average Kyte–Doolittle hydropathy outside `min..=max`.
+    Hydropathy {
+        average: f32,
+        min: f32,
+        max: f32,
+    },
+    /// `pair` occupies `pos` and `pos + 1`.
+    ForbiddenPair {
+        pos: usize,
+        pair: (u8, u8),
+    },
+    /// The cleavage site called `site` cuts between `pos` and `pos + 1`.
+    Cleavage {
+        pos: usize,
+        site: String,
+    },
+    /// `len` copies of `residue` starting at `pos`, more than `max_run`.
+    Homopolymer {
+        pos: usize,
+        len: usize,
+        residue: u8,
+    },
+    Instability {
+        index: f32,
+        max: f32,
+    },
+    /// N-glycosylation sequon N-X-S/T (X not Pro) with its Asn at `pos`.
+    Sequon {
+        pos: usize,
+    },
+}
+
+impl std::fmt::Display for Violation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let letter = |aa: u8| AA_LETTERS[aa as usize] as char;
+        match *self {
+            Violation::Empty => write!(f, "empty sequence"),
+            Violation::Hydropathy { average, min, max } => write!(
+                f,
+                "average hydropathy {:.2} outside {}..{}",
+                average, min, max
+            ),
+            Violation::ForbiddenPair { pos, pair } => write!(
+                f,
+                "forbidden pair {}{} at {}",
+                letter(pair.0),
+                letter(pair.1),
+                pos + 1
+            ),
+            Violation::Cleavage { pos, ref site } => {
+                write!(f, "{} cleavage site after {}", site, pos + 1)
+            }
+            Violation::Homopolymer { pos, len, residue } => {
+                write!(f, "run of {} {} at {}", len, letter(residue), pos + 1)
+            }
+            Violation::Instability { index, max } => {
+                write!(f, "instability index {:.1} above {}", index, max)
+            }
+            Violation::Sequon { pos } => write!(f, "glycosylation sequon at {}", pos + 1),
+        }
+    }
+}
+
+/// Every rule of a `ValidityConfig` a sequence breaks, in the order the
+/// checks run: hydropathy, forbidden pairs, cleavage sites, instability,
+/// homopolymers, glycosylation sequons.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct ValidityReport {
+    pub violations: Vec<Violation>,
+}
+
+impl ValidityReport {
+    pub fn is_valid(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
+
+impl std::fmt::Display for ValidityReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_valid() {
+            return write!(f, "valid");
+        }
+        for (i, v) in self.violations.iter().enumerate() {
+            if i > 0 {
+                write!(f, "; ")?;
+            }
+            write!(f, "{}", v)?;
+        }
+        Ok(())
+    }
+}
+
+/// Why `is_biologically_valid` rejects `seq`, if it does.
+pub fn validity_report(seq: &[u8]) -> ValidityReport {
+    validity_report_with(seq, &ValidityConfig::default())
+}
+
+/// `validity_report` with explicit thresholds.
+pub fn validity_report_with(seq: &[u8], cfg: &ValidityConfig) -> ValidityReport {
+    let mut violations = Vec::new();
     if seq.is_empty() {
-        return false;
+        violations.push(Violation::Empty);
+        return ValidityReport { violations };
     }
 
     // --- average hydropathy ---
     let avg_hydro: f32 =
         seq.iter().map(|&aa| HYDROPATHY[aa as usize]).sum::<f32>() / (seq.len() as f32);
-    if !(-1.5..=3.0).contains(&avg_hydro) {
-        return false;
+    if !(cfg.hydro_min..=cfg.hydro_max).contains(&avg_hydro) {
+        violations.push(Violation::Hydropathy {
+            average: avg_hydro,
+            min: cfg.hydro_min,
+            max: cfg.hydro_max,
+        });
     }
 
     // --- forbidden adjacent pairs ---
-    // C = index 1, P = index 12 in AA_LETTERS
-    for win in seq.windows(2) {
-        if (win[0] == 1 && win[1] == 1) || (win[0] == 12 && win[1] == 12) {
+    for (pos, win) in seq.windows(2).enumerate() {
+        if cfg.forbidden_pairs.contains(&(win[0], win[1])) {
+            violations.push(Violation::ForbiddenPair {
+                pos,
+                pair: (win[0], win[1]),
+            });
+        }
+    }
+
+    // --- protease cleavage sites ---
+    for (pos, win) in seq.windows(2).enumerate() {
+        if let Some(site) = cfg
+            .cleavage_sites
+            .iter()
+            .find(|s| s.cleaves(win[0], win[1]))
+        {
+            violations.push(Violation::Cleavage {
+                pos,
+                site: site.name.clone(),
+            });
+        }
+    }
+
+    // --- instability index (optional) ---
+    if let Some(max) = cfg.max_instability {
+        let index = instability_index(seq);
+        if index > max {
+            violations.push(Violation::Instability { index, max });
+        }
+    }
+
+    // --- long homopolymers ---
+    let mut start = 0;
+    for i in 1..=seq.len() {
+        if i == seq.len() || seq[i] != seq[start] {
+            let len = i - start;
+            if len > cfg.max_run {
+                violations.push(Violation::Homopolymer {
+                    pos: start,
+                    len,
+                    residue: seq[start],
+                });
+            }
+            start = i;
+        }
+    }
+
+    // --- N-glycosylation sequons (optional) ---
+    if cfg.avoid_glycosylation {
+        violations.extend(sequon_positions(seq).map(|pos| Violation::Sequon { pos }));
+    }
+
+    ValidityReport { violations }
+}
+
+const ASN: u8 = const_aa_index(b'N') as u8;
+const PRO: u8 = const_aa_index(b'P') as u8;
+const SER: u8 = const_aa_index(b'S') as u8;
+const THR: u8 = const_aa_index(b'T') as u8;
+
+/// Start of every N-X-S/T window (X not Pro) in `seq`, the motif that
+/// triggers N-linked glycosylation.
+pub fn sequon_positions(seq: &[u8]) -> impl Iterator<Item = usize> + '_ {
+    seq.windows(3)
+        .enumerate()
+        .filter(|(_, w)| w[0] == ASN && w[1] != PRO && (w[2] == SER || w[2] == THR))
+        .map(|(pos, _)| pos)
+}
+
+/// Number of `ValidityConfig` rules `seq` breaks: one per entry of its
+/// `ValidityReport`, except that a homopolymer counts once per residue
+/// beyond `max_run`. `0` exactly when `is_biologically_valid_with` accepts.
+pub fn validity_violations(seq: &[u8], cfg: &ValidityConfig) -> usize {
+    validity_report_with(seq, cfg)
+        .violations
+        .iter()
+        .map(|v| match v {
+            Violation::Homopolymer { len, .. } => len - cfg.max_run,
+            _ => 1,
+        })
+        .sum()
+}
+
+/// Whether `seq` still passes `cfg` after `seq[pos] = new_aa`, given that it
+/// passes now. Pairs, cleavage sites, homopolymers and sequons are only
+/// checked in the windows covering `pos`; the hydropathy average and the
+/// instability index are whole-sequence sums, recomputed in the same order
+/// as `validity_report_with` so both agree on borderline values.
+pub fn substitution_keeps_valid(seq: &[u8], pos: usize, new_aa: u8, cfg: &ValidityConfig) -> bool {
+    let n = seq.len();
+    let at = |i: usize| if i == pos { new_aa } else { seq[i] };
+
+    let avg_hydro = (0..n).map(|i| HYDROPATHY[at(i) as usize]).sum::<f32>() / n as f32;
+    if !(cfg.hydro_min..=cfg.hydro_max).contains(&avg_hydro) {
+        return false;
+    }
+
+    // the (up to) two bonds touching `pos`
+    for i in (pos.saturating_sub(1)..=pos).filter(|&i| i + 1 < n) {
+        let (a, b) = (at(i), at(i + 1));
+        if cfg.forbidden_pairs.contains(&(a, b))
+            || cfg.cleavage_sites.iter().any(|s| s.cleaves(a, b))
+        {
             return false;
         }
     }
 
-    // --- long homopolymers (≥4) ---
-    let mut run = 1usize;
-    for i in 1..seq.len() {
-        if seq[i] == seq[i - 1] {
-            run += 1;
-            if run >= 4 {
+    if let Some(max) = cfg.max_instability {
+        let sum: f32 = (1..n)
+            .map(|i| INSTABILITY_DIWV[at(i - 1) as usize][at(i) as usize])
+            .sum();
+        if 10.0 * sum / n as f32 > max {
+            return false;
+        }
+    }
+
+    // the run of `new_aa` through `pos`
+    let left = (0..pos).rev().take_while(|&i| seq[i] == new_aa).count();
+    let right = (pos + 1..n).take_while(|&i| seq[i] == new_aa).count();
+    if left + 1 + right > cfg.max_run {
+        return false;
+    }
+
+    // the (up to) three sequon windows covering `pos`
+    if cfg.avoid_glycosylation {
+        for s in (pos.saturating_sub(2)..=pos).filter(|&s| s + 3 <= n) {
+            if at(s) == ASN && at(s + 1) != PRO && (at(s + 2) == SER || at(s + 2) == THR) {
                 return false;
             }
+        }
+    }
+    true
+}
+
+/// Passes over the sequence `repair_validity` makes at most.
+const REPAIR_ROUNDS: usize = 3;
+
+/// Edits `seq` towards validity instead of discarding it, guided by its
+/// `ValidityReport`: the second residue of a forbidden pair and every
+/// `max_run + 1`-th residue of a homopolymer are substituted, and while the
+/// average hydropathy is outside its window the most extreme residue on the
+/// wrong side is replaced by one from the other side. The first residue of
+/// a cleavage site is replaced by one no site cuts after, and the Asn of a
+/// glycosylation sequon is replaced by a residue that cannot complete
+/// another. The instability index is not repaired, so the result can still
+/// be invalid.
+pub fn repair_validity<R: Rng>(seq: &mut [u8], cfg: &ValidityConfig, rng: &mut R) {
+    for _ in 0..REPAIR_ROUNDS {
+        let report = validity_report_with(seq, cfg);
+        if report.is_valid() {
+            return;
+        }
+        for v in &report.violations {
+            match *v {
+                Violation::ForbiddenPair { pos, .. } => {
+                    seq[pos + 1] = fitting_residue(seq, pos + 1, cfg, rng, |_| true);
+                }
+                Violation::Cleavage { pos, .. } => {
+                    seq[pos] = fitting_residue(seq, pos, cfg, rng, |aa| {
+                        !cfg.cleavage_sites.iter().any(|s| s.after.contains(&aa))
+                    });
+                }
+                Violation::Homopolymer { pos, len, .. } => {
+                    for i in (pos + cfg.max_run..pos + len).step_by(cfg.max_run + 1) {
+                        seq[i] = fitting_residue(seq, i, cfg, rng, |_| true);
+                    }
+                }
+                Violation::Hydropathy { .. } => repair_hydropathy(seq, cfg, rng),
+                Violation::Sequon { pos } => {
+                    seq[pos] = fitting_residue(seq, pos, cfg, rng, |aa| {
+                        aa != ASN && aa != SER && aa != THR
+                    });
+                }
+                Violation::Empty | Violation::Instability { .. } => {}
+            }
+        }
+    }
+}
+
+// Swaps out the most extreme residues until the average hydropathy is back
+// inside its window (or nothing more can be done)
+fn repair_hydropathy<R: Rng>(seq: &mut [u8], cfg: &ValidityConfig, rng: &mut R) {
+    let len = seq.len() as f32;
+    let mut total: f32 = seq.iter().map(|&aa| HYDROPATHY[aa as usize]).sum();
+    for _ in 0..seq.len() {
+        let avg = total / len;
+        let too_high = avg > cfg.hydro_max;
+        if !too_high && avg >= cfg.hydro_min {
+            break;
+        }
+        let key = |&i: &usize| HYDROPATHY[seq[i] as usize];
+        let extreme = if too_high {
+            (0..seq.len()).max_by(|a, b| key(a).total_cmp(&key(b)))
+        } else {
+            (0..seq.len()).min_by(|a, b| key(a).total_cmp(&key(b)))
+        };
+        let Some(i) = extreme else { break };
+        let old = HYDROPATHY[seq[i] as usize];
+        seq[i] = fitting_residue(seq, i, cfg, rng, |aa| {
+            let h = HYDROPATHY[aa as usize];
+            if too_high {
+                h < old.min(cfg.hydro_max)
+            } else {
+                h > old.max(cfg.hydro_min)
+            }
+        });
+        total += HYDROPATHY[seq[i] as usize] - old;
+    }
+}
+
+// Random residue for `seq[i]` that passes `accept` and makes neither a
+// forbidden pair nor a repeat with its neighbours; keeps `seq[i]` if none does
+fn fitting_residue<R: Rng>(
+    seq: &[u8],
+    i: usize,
+    cfg: &ValidityConfig,
+    rng: &mut R,
+    accept: impl Fn(u8) -> bool,
+) -> u8 {
+    let left = i.checked_sub(1).map(|j| seq[j]);
+    let right = seq.get(i + 1).copied();
+    let fits = |aa: u8| {
+        accept(aa)
+            && left.is_none_or(|l| l != aa && !cfg.forbidden_pairs.contains(&(l, aa)))
+            && right.is_none_or(|r| r != aa && !cfg.forbidden_pairs.contains(&(aa, r)))
+    };
+    let candidates: Vec<u8> = (0..ALPHABET_SIZE as u8).filter(|&aa| fits(aa)).collect();
+    candidates.choose(rng).copied().unwrap_or(seq[i])
+}
+
+// pKa values (EMBOSS) of the termini and ionisable side chains
+const PKA_N_TERM: f32 = 8.6;
+const PKA_C_TERM: f32 = 3.6;
+/// Positive groups: K, R, H.
+const PKA_BASIC: [(u8, f32); 3] = [(8, 10.8), (14, 12.5), (6, 6.5)];
+/// Negative groups: D, E, C, Y.
+const PKA_ACIDIC: [(u8, f32); 4] = [(2, 3.9), (3, 4.1), (1, 8.5), (19, 10.1)];
+
+// Henderson–Hasselbalch charge of the whole peptide at `ph`
+fn charge_at_ph(seq: &[u8], ph: f32) -> f32 {
+    let pos = |pka: f32| 1.0 / (1.0 + 10f32.powf(ph - pka));
+    let neg = |pka: f32| -1.0 / (1.0 + 10f32.powf(pka - ph));
+
+    let mut charge = pos(PKA_N_TERM) + neg(PKA_C_TERM);
+    for &aa in seq {
+        if let Some(&(_, pka)) = PKA_BASIC.iter().find(|&&(r, _)| r == aa) {
+            charge += pos(pka);
+        } else if let Some(&(_, pka)) = PKA_ACIDIC.iter().find(|&&(r, _)| r == aa) {
+            charge += neg(pka);
+        }
+    }
+    charge
+}
+
+/// Isoelectric point: the pH at which the net charge is zero, found by
+/// bisection over 0–14 (charge falls monotonically with pH).
+pub fn isoelectric_point(seq: &[u8]) -> f32 {
+    let (mut lo, mut hi) = (0.0f32, 14.0f32);
+    while hi - lo > 1e-3 {
+        let mid = 0.5 * (lo + hi);
+        if charge_at_ph(seq, mid) > 0.0 {
+            lo = mid;
         } else {
-            run = 1;
+            hi = mid;
         }
     }
+    0.5 * (lo + hi)
+}
 
-    true
+/// Peptides with an instability index above this are predicted unstable.
+pub const INSTABILITY_THRESHOLD: f32 = 40.0;
+
+/// Guruprasad instability index: `10 / L` times the summed dipeptide
+/// instability weights. Values above 40 predict an unstable peptide.
+pub fn instability_index(seq: &[u8]) -> f32 {
+    if seq.is_empty() {
+        return 0.0;
+    }
+    let sum: f32 = seq
+        .windows(2)
+        .map(|w| INSTABILITY_DIWV[w[0] as usize][w[1] as usize])
+        .sum();
+    10.0 * sum / seq.len() as f32
+}
+
+/// Relative weights of the terms summed by `combined_fitness`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct FitnessWeights {
+    pub blosum: f32,
+    pub nepre: f32,
+    /// Weight of `|net_charge - target_charge|`; the term is off while
+    /// `target_charge` is `None`.
+    pub charge: f32,
+    pub target_charge: Option<f32>,
+}
+
+impl Default for FitnessWeights {
+    fn default() -> Self {
+        DEFAULT_WEIGHTS
+    }
+}
+
+const DEFAULT_WEIGHTS: FitnessWeights = FitnessWeights {
+    blosum: 1.0,
+    nepre: NEPRE_WEIGHT,
+    charge: 1.0,
+    target_charge: None,
+};
+
+static FITNESS_WEIGHTS: RwLock<FitnessWeights> = RwLock::new(DEFAULT_WEIGHTS);
+
+// Objective built from `FITNESS_WEIGHTS`, kept so scoring does not rebuild it
+static DEFAULT_FITNESS: Lazy<RwLock<CompositeFitness>> =
+    Lazy::new(|| RwLock::new(CompositeFitness::from_weights(&DEFAULT_WEIGHTS)));
+
+// Set the weights used by `combined_fitness`
+pub fn set_fitness_weights(weights: FitnessWeights) {
+    *FITNESS_WEIGHTS.write().unwrap() = weights;
+    *DEFAULT_FITNESS.write().unwrap() = CompositeFitness::from_weights(&weights);
+}
+
+// Get the weights used by `combined_fitness`
+pub fn fitness_weights() -> FitnessWeights {
+    *FITNESS_WEIGHTS.read().unwrap()
 }
 
 /// Combined energy  (lower = better).
 /// Decides automatically whether to align against the *current motif*
 /// or against *all motifs* (whichever `set_use_best_motif()` selected).
+/// Blanks are stripped first. An empty sequence scores `f32::INFINITY`, the
+/// worst possible value, rather than the `0.0` its empty sums would give.
 pub fn combined_fitness(seq: &[u8]) -> f32 {
-    // --- BLOSUM term ---
-    let blosum_e = if get_use_best_motif() {
-        PeptideProblem::energy_best_motif(seq) as f32
-    } else {
-        PeptideProblem::energy(seq) as f32
-    };
+    let seq = strip_blanks(seq);
+    if seq.is_empty() {
+        return f32::INFINITY;
+    }
+    DEFAULT_FITNESS.read().unwrap().score(&seq)
+}
+
+/// `combined_fitness` per residue, so peptides of different lengths can be
+/// compared. For reporting only: optimisation keeps using the raw score.
+pub fn combined_fitness_normalized(seq: &[u8]) -> f32 {
+    let len = strip_blanks(seq).len();
+    combined_fitness(seq) / len.max(1) as f32
+}
+
+/// `combined_fitness` with explicit term weights instead of the global ones.
+pub fn combined_fitness_weighted(seq: &[u8], weights: &FitnessWeights) -> f32 {
+    let seq = strip_blanks(seq);
+    if seq.is_empty() {
+        return f32::INFINITY;
+    }
+    CompositeFitness::from_weights(weights).score(&seq)
+}
+
+/// Weighted contribution of each term to `combined_fitness`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct FitnessBreakdown {
+    pub blosum: f32,
+    pub nepre: f32,
+    /// Net-charge penalty, `0.0` while no target charge is set.
+    pub charge: f32,
+    pub total: f32,
+}
 
-    // --- NEPRE term (pairwise neighbourhood energy) ---
-    let nepre_e: f32 = seq.windows(2).map(|w| nepre::pair(w[0], w[1])).sum();
+/// `combined_fitness` split into its terms (`total` is their sum, or
+/// infinite with all terms zero for an empty or all-blank sequence).
+pub fn combined_fitness_breakdown(seq: &[u8]) -> FitnessBreakdown {
+    combined_fitness_breakdown_weighted(seq, &fitness_weights())
+}
 
-    blosum_e + NEPRE_WEIGHT * nepre_e
+/// `combined_fitness_breakdown` with explicit term weights.
+pub fn combined_fitness_breakdown_weighted(
+    seq: &[u8],
+    weights: &FitnessWeights,
+) -> FitnessBreakdown {
+    let seq = &*strip_blanks(seq);
+    if seq.is_empty() {
+        return FitnessBreakdown {
+            blosum: 0.0,
+            nepre: 0.0,
+            charge: 0.0,
+            total: f32::INFINITY,
+        };
+    }
+    let blosum = weights.blosum * BlosumTerm { weight: 1.0 }.score(seq);
+    let nepre = weights.nepre * NepreTerm { weight: 1.0 }.score(seq);
+    let charge = weights.target_charge.map_or(0.0, |target| {
+        weights.charge
+            * ChargeTerm {
+                weight: 1.0,
+                target,
+            }
+            .score(seq)
+    });
+    FitnessBreakdown {
+        blosum,
+        nepre,
+        charge,
+        total: blosum + nepre + charge,
+    }
 }
 
-// Default motif index to use if none specified
-static mut CURRENT_MOTIF_IDX: usize = 0;
+/// Change in `combined_fitness` if `seq[pos]` were replaced by `new_aa`.
+///
+/// Both terms are position-local, so only the BLOSUM score at `pos` and the
+/// (up to) two NEPRE pairs touching `pos` are recomputed: O(1) instead of O(n).
+/// In best-motif mode the BLOSUM term is a minimum over motifs, and under
+/// alignment scoring it depends on the whole sequence; neither is local, so
+/// the full score is recomputed instead.
+pub fn fitness_delta(seq: &[u8], pos: usize, new_aa: u8) -> f32 {
+    let old_aa = seq[pos];
+    if old_aa == new_aa {
+        return 0.0;
+    }
+    let weights = fitness_weights();
+
+    if get_use_best_motif() || !matches!(*SCORING_MODE.read().unwrap(), ScoringMode::Positional) {
+        let mut after = seq.to_vec();
+        after[pos] = new_aa;
+        return combined_fitness_weighted(&after, &weights)
+            - combined_fitness_weighted(seq, &weights);
+    }
+
+    let blosum_d = {
+        let motifs = MOTIF_INDICES.read().unwrap();
+        let motif_indices = &motifs[current_motif()];
+        let m = motif_indices[pos % motif_indices.len()];
+        (motif_score(old_aa, m) - motif_score(new_aa, m)) as f32
+    };
 
-// Set which motif to use
-pub fn set_motif(index: usize) {
-    if index < MOTIFS.len() {
-        unsafe {
-            CURRENT_MOTIF_IDX = index;
+    let mut nepre_d = 0.0;
+    if pos > 0 {
+        nepre_d += nepre::pair(seq[pos - 1], new_aa) - nepre::pair(seq[pos - 1], old_aa);
+    }
+    if pos + 1 < seq.len() {
+        nepre_d += nepre::pair(new_aa, seq[pos + 1]) - nepre::pair(old_aa, seq[pos + 1]);
+    }
+    if nepre::has_triplet() && seq.len() >= 3 {
+        // the (up to) three windows covering `pos`
+        for start in pos.saturating_sub(2)..=pos.min(seq.len().saturating_sub(3)) {
+            let at = |i: usize, aa: u8| if i == pos { aa } else { seq[i] };
+            let window = |aa| nepre::triplet(at(start, aa), at(start + 1, aa), at(start + 2, aa));
+            nepre_d += window(new_aa) - window(old_aa);
         }
     }
+
+    // the charge penalty is not additive, but the net charge itself is
+    let charge_d = weights.target_charge.map_or(0.0, |t| {
+        let before = net_charge(seq);
+        let after = before - CHARGE[old_aa as usize] + CHARGE[new_aa as usize];
+        (after - t).abs() - (before - t).abs()
+    });
+
+    weights.blosum * blosum_d + weights.nepre * nepre_d + weights.charge * charge_d
+}
+
+// Index of the active motif (defaults to the first one).
+// Atomic so the fitness path stays sound when called from several threads.
+static CURRENT_MOTIF_IDX: AtomicUsize = AtomicUsize::new(0);
+
+/// Selects the motif fitness is scored against; an index past the
+/// available motifs (see `list_motifs`) is an error and changes nothing.
+pub fn set_motif(index: usize) -> Result<(), MotifError> {
+    let count = motif_count();
+    if index >= count {
+        return Err(MotifError::OutOfRange { index, count });
+    }
+    CURRENT_MOTIF_IDX.store(index, Ordering::Relaxed);
+    Ok(())
+}
+
+// Get the index of the active motif
+pub fn current_motif() -> usize {
+    CURRENT_MOTIF_IDX.load(Ordering::Relaxed)
 }
 
 // Get current motif length
 pub fn current_motif_len() -> usize {
-    let motif_idx = unsafe { CURRENT_MOTIF_IDX };
-    MOTIFS[motif_idx].len()
+    MOTIF_INDICES.read().unwrap()[current_motif()].len()
+}
+
+// Designed peptide length when it should differ from the motif's;
+// 0 means "follow the active motif"
+static TARGET_LEN: AtomicUsize = AtomicUsize::new(0);
+
+// Override the designed length, or go back to the motif length with `None`
+pub fn set_target_len(len: Option<usize>) {
+    assert!(len != Some(0), "target length must be positive");
+    TARGET_LEN.store(len.unwrap_or(0), Ordering::Relaxed);
+}
+
+// Length of designed peptides: the override if set, else the motif length
+pub fn target_len() -> usize {
+    match TARGET_LEN.load(Ordering::Relaxed) {
+        0 => current_motif_len(),
+        len => len,
+    }
 }
 
 lazy_static! {
-    // All motifs converted to amino acid indices
-    static ref MOTIF_INDICES: Vec<Vec<u8>> = {
-        MOTIFS
-            .iter()
-            .map(|motif| {
-                motif.iter()
-                    .map(|&c| aa_index(c) as u8)
-                    .collect()
-            })
-            .collect()
+    // All motifs converted to amino acid indices: the built-in `MOTIFS`
+    // followed by any registered with `add_motif`
+    static ref MOTIF_INDICES: RwLock<Vec<Vec<u8>>> = {
+        RwLock::new(
+            MOTIFS
+                .iter()
+                .map(|motif| {
+                    motif.iter()
+                        .map(|&c| aa_index(c) as u8)
+                        .collect()
+                })
+                .collect(),
+        )
     };
 }
 
-#[derive(Clone, PartialEq)]
+// Number of available motifs (built-in plus registered)
+pub fn motif_count() -> usize {
+    MOTIF_INDICES.read().unwrap().len()
+}
+
+// One-letter string of the motif at `index`
+pub fn motif_string(index: usize) -> String {
+    MOTIF_INDICES.read().unwrap()[index]
+        .iter()
+        .map(|&aa| match aa {
+            WILDCARD => 'x',
+            _ => AA_LETTERS[aa as usize] as char,
+        })
+        .collect()
+}
+
+/// Index and one-letter string of every motif `set_motif` accepts: the
+/// built-in `MOTIFS` followed by those registered with `add_motif`.
+pub fn list_motifs() -> Vec<(usize, String)> {
+    (0..motif_count()).map(|i| (i, motif_string(i))).collect()
+}
+
+/// Number of positions where `seq` differs from the active motif, which is
+/// repeated cyclically along `seq` as in positional scoring; a wildcard
+/// matches anything. Blanks are stripped first.
+pub fn hamming_to_motif(seq: &[u8]) -> usize {
+    let seq = strip_blanks(seq);
+    let motifs = MOTIF_INDICES.read().unwrap();
+    let motif = &motifs[current_motif()];
+    seq.iter()
+        .enumerate()
+        .filter(|&(i, &aa)| {
+            let m = motif[i % motif.len()];
+            m != WILDCARD && aa != m
+        })
+        .count()
+}
+
+/// Registers a user-supplied motif (one-letter codes, `x` for a wildcard)
+/// and returns its index, which can then be passed to `set_motif`.
+pub fn add_motif(seq: &str) -> Result<usize, MotifError> {
+    let indices = parse_motif(seq)?;
+    let mut motifs = MOTIF_INDICES.write().unwrap();
+    motifs.push(indices);
+    Ok(motifs.len() - 1)
+}
+
 // possible sequence modifications
+#[derive(Clone, PartialEq)]
 pub enum Move {
-    Swap { p1: usize, p2: usize },
-    Subst { pos: usize, old: u8, new: u8 },
-    Insert { pos: usize, aa: u8 },
-    Delete { pos: usize, aa: u8 },
+    Swap {
+        p1: usize,
+        p2: usize,
+    },
+    Subst {
+        pos: usize,
+        old: u8,
+        new: u8,
+    },
+    /// Reverses `seq[i..=j]`. Its own inverse, so a tabu `Invert` also
+    /// forbids undoing it.
+    Invert {
+        i: usize,
+        j: usize,
+    },
+    /// Cuts out the block `seq[from..from + len]` and reinserts it so that
+    /// it starts at `to`, an index into the sequence *without* the block
+    /// (`0..=seq.len() - len`). Residues between the two places shift by
+    /// `len` towards `from`; `Relocate { from: to, len, to: from }` undoes it.
+    Relocate {
+        from: usize,
+        len: usize,
+        to: usize,
+    },
+    Insert {
+        pos: usize,
+        aa: u8,
+    },
+    Delete {
+        pos: usize,
+        aa: u8,
+    },
+}
+
+/// How residues of fresh random peptides are drawn.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum InitMode {
+    /// Every residue equally likely.
+    #[default]
+    Uniform,
+    /// Natural composition (`BACKGROUND_FREQ`), so rare residues such as
+    /// Trp and Cys stay rare.
+    Background,
+}
+
+static BACKGROUND_DIST: Lazy<WeightedIndex<f32>> =
+    Lazy::new(|| WeightedIndex::new(BACKGROUND_FREQ).expect("positive frequencies"));
+
+/// Random peptide of `len` residues drawn according to `mode`.
+pub fn random_peptide<R: Rng>(rng: &mut R, len: usize, mode: InitMode) -> Vec<u8> {
+    match mode {
+        InitMode::Uniform => (0..len)
+            .map(|_| rng.gen_range(0..ALPHABET_SIZE) as u8)
+            .collect(),
+        InitMode::Background => (0..len)
+            .map(|_| BACKGROUND_DIST.sample(rng) as u8)
+            .collect(),
+    }
+}
+
+/// Softmax temperature of guided substitution; lower favours the motif
+/// residue (and its closest substitutes) more strongly.
+const GUIDED_TEMPERATURE: f64 = 1.0;
+
+/// Per motif residue `m`, a distribution over replacements proportional to
+/// `exp(BLOSUM62[a][m] / T)`.
+static GUIDED_DIST: Lazy<Vec<WeightedIndex<f64>>> = Lazy::new(|| {
+    (0..ALPHABET_SIZE)
+        .map(|m| {
+            let weights =
+                (0..ALPHABET_SIZE).map(|a| (BLOSUM62[a][m] as f64 / GUIDED_TEMPERATURE).exp());
+            WeightedIndex::new(weights).expect("positive weights")
+        })
+        .collect()
+});
+
+/// Replacement residue for position `pos`, sampled by BLOSUM62 similarity to
+/// the active motif's residue there (the motif repeats for longer peptides);
+/// uniform at a wildcard.
+pub fn guided_substitution<R: Rng>(rng: &mut R, pos: usize) -> u8 {
+    let m = {
+        let motifs = MOTIF_INDICES.read().unwrap();
+        let motif = &motifs[current_motif()];
+        motif[pos % motif.len()]
+    };
+    if m == WILDCARD {
+        return rng.gen_range(0..ALPHABET_SIZE) as u8;
+    }
+    GUIDED_DIST[m as usize].sample(rng) as u8
 }
 
 pub struct PeptideProblem {
@@ -145,57 +1141,187 @@ pub struct PeptideProblem {
 }
 
 // Global flag to determine whether to use best motif matching
-static mut USE_BEST_MOTIF: bool = false;
+static USE_BEST_MOTIF: AtomicBool = AtomicBool::new(false);
 
 // Public function to set the flag
 pub fn set_use_best_motif(use_best: bool) {
-    unsafe {
-        USE_BEST_MOTIF = use_best;
-    }
+    USE_BEST_MOTIF.store(use_best, Ordering::Relaxed);
 }
 
 // Public function to get the flag value
 pub fn get_use_best_motif() -> bool {
-    unsafe { USE_BEST_MOTIF }
+    USE_BEST_MOTIF.load(Ordering::Relaxed)
 }
 
-impl PeptideProblem {
+// Variable-length design: when on, `neighbourhood` also proposes
+// Insert/Delete moves and `repair` only clamps to [min_len, max_len]
+static VARIABLE_LENGTH: AtomicBool = AtomicBool::new(false);
+static MIN_LEN: AtomicUsize = AtomicUsize::new(1);
+static MAX_LEN: AtomicUsize = AtomicUsize::new(usize::MAX);
+
+// Enable or disable variable-length peptides
+pub fn set_variable_length(enabled: bool) {
+    VARIABLE_LENGTH.store(enabled, Ordering::Relaxed);
+}
+
+pub fn get_variable_length() -> bool {
+    VARIABLE_LENGTH.load(Ordering::Relaxed)
+}
+
+// Set the inclusive length bounds used in variable-length mode
+pub fn set_length_bounds(min_len: usize, max_len: usize) {
+    assert!(
+        1 <= min_len && min_len <= max_len,
+        "invalid length bounds {}..={}",
+        min_len,
+        max_len
+    );
+    MIN_LEN.store(min_len, Ordering::Relaxed);
+    MAX_LEN.store(max_len, Ordering::Relaxed);
+}
+
+pub fn length_bounds() -> (usize, usize) {
+    (
+        MIN_LEN.load(Ordering::Relaxed),
+        MAX_LEN.load(Ordering::Relaxed),
+    )
+}
+
+/// Relative frequencies of the move kinds `neighbourhood` proposes. They
+/// are normalised over the moves available: insert and delete only count
+/// in variable-length mode. GA mutation draws from substitution and swap
+/// in the same ratio.
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub struct NeighbourhoodWeights {
+    pub subst: f32,
+    pub swap: f32,
+    /// Segment reversal (`Move::Invert`); not used by GA mutation.
+    pub invert: f32,
+    /// Block relocation (`Move::Relocate`), off by default; not used by GA
+    /// mutation either.
+    pub relocate: f32,
+    pub insert: f32,
+    pub delete: f32,
+}
+
+impl Default for NeighbourhoodWeights {
+    fn default() -> Self {
+        DEFAULT_NEIGHBOURHOOD_WEIGHTS
+    }
+}
+
+const DEFAULT_NEIGHBOURHOOD_WEIGHTS: NeighbourhoodWeights = NeighbourhoodWeights {
+    subst: 0.7,
+    swap: 0.3,
+    invert: 0.1,
+    relocate: 0.0,
+    insert: 0.15,
+    delete: 0.15,
+};
+
+static NEIGHBOURHOOD_WEIGHTS: RwLock<NeighbourhoodWeights> =
+    RwLock::new(DEFAULT_NEIGHBOURHOOD_WEIGHTS);
+
+// Set the move mix of `neighbourhood` and `mutate`
+pub fn set_neighbourhood_weights(weights: NeighbourhoodWeights) {
+    let all = [
+        weights.subst,
+        weights.swap,
+        weights.invert,
+        weights.relocate,
+        weights.insert,
+        weights.delete,
+    ];
+    assert!(
+        all.iter().all(|w| w.is_finite() && *w >= 0.0),
+        "neighbourhood weights must be finite and non-negative"
+    );
+    assert!(
+        weights.subst + weights.swap > 0.0,
+        "substitution and swap weights cannot both be zero"
+    );
+    *NEIGHBOURHOOD_WEIGHTS.write().unwrap() = weights;
+}
+
+pub fn neighbourhood_weights() -> NeighbourhoodWeights {
+    *NEIGHBOURHOOD_WEIGHTS.read().unwrap()
+}
+
+/// How the BLOSUM term matches a peptide against a motif.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub enum ScoringMode {
+    /// Residue `i` against `motif[i % len]`.
+    #[default]
+    Positional,
+    /// Global Needleman–Wunsch alignment with a linear `gap` penalty, so
+    /// peptides longer or shorter than the motif are scored sensibly.
+    Global { gap: i32 },
+    /// Local Smith–Waterman alignment: rewards containing the motif
+    /// anywhere in the peptide rather than matching it end to end.
+    LocalAlignment { gap: i32 },
+    /// Position-specific scoring matrix: residue `i` scores
+    /// `pssm[i % len][aa]` and the motif itself is ignored.
+    Pssm(Vec<[f32; ALPHABET_SIZE]>),
+}
+
+static SCORING_MODE: RwLock<ScoringMode> = RwLock::new(ScoringMode::Positional);
+
+impl PeptideProblem {
+    // Select how `energy` matches peptides against the motif
+    pub fn set_scoring_mode(mode: ScoringMode) {
+        *SCORING_MODE.write().unwrap() = mode;
+    }
+
+    pub fn scoring_mode() -> ScoringMode {
+        SCORING_MODE.read().unwrap().clone()
+    }
+
+    // BLOSUM energy of `ind` against one motif (lower = better)
+    fn motif_energy(ind: &[u8], motif_indices: &[u8], mode: &ScoringMode) -> f32 {
+        match mode {
+            ScoringMode::Positional => ind
+                .iter()
+                .enumerate()
+                .map(|(i, &aa)| -motif_score(aa, motif_indices[i % motif_indices.len()]))
+                .sum::<i32>() as f32,
+            ScoringMode::Global { gap } => {
+                -align::needleman_wunsch(ind, motif_indices, *gap) as f32
+            }
+            ScoringMode::LocalAlignment { gap } => {
+                -align::smith_waterman(ind, motif_indices, *gap) as f32
+            }
+            ScoringMode::Pssm(pssm) => pssm::energy(ind, pssm),
+        }
+    }
+
     // calculate the energy of a peptide sequence
     // based on the BLOSUM62 matrix and the selected motif
-    fn energy(ind: &[u8]) -> i32 {
-        // Get the current motif index
-        let motif_idx = unsafe { CURRENT_MOTIF_IDX };
-
+    pub(crate) fn energy(ind: &[u8]) -> f32 {
         // Use the selected motif's indices
-        let motif_indices = &MOTIF_INDICES[motif_idx];
+        let motifs = MOTIF_INDICES.read().unwrap();
+        let mode = SCORING_MODE.read().unwrap();
+        Self::motif_energy(ind, &motifs[current_motif()], &mode)
+    }
 
-        ind.iter()
-            .enumerate()
-            .map(|(i, &aa)| {
-                let a = aa as usize;
-                let b = motif_indices[i % motif_indices.len()] as usize;
-                -(BLOSUM62[a][b] as i32)
-            })
-            .sum()
-    }
-
-    // Calculate energy using all motifs and return the best (minimum) value
-    fn energy_best_motif(ind: &[u8]) -> i32 {
-        (0..MOTIFS.len())
-            .map(|motif_idx| {
-                let motif_indices = &MOTIF_INDICES[motif_idx];
-
-                ind.iter()
-                    .enumerate()
-                    .map(|(i, &aa)| {
-                        let a = aa as usize;
-                        let b = motif_indices[i % motif_indices.len()] as usize;
-                        -(BLOSUM62[a][b] as i32)
-                    })
-                    .sum()
-            })
-            .min()
-            .unwrap_or(0)
+    /// Lowest BLOSUM energy over all registered motifs.
+    pub fn energy_best_motif(ind: &[u8]) -> f32 {
+        Self::energy_best_motif_with_index(ind).0
+    }
+
+    /// Lowest BLOSUM energy over all registered motifs and the index of the
+    /// motif achieving it (the first one on a tie).
+    pub fn energy_best_motif_with_index(ind: &[u8]) -> (f32, usize) {
+        let mode = SCORING_MODE.read().unwrap();
+        let energies: Vec<f32> = MOTIF_INDICES
+            .read()
+            .unwrap()
+            .iter()
+            .map(|motif_indices| Self::motif_energy(ind, motif_indices, &mode))
+            .collect();
+        crate::util::best_index(&energies)
+            .map(|(idx, e)| (e, idx))
+            .unwrap_or((0.0, 0))
     }
 }
 
@@ -204,8 +1330,10 @@ impl TSProblem for PeptideProblem {
     type Move = Move;
 
     fn random_individual<R: Rng>(rng: &mut R) -> Self::Individ {
-        let len = current_motif_len();
-        (0..len).map(|_| rng.gen_range(0..20) as u8).collect()
+        let len = target_len();
+        (0..len)
+            .map(|_| rng.gen_range(0..ALPHABET_SIZE) as u8)
+            .collect()
     }
 
     fn fitness(ind: &Self::Individ) -> f64 {
@@ -219,22 +1347,42 @@ impl TSProblem for PeptideProblem {
         size: usize,
     ) -> Vec<(Self::Individ, Self::Move)> {
         let mut out = Vec::with_capacity(size);
+        let variable_length = get_variable_length();
+        let (min_len, max_len) = length_bounds();
+
+        // cumulative thresholds of subst, swap, invert, relocate and insert;
+        // delete takes the rest
+        let w = neighbourhood_weights();
+        let (insert, delete) = if variable_length {
+            (w.insert, w.delete)
+        } else {
+            (0.0, 0.0)
+        };
+        let mut cumulative = 0.0;
+        let mut below = [0.0; 5];
+        let shares = [w.subst, w.swap, w.invert, w.relocate, insert];
+        for (b, share) in below.iter_mut().zip(shares) {
+            cumulative += share;
+            *b = cumulative;
+        }
+        let total = (cumulative + delete) as f64;
+        let below = below.map(|b| b as f64 / total);
 
         for _ in 0..size {
             let mut neigh = ind.clone();
             let r: f64 = rng.gen();
 
-            if r < 0.7 {
+            if r < below[0] {
                 // ---------- SUBST ----------
                 let pos = rng.gen_range(0..neigh.len());
                 let old = neigh[pos];
-                let mut new = rng.gen_range(0..20) as u8;
+                let mut new = rng.gen_range(0..ALPHABET_SIZE) as u8;
                 while new == old {
-                    new = rng.gen_range(0..20) as u8;
+                    new = rng.gen_range(0..ALPHABET_SIZE) as u8;
                 }
                 neigh[pos] = new;
                 out.push((neigh, Move::Subst { pos, old, new }));
-            } else {
+            } else if r < below[1] {
                 // ---------- SWAP ----------
                 if neigh.len() >= 2 {
                     let p1 = rng.gen_range(0..neigh.len());
@@ -245,6 +1393,42 @@ impl TSProblem for PeptideProblem {
                     neigh.swap(p1, p2);
                     out.push((neigh, Move::Swap { p1, p2 }));
                 }
+            } else if r < below[2] {
+                // ---------- INVERT ----------
+                if neigh.len() >= 2 {
+                    let i = rng.gen_range(0..neigh.len() - 1);
+                    let j = rng.gen_range(i + 1..neigh.len());
+                    neigh[i..=j].reverse();
+                    out.push((neigh, Move::Invert { i, j }));
+                }
+            } else if r < below[3] {
+                // ---------- RELOCATE ----------
+                if neigh.len() >= 2 {
+                    let len = rng.gen_range(1..neigh.len());
+                    let from = rng.gen_range(0..=neigh.len() - len);
+                    let mut to = rng.gen_range(0..neigh.len() - len);
+                    if to >= from {
+                        to += 1;
+                    }
+                    let mv = Move::Relocate { from, len, to };
+                    Self::apply_move(&mut neigh, &mv);
+                    out.push((neigh, mv));
+                }
+            } else if r < below[4] {
+                // ---------- INSERT ----------
+                if neigh.len() < max_len {
+                    let pos = rng.gen_range(0..=neigh.len());
+                    let aa = rng.gen_range(0..ALPHABET_SIZE) as u8;
+                    neigh.insert(pos, aa);
+                    out.push((neigh, Move::Insert { pos, aa }));
+                }
+            } else {
+                // ---------- DELETE ----------
+                if neigh.len() > min_len {
+                    let pos = rng.gen_range(0..neigh.len());
+                    let aa = neigh.remove(pos);
+                    out.push((neigh, Move::Delete { pos, aa }));
+                }
             }
         }
         out
@@ -254,25 +1438,1151 @@ impl TSProblem for PeptideProblem {
         match *mv {
             Move::Subst { pos, new, .. } => ind[pos] = new,
             Move::Swap { p1, p2 } => ind.swap(p1, p2),
-            // Insert and Delete operations are no longer supported
-            Move::Insert { .. } => panic!("Insert operation not supported with fixed length"),
-            Move::Delete { .. } => panic!("Delete operation not supported with fixed length"),
+            Move::Invert { i, j } => ind[i..=j].reverse(),
+            Move::Relocate { from, len, to } => {
+                let block: Vec<u8> = ind.drain(from..from + len).collect();
+                ind.splice(to..to, block);
+            }
+            Move::Insert { pos, aa } => ind.insert(pos, aa),
+            Move::Delete { pos, .. } => {
+                ind.remove(pos);
+            }
         }
     }
 
     fn repair(ind: &mut Self::Individ) {
-        let target_len = current_motif_len();
+        ind.retain(|&aa| aa != BLANK);
+
+        // In variable-length mode only clamp into the allowed range
+        let target_len = if get_variable_length() {
+            let (min_len, max_len) = length_bounds();
+            ind.len().clamp(min_len, max_len)
+        } else {
+            target_len()
+        };
 
         // Ensure the individual has exactly the target length
         if ind.len() < target_len {
             // If too short, extend with random amino acids
             let mut rng = rand::thread_rng();
             while ind.len() < target_len {
-                ind.push(rng.gen_range(0..20) as u8);
+                ind.push(rng.gen_range(0..ALPHABET_SIZE) as u8);
             }
         } else if ind.len() > target_len {
             // If too long, truncate
             ind.truncate(target_len);
         }
     }
+
+    fn attributes(ind: &Self::Individ) -> Vec<(usize, usize)> {
+        ind.iter()
+            .enumerate()
+            .map(|(i, &aa)| (i, aa as usize))
+            .collect()
+    }
+
+    fn initial_individual<R: Rng>(rng: &mut R, init: InitMode) -> Self::Individ {
+        random_peptide(rng, target_len(), init)
+    }
+
+    fn crossover<R: Rng>(
+        a: &Self::Individ,
+        b: &Self::Individ,
+        op: &dyn CrossoverOp,
+        rng: &mut R,
+    ) -> Self::Individ {
+        if !get_variable_length() || a.len() == b.len() {
+            return op.cross(a, b, rng).0;
+        }
+        // align both parents on blank-padded genomes so the child can take
+        // any length in between, then drop the blanks again
+        let len = a.len().max(b.len());
+        let (mut child, _) = op.cross(
+            &pad_with_blanks(rng, a, len),
+            &pad_with_blanks(rng, b, len),
+            rng,
+        );
+        Self::repair(&mut child);
+        child
+    }
+
+    fn mutate<R: Rng>(ind: &mut Self::Individ, rng: &mut R) {
+        // Use one of the mutation operations randomly (only fixed-length operations)
+        let w = neighbourhood_weights();
+        let r: f64 = rng.gen();
+
+        mutate_residues(ind, |residues| {
+            if r < (w.subst / (w.subst + w.swap)) as f64 {
+                Substitution {
+                    alphabet: ALPHABET_SIZE,
+                }
+                .mutate(residues, rng);
+            } else {
+                Swap.mutate(residues, rng);
+            }
+        });
+    }
+
+    fn mutate_with<R: Rng>(ind: &mut Self::Individ, ops: &MutationSet, rng: &mut R) {
+        mutate_residues(ind, |residues| {
+            ops.apply(residues, rng);
+        });
+    }
+
+    /// Hamming distance normalised by the longer length; 0 = clones,
+    /// 1 = all positions differ.
+    fn distance(a: &Self::Individ, b: &Self::Individ) -> f64 {
+        let len = a.len().max(b.len()).max(1);
+        let diff =
+            a.iter().zip(b.iter()).filter(|(x, y)| x != y).count() + a.len().abs_diff(b.len());
+        diff as f64 / len as f64
+    }
+}
+
+impl SequenceProblem for PeptideProblem {
+    const ALPHABET: usize = ALPHABET_SIZE;
+
+    fn substitution_delta(seq: &[u8], pos: usize, value: u8) -> f64 {
+        fitness_delta(seq, pos, value) as f64
+    }
+
+    fn is_valid(seq: &[u8], validity: &ValidityConfig) -> bool {
+        is_biologically_valid_with(seq, validity)
+    }
+
+    fn substitution_keeps_valid(
+        seq: &[u8],
+        pos: usize,
+        value: u8,
+        validity: &ValidityConfig,
+    ) -> bool {
+        substitution_keeps_valid(seq, pos, value, validity)
+    }
+
+    fn violations(seq: &[u8], validity: &ValidityConfig) -> usize {
+        validity_violations(seq, validity)
+    }
+
+    fn repair_validity<R: Rng>(seq: &mut [u8], validity: &ValidityConfig, rng: &mut R) {
+        repair_validity(seq, validity, rng)
+    }
+
+    fn guided_gene<R: Rng>(rng: &mut R, pos: usize) -> u8 {
+        guided_substitution(rng, pos)
+    }
+}
+
+/// Serialises tests that touch the global motif / scoring settings.
+#[cfg(test)]
+pub(crate) fn global_lock() -> std::sync::MutexGuard<'static, ()> {
+    static LOCK: std::sync::Mutex<()> = std::sync::Mutex::new(());
+    LOCK.lock().unwrap_or_else(|e| e.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::genetic::Crossover;
+    use rand::SeedableRng;
+
+    #[test]
+    fn test_use_best_motif_toggled_from_threads() {
+        let _guard = global_lock();
+
+        let handles: Vec<_> = (0..2)
+            .map(|t| {
+                std::thread::spawn(move || {
+                    for i in 0..10_000 {
+                        set_use_best_motif((i + t) % 2 == 0);
+                        let _ = get_use_best_motif();
+                    }
+                })
+            })
+            .collect();
+        for h in handles {
+            h.join().unwrap();
+        }
+
+        set_use_best_motif(true);
+        assert!(get_use_best_motif());
+        set_use_best_motif(false);
+        assert!(!get_use_best_motif());
+    }
+
+    #[test]
+    fn test_zero_nepre_weight_is_pure_blosum() {
+        let _guard = global_lock();
+        set_motif(1).unwrap();
+
+        let seq: Vec<u8> = b"RGE".iter().map(|&c| aa_index(c) as u8).collect();
+        let weights = FitnessWeights {
+            blosum: 1.0,
+            nepre: 0.0,
+            ..FitnessWeights::default()
+        };
+        let score = combined_fitness_weighted(&seq, &weights);
+        assert_eq!(score, PeptideProblem::energy(&seq));
+    }
+
+    #[test]
+    fn test_apply_insert_and_delete() {
+        let mut seq = vec![0, 1, 2];
+        PeptideProblem::apply_move(&mut seq, &Move::Insert { pos: 1, aa: 7 });
+        assert_eq!(seq, vec![0, 7, 1, 2]);
+        PeptideProblem::apply_move(&mut seq, &Move::Delete { pos: 0, aa: 0 });
+        assert_eq!(seq, vec![7, 1, 2]);
+    }
+
+    #[test]
+    fn test_variable_length_neighbourhood_and_repair() {
+        let _guard = global_lock();
+        set_motif(1).unwrap();
+        set_variable_length(true);
+        set_length_bounds(2, 5);
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        let start = vec![0u8, 1, 2];
+        let neigh = PeptideProblem::neighbourhood(&mut rng, &start, 500);
+        let mut saw_insert = false;
+        let mut saw_delete = false;
+        for (cand, mv) in &neigh {
+            let mut replay = start.clone();
+            PeptideProblem::apply_move(&mut replay, mv);
+            assert_eq!(&replay, cand);
+            saw_insert |= matches!(mv, Move::Insert { .. });
+            saw_delete |= matches!(mv, Move::Delete { .. });
+        }
+        assert!(saw_insert && saw_delete);
+
+        let mut long = vec![3u8; 9];
+        PeptideProblem::repair(&mut long);
+        assert_eq!(long.len(), 5);
+        let mut short = vec![3u8; 1];
+        PeptideProblem::repair(&mut short);
+        assert_eq!(short.len(), 2);
+        let mut ok = vec![3u8; 4];
+        PeptideProblem::repair(&mut ok);
+        assert_eq!(ok.len(), 4);
+
+        set_variable_length(false);
+        set_length_bounds(1, usize::MAX);
+        PeptideProblem::repair(&mut ok);
+        assert_eq!(ok.len(), 3);
+    }
+
+    #[test]
+    fn test_parse_sequence() {
+        assert_eq!(parse_sequence("rGd").unwrap(), vec![14, 5, 2]);
+        assert_eq!(
+            parse_sequence("RGZ"),
+            Err(MotifError::InvalidResidue { ch: 'Z', pos: 2 })
+        );
+        assert_eq!(parse_sequence(""), Err(MotifError::Empty));
+    }
+
+    #[test]
+    fn test_add_custom_motif() {
+        let _guard = global_lock();
+        let before = motif_count();
+        let idx = add_motif("RGDSP").unwrap();
+        assert_eq!(idx, before);
+        assert_eq!(motif_string(idx), "RGDSP");
+
+        set_motif(idx).unwrap();
+        assert_eq!(current_motif_len(), 5);
+        let seq = parse_sequence("RGDSP").unwrap();
+        assert!(PeptideProblem::energy(&seq) < 0.0);
+        set_motif(0).unwrap();
+
+        assert!(add_motif("RGB").is_err());
+        assert_eq!(motif_count(), before + 1);
+    }
+
+    #[test]
+    fn test_wildcard_motif() {
+        let _guard = global_lock();
+        let idx = add_motif("RGxP").unwrap();
+        assert_eq!(motif_string(idx), "RGxP");
+        assert_eq!(parse_motif("RGxP").unwrap()[2], WILDCARD);
+        assert!(parse_sequence("RGxP").is_err());
+
+        set_motif(idx).unwrap();
+        let mut seq = parse_sequence("RGDP").unwrap();
+        let energy = PeptideProblem::energy(&seq);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(4);
+        for aa in 0..ALPHABET_SIZE as u8 {
+            seq[2] = aa;
+            assert_eq!(PeptideProblem::energy(&seq), energy);
+            assert_eq!(hamming_to_motif(&seq), 0);
+            assert!(guided_substitution(&mut rng, 2) < ALPHABET_SIZE as u8);
+        }
+        set_motif(0).unwrap();
+    }
+
+    #[test]
+    fn test_set_motif_out_of_range() {
+        let _guard = global_lock();
+        set_motif(2).unwrap();
+        let count = motif_count();
+        assert_eq!(
+            set_motif(count),
+            Err(MotifError::OutOfRange {
+                index: count,
+                count
+            })
+        );
+        // the active motif is left alone
+        assert_eq!(current_motif(), 2);
+        set_motif(0).unwrap();
+    }
+
+    #[test]
+    fn test_list_motifs() {
+        let _guard = global_lock();
+        let motifs = list_motifs();
+        // other tests may have registered motifs after the built-in ones
+        assert_eq!(motifs.len(), motif_count());
+        assert!(motifs.len() >= MOTIFS.len());
+        assert_eq!(motifs[1], (1, "RGD".to_string()));
+        for (i, (idx, motif)) in motifs.iter().take(MOTIFS.len()).enumerate() {
+            assert_eq!(*idx, i);
+            assert_eq!(motif.as_bytes(), MOTIFS[i]);
+        }
+    }
+
+    #[test]
+    fn test_target_len_overrides_motif_length() {
+        let _guard = global_lock();
+        set_motif(1).unwrap();
+        set_target_len(Some(20));
+        PeptideProblem::set_scoring_mode(ScoringMode::LocalAlignment { gap: 4 });
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        assert_eq!(PeptideProblem::random_individual(&mut rng).len(), 20);
+        for init in [InitMode::Uniform, InitMode::Background] {
+            assert_eq!(PeptideProblem::initial_individual(&mut rng, init).len(), 20);
+        }
+        let mut short = parse_sequence("RGD").unwrap();
+        PeptideProblem::repair(&mut short);
+        assert_eq!(short.len(), 20);
+
+        let best = crate::genetic::GeneticAlgorithm::<PeptideProblem>::builder()
+            .population_size(20)
+            .generations(5)
+            .build()
+            .run(1)
+            .best;
+        assert_eq!(best.len(), 20);
+
+        set_target_len(None);
+        PeptideProblem::set_scoring_mode(ScoringMode::Positional);
+        assert_eq!(target_len(), 3);
+    }
+
+    #[test]
+    fn test_blanks_stripped_before_scoring() {
+        let _guard = global_lock();
+        set_motif(2).unwrap();
+
+        let seq = parse_sequence("KRDEWY").unwrap();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(8);
+        let padded = pad_with_blanks(&mut rng, &seq, 10);
+        assert_eq!(padded.iter().filter(|&&aa| aa == BLANK).count(), 4);
+        assert_eq!(strip_blanks(&padded), seq.as_slice());
+        assert_eq!(combined_fitness(&padded), combined_fitness(&seq));
+        assert_eq!(
+            combined_fitness_breakdown(&padded),
+            combined_fitness_breakdown(&seq)
+        );
+        assert_eq!(combined_fitness(&[BLANK; 3]), f32::INFINITY);
+    }
+
+    #[test]
+    fn test_hamming_to_motif() {
+        let _guard = global_lock();
+        set_motif(1).unwrap();
+
+        let rgd = parse_sequence("RGD").unwrap();
+        assert_eq!(hamming_to_motif(&rgd), 0);
+        assert_eq!(hamming_to_motif(&parse_sequence("RGE").unwrap()), 1);
+        // the motif repeats along longer peptides
+        assert_eq!(hamming_to_motif(&parse_sequence("RGDRGA").unwrap()), 1);
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        assert_eq!(hamming_to_motif(&pad_with_blanks(&mut rng, &rgd, 6)), 0);
+    }
+
+    #[test]
+    fn test_normalized_fitness_by_length() {
+        let _guard = global_lock();
+        set_motif(2).unwrap();
+
+        let short = parse_sequence("KRDEWY").unwrap();
+        let long = short.repeat(3);
+        let (raw_short, raw_long) = (combined_fitness(&short), combined_fitness(&long));
+        let (norm_short, norm_long) = (
+            combined_fitness_normalized(&short),
+            combined_fitness_normalized(&long),
+        );
+        assert_eq!(norm_short, raw_short / 6.0);
+        assert_eq!(norm_long, raw_long / 18.0);
+        // the repeat scores about three times as high, but the same per residue
+        assert!(raw_long > 2.0 * raw_short);
+        assert!((norm_long - norm_short).abs() < 0.01);
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(8);
+        let padded = pad_with_blanks(&mut rng, &short, 10);
+        assert_eq!(combined_fitness_normalized(&padded), norm_short);
+    }
+
+    #[test]
+    fn test_mutation_keeps_blank_padding() {
+        use crate::mutation::{Inversion, MutationSet};
+
+        let _guard = global_lock();
+        set_motif(2).unwrap();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(6);
+        let seq = parse_sequence("KRDEWY").unwrap();
+        let padded = pad_with_blanks(&mut rng, &seq, 10);
+        let blanks = |g: &[u8]| -> Vec<usize> { (0..g.len()).filter(|&i| g[i] == BLANK).collect() };
+
+        let ops = MutationSet::new().with(Swap, 1.0).with(Inversion, 1.0);
+        let mut changed = false;
+        for _ in 0..50 {
+            let mut ind = padded.clone();
+            PeptideProblem::mutate(&mut ind, &mut rng);
+            assert_eq!(blanks(&ind), blanks(&padded));
+            changed |= ind != padded;
+
+            let mut ind = padded.clone();
+            PeptideProblem::mutate_with(&mut ind, &ops, &mut rng);
+            assert_eq!(blanks(&ind), blanks(&padded));
+            // swap and inversion only rearrange the residues
+            let mut residues = strip_blanks(&ind).into_owned();
+            residues.sort_unstable();
+            let mut expected = seq.clone();
+            expected.sort_unstable();
+            assert_eq!(residues, expected);
+        }
+        assert!(changed);
+        set_motif(0).unwrap();
+    }
+
+    #[test]
+    fn test_repair_fully_blank_genome() {
+        let _guard = global_lock();
+        set_motif(1).unwrap();
+
+        let mut ind = vec![BLANK; 5];
+        PeptideProblem::repair(&mut ind);
+        assert_eq!(ind.len(), current_motif_len());
+        assert!(ind.iter().all(|&aa| (aa as usize) < ALPHABET_SIZE));
+
+        set_variable_length(true);
+        set_length_bounds(2, 8);
+        let mut ind = vec![BLANK; 5];
+        PeptideProblem::repair(&mut ind);
+        assert_eq!(ind.len(), 2);
+
+        // parents of different lengths recombine through blank padding
+        let mut rng = rand::rngs::StdRng::seed_from_u64(4);
+        let a = parse_sequence("RRR").unwrap();
+        let b = parse_sequence("WWWWWWW").unwrap();
+        for _ in 0..20 {
+            let child = PeptideProblem::crossover(&a, &b, &Crossover::Uniform, &mut rng);
+            assert!((2..=7).contains(&child.len()));
+            assert!(!child.contains(&BLANK));
+        }
+        set_variable_length(false);
+        set_length_bounds(1, usize::MAX);
+    }
+
+    #[test]
+    fn test_zero_swap_weight_gives_only_substitutions() {
+        let _guard = global_lock();
+        set_motif(5).unwrap();
+
+        set_neighbourhood_weights(NeighbourhoodWeights {
+            swap: 0.0,
+            invert: 0.0,
+            ..NeighbourhoodWeights::default()
+        });
+        let mut rng = rand::rngs::StdRng::seed_from_u64(6);
+        let ind = PeptideProblem::random_individual(&mut rng);
+        let moves = PeptideProblem::neighbourhood(&mut rng, &ind, 200);
+        assert_eq!(moves.len(), 200);
+        assert!(moves.iter().all(|(_, mv)| matches!(mv, Move::Subst { .. })));
+
+        // with variable length on, only insert and delete join in
+        set_variable_length(true);
+        let moves = PeptideProblem::neighbourhood(&mut rng, &ind, 200);
+        assert!(moves
+            .iter()
+            .all(|(_, mv)| !matches!(mv, Move::Swap { .. } | Move::Invert { .. })));
+        assert!(moves
+            .iter()
+            .any(|(_, mv)| matches!(mv, Move::Insert { .. })));
+        set_variable_length(false);
+        set_neighbourhood_weights(NeighbourhoodWeights::default());
+    }
+
+    #[test]
+    fn test_inversion_is_its_own_inverse() {
+        let _guard = global_lock();
+        set_motif(5).unwrap();
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(9);
+        let ind = PeptideProblem::random_individual(&mut rng);
+        let inversions: Vec<_> = PeptideProblem::neighbourhood(&mut rng, &ind, 300)
+            .into_iter()
+            .filter(|(_, mv)| matches!(mv, Move::Invert { .. }))
+            .collect();
+        assert!(!inversions.is_empty());
+        for (neigh, mv) in &inversions {
+            let mut seq = ind.clone();
+            PeptideProblem::apply_move(&mut seq, mv);
+            assert_eq!(&seq, neigh);
+            PeptideProblem::apply_move(&mut seq, mv);
+            assert_eq!(seq, ind);
+        }
+
+        let mut seq = parse_sequence("ACDEF").unwrap();
+        PeptideProblem::apply_move(&mut seq, &Move::Invert { i: 1, j: 3 });
+        assert_eq!(seq, parse_sequence("AEDCF").unwrap());
+        assert!(Move::Invert { i: 1, j: 3 } == Move::Invert { i: 1, j: 3 });
+        assert!(Move::Invert { i: 1, j: 3 } != Move::Invert { i: 1, j: 4 });
+    }
+
+    #[test]
+    fn test_relocation_preserves_residues() {
+        let _guard = global_lock();
+        set_motif(5).unwrap();
+
+        let mut seq = parse_sequence("ACDEFG").unwrap();
+        let mv = Move::Relocate {
+            from: 1,
+            len: 2,
+            to: 3,
+        };
+        PeptideProblem::apply_move(&mut seq, &mv);
+        assert_eq!(seq, parse_sequence("AEFCDG").unwrap());
+        PeptideProblem::apply_move(
+            &mut seq,
+            &Move::Relocate {
+                from: 3,
+                len: 2,
+                to: 1,
+            },
+        );
+        assert_eq!(seq, parse_sequence("ACDEFG").unwrap());
+
+        set_neighbourhood_weights(NeighbourhoodWeights {
+            relocate: 1.0,
+            ..NeighbourhoodWeights::default()
+        });
+        let mut rng = rand::rngs::StdRng::seed_from_u64(12);
+        let ind = PeptideProblem::random_individual(&mut rng);
+        let mut sorted = ind.clone();
+        sorted.sort();
+        let mut relocations = 0;
+        for (neigh, mv) in PeptideProblem::neighbourhood(&mut rng, &ind, 300) {
+            if let Move::Relocate { .. } = mv {
+                relocations += 1;
+                let mut applied = ind.clone();
+                PeptideProblem::apply_move(&mut applied, &mv);
+                assert_eq!(applied, neigh);
+                let mut residues = neigh;
+                residues.sort();
+                assert_eq!(residues, sorted);
+            }
+        }
+        assert!(relocations > 0);
+        set_neighbourhood_weights(NeighbourhoodWeights::default());
+    }
+
+    #[test]
+    fn test_violations_agree_with_validity() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(13);
+        let cfg = ValidityConfig {
+            max_instability: Some(INSTABILITY_THRESHOLD),
+            ..ValidityConfig::default()
+        };
+        for _ in 0..500 {
+            let len = rng.gen_range(1..15);
+            let mut seq: Vec<u8> = (0..len).map(|_| rng.gen_range(0..4) * 4).collect();
+            assert_eq!(
+                validity_violations(&seq, &cfg) == 0,
+                is_biologically_valid_with(&seq, &cfg)
+            );
+            repair_validity(&mut seq, &ValidityConfig::default(), &mut rng);
+            assert_eq!(seq.len(), len);
+        }
+        // too hydrophilic and one residue past the allowed run
+        let seq = parse_sequence("KKKKE").unwrap();
+        assert_eq!(validity_violations(&seq, &ValidityConfig::default()), 2);
+    }
+
+    #[test]
+    fn test_substitution_keeps_valid_matches_full_check() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(21);
+        let cfg = ValidityConfig {
+            cleavage_sites: vec![CleavageSite::trypsin()],
+            max_run: 2,
+            max_instability: Some(INSTABILITY_THRESHOLD),
+            avoid_glycosylation: true,
+            ..ValidityConfig::default()
+        };
+        let mut checked = 0;
+        while checked < 200 {
+            let len = rng.gen_range(1..12);
+            let seq: Vec<u8> = (0..len)
+                .map(|_| rng.gen_range(0..ALPHABET_SIZE as u8))
+                .collect();
+            if !is_biologically_valid_with(&seq, &cfg) {
+                continue;
+            }
+            checked += 1;
+            for pos in 0..len {
+                for aa in 0..ALPHABET_SIZE as u8 {
+                    let mut after = seq.clone();
+                    after[pos] = aa;
+                    assert_eq!(
+                        substitution_keeps_valid(&seq, pos, aa, &cfg),
+                        is_biologically_valid_with(&after, &cfg),
+                        "{:?} at {} -> {}",
+                        seq,
+                        pos,
+                        aa
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_repair_fixes_hydropathy() {
+        let cfg = ValidityConfig::default();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(1);
+        let mut seq = parse_sequence("KRDKERDKE").unwrap();
+        assert!(!is_biologically_valid_with(&seq, &cfg));
+        repair_validity(&mut seq, &cfg, &mut rng);
+        assert!(is_biologically_valid_with(&seq, &cfg));
+    }
+
+    #[test]
+    fn test_fitness_delta_matches_full_rescore() {
+        let _guard = global_lock();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(21);
+
+        let global = ScoringMode::Global { gap: 4 };
+        for (best_motif, target_charge, mode) in [
+            (false, None, ScoringMode::Positional),
+            (true, None, ScoringMode::Positional),
+            (false, Some(2.0), ScoringMode::Positional),
+            (false, None, global),
+            (false, None, ScoringMode::LocalAlignment { gap: 4 }),
+        ] {
+            set_use_best_motif(best_motif);
+            PeptideProblem::set_scoring_mode(mode);
+            set_fitness_weights(FitnessWeights {
+                target_charge,
+                ..FitnessWeights::default()
+            });
+            for motif in [0, 6] {
+                set_motif(motif).unwrap();
+                for _ in 0..200 {
+                    let seq = PeptideProblem::random_individual(&mut rng);
+                    let pos = rng.gen_range(0..seq.len());
+                    let new_aa = rng.gen_range(0..ALPHABET_SIZE) as u8;
+
+                    let mut after = seq.clone();
+                    after[pos] = new_aa;
+                    let expected = combined_fitness(&after);
+                    let got = combined_fitness(&seq) + fitness_delta(&seq, pos, new_aa);
+                    assert!((expected - got).abs() < 1e-3, "{} vs {}", expected, got);
+                }
+            }
+        }
+        set_use_best_motif(false);
+        set_fitness_weights(FitnessWeights::default());
+        PeptideProblem::set_scoring_mode(ScoringMode::Positional);
+        set_motif(0).unwrap();
+    }
+
+    #[test]
+    fn test_best_motif_index() {
+        let _guard = global_lock();
+        let seq = parse_sequence("RGD").unwrap();
+        let (energy, idx) = PeptideProblem::energy_best_motif_with_index(&seq);
+        assert_eq!(idx, 1);
+        assert_eq!(energy, -17.0);
+        assert_eq!(PeptideProblem::energy_best_motif(&seq), energy);
+    }
+
+    #[test]
+    fn test_local_scoring_rewards_embedded_motif() {
+        let _guard = global_lock();
+        set_motif(1).unwrap(); // RGD
+        PeptideProblem::set_scoring_mode(ScoringMode::LocalAlignment { gap: 4 });
+        let with = PeptideProblem::energy(&parse_sequence("AAWRGDWAA").unwrap());
+        let without = PeptideProblem::energy(&parse_sequence("AAWKLMWAA").unwrap());
+        PeptideProblem::set_scoring_mode(ScoringMode::Positional);
+
+        assert_eq!(with, -17.0);
+        assert!(with < without, "{} vs {}", with, without);
+        set_motif(0).unwrap();
+    }
+
+    #[test]
+    fn test_global_scoring_tolerates_insertions() {
+        let _guard = global_lock();
+        set_motif(1).unwrap(); // RGD
+        let peptide = parse_sequence("RGWD").unwrap();
+
+        let positional = PeptideProblem::energy(&peptide);
+        PeptideProblem::set_scoring_mode(ScoringMode::Global { gap: 4 });
+        let aligned = PeptideProblem::energy(&peptide);
+        PeptideProblem::set_scoring_mode(ScoringMode::Positional);
+
+        assert_eq!(aligned, -(17.0 - 4.0));
+        assert!(aligned < positional, "{} vs {}", aligned, positional);
+        set_motif(0).unwrap();
+    }
+
+    #[test]
+    fn test_net_charge() {
+        assert!(net_charge(&parse_sequence("KWRWKRWKK").unwrap()) >= 5.0);
+        assert!(net_charge(&parse_sequence("DEEDG").unwrap()) <= -3.0);
+        assert_eq!(net_charge(&parse_sequence("AGLV").unwrap()), 0.0);
+    }
+
+    #[test]
+    fn test_instability_index() {
+        // every dipeptide weighs 1.0: 10 * 3 / 4
+        let calm = parse_sequence("AAAA").unwrap();
+        assert!((instability_index(&calm) - 7.5).abs() < 1e-4);
+        // RR weighs 58.28
+        let unstable = parse_sequence("RRRRRR").unwrap();
+        assert!(instability_index(&unstable) > INSTABILITY_THRESHOLD);
+    }
+
+    #[test]
+    fn test_validity_hydropathy_window() {
+        // average hydropathy -4.2
+        let seq = parse_sequence("RKRK").unwrap();
+        assert!(!is_biologically_valid(&seq));
+        let cfg = ValidityConfig {
+            hydro_min: -5.0,
+            ..ValidityConfig::default()
+        };
+        assert!(is_biologically_valid_with(&seq, &cfg));
+    }
+
+    #[test]
+    fn test_validity_forbidden_pairs() {
+        let seq = parse_sequence("ACCA").unwrap();
+        assert!(!is_biologically_valid(&seq));
+        let cfg = ValidityConfig {
+            forbidden_pairs: Vec::new(),
+            ..ValidityConfig::default()
+        };
+        assert!(is_biologically_valid_with(&seq, &cfg));
+        let cfg = ValidityConfig {
+            forbidden_pairs: vec![(0, 1)],
+            ..ValidityConfig::default()
+        };
+        assert!(!is_biologically_valid_with(
+            &parse_sequence("GACG").unwrap(),
+            &cfg
+        ));
+    }
+
+    #[test]
+    fn test_validity_max_run() {
+        let seq = parse_sequence("GAAAAG").unwrap();
+        assert!(!is_biologically_valid(&seq));
+        let cfg = ValidityConfig {
+            max_run: 4,
+            ..ValidityConfig::default()
+        };
+        assert!(is_biologically_valid_with(&seq, &cfg));
+        let cfg = ValidityConfig {
+            max_run: 1,
+            ..ValidityConfig::default()
+        };
+        assert!(!is_biologically_valid_with(
+            &parse_sequence("GAAG").unwrap(),
+            &cfg
+        ));
+    }
+
+    #[test]
+    fn test_validity_instability_is_opt_in() {
+        // passes the default rules, but SR weighs 20.26
+        let seq = parse_sequence("LSRLSRLS").unwrap();
+        assert!(instability_index(&seq) > INSTABILITY_THRESHOLD);
+        assert!(is_biologically_valid(&seq));
+        let cfg = ValidityConfig {
+            max_instability: Some(INSTABILITY_THRESHOLD),
+            ..ValidityConfig::default()
+        };
+        assert!(!is_biologically_valid_with(&seq, &cfg));
+    }
+
+    #[test]
+    fn test_report_hydropathy() {
+        let report = validity_report(&parse_sequence("RKRK").unwrap());
+        assert_eq!(report.violations.len(), 1);
+        let Violation::Hydropathy { average, .. } = report.violations[0] else {
+            panic!("unexpected {:?}", report.violations[0]);
+        };
+        assert!((average + 4.2).abs() < 1e-6);
+        assert!(validity_report(&parse_sequence("GAGA").unwrap()).is_valid());
+    }
+
+    #[test]
+    fn test_report_forbidden_pairs() {
+        let c = aa_index(b'C') as u8;
+        let p = aa_index(b'P') as u8;
+        let report = validity_report(&parse_sequence("ACCAGPPG").unwrap());
+        assert_eq!(
+            report.violations,
+            vec![
+                Violation::ForbiddenPair {
+                    pos: 1,
+                    pair: (c, c)
+                },
+                Violation::ForbiddenPair {
+                    pos: 5,
+                    pair: (p, p)
+                },
+            ]
+        );
+        assert_eq!(
+            report.to_string(),
+            "forbidden pair CC at 2; forbidden pair PP at 6"
+        );
+    }
+
+    #[test]
+    fn test_report_homopolymers() {
+        let a = aa_index(b'A') as u8;
+        let report = validity_report(&parse_sequence("GAAAAGAAAGAAAAA").unwrap());
+        // the run of three is allowed
+        assert_eq!(
+            report.violations,
+            vec![
+                Violation::Homopolymer {
+                    pos: 1,
+                    len: 4,
+                    residue: a
+                },
+                Violation::Homopolymer {
+                    pos: 10,
+                    len: 5,
+                    residue: a
+                },
+            ]
+        );
+        assert_eq!(
+            validity_violations(
+                &parse_sequence("GAAAAGAAAGAAAAA").unwrap(),
+                &ValidityConfig::default()
+            ),
+            3
+        );
+    }
+
+    #[test]
+    fn test_report_instability_and_empty() {
+        let cfg = ValidityConfig {
+            max_instability: Some(INSTABILITY_THRESHOLD),
+            ..ValidityConfig::default()
+        };
+        let report = validity_report_with(&parse_sequence("LSRLSRLS").unwrap(), &cfg);
+        assert!(matches!(
+            report.violations[..],
+            [Violation::Instability { index, .. }] if index > INSTABILITY_THRESHOLD
+        ));
+        assert_eq!(validity_report(&[]).violations, vec![Violation::Empty]);
+        assert_eq!(validity_report(&[]).to_string(), "empty sequence");
+    }
+
+    #[test]
+    fn test_glycosylation_sequon() {
+        // wide hydropathy window so only the sequon rule decides
+        let cfg = ValidityConfig {
+            hydro_min: -5.0,
+            avoid_glycosylation: true,
+            ..ValidityConfig::default()
+        };
+        let ngs = parse_sequence("NGS").unwrap();
+        assert_eq!(
+            validity_report_with(&ngs, &cfg).violations,
+            vec![Violation::Sequon { pos: 0 }]
+        );
+        assert!(is_biologically_valid_with(
+            &parse_sequence("NPS").unwrap(),
+            &cfg
+        ));
+        assert!(is_biologically_valid_with(
+            &parse_sequence("NGA").unwrap(),
+            &cfg
+        ));
+        // opt-in: off by default
+        let off = ValidityConfig {
+            avoid_glycosylation: false,
+            ..cfg.clone()
+        };
+        assert!(is_biologically_valid_with(&ngs, &off));
+        assert_eq!(
+            sequon_positions(&parse_sequence("ANATGNKSA").unwrap()).collect::<Vec<_>>(),
+            vec![1, 5]
+        );
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(3);
+        let mut seq = parse_sequence("GNVTANLS").unwrap();
+        repair_validity(&mut seq, &cfg, &mut rng);
+        assert_eq!(sequon_positions(&seq).count(), 0);
+    }
+
+    #[test]
+    fn test_cleavage_sites() {
+        let cfg = ValidityConfig {
+            hydro_min: -5.0,
+            cleavage_sites: vec![CleavageSite::trypsin()],
+            ..ValidityConfig::default()
+        };
+        let kg = parse_sequence("GKGA").unwrap();
+        assert_eq!(validity_violations(&kg, &cfg), 1);
+        assert_eq!(
+            validity_report_with(&kg, &cfg).to_string(),
+            "trypsin cleavage site after 2"
+        );
+        // Pro after Lys blocks trypsin, and a C-terminal Lys has no bond to cut
+        assert_eq!(
+            validity_violations(&parse_sequence("GKPA").unwrap(), &cfg),
+            0
+        );
+        assert_eq!(
+            validity_violations(&parse_sequence("GAGK").unwrap(), &cfg),
+            0
+        );
+        assert_eq!(validity_violations(&kg, &ValidityConfig::default()), 0);
+
+        assert_eq!(
+            CleavageSite::parse("Trypsin").unwrap(),
+            CleavageSite::trypsin()
+        );
+        let chymotrypsin = CleavageSite::parse("FWY!P").unwrap();
+        let f = aa_index(b'F') as u8;
+        assert!(chymotrypsin.cleaves(f, aa_index(b'G') as u8));
+        assert!(!chymotrypsin.cleaves(f, aa_index(b'P') as u8));
+        assert_eq!(
+            CleavageSite::parse("KR!Z"),
+            Err(CleavageSiteError::InvalidResidue { ch: 'Z', pos: 3 })
+        );
+        assert_eq!(
+            CleavageSite::parse("ACDEFGHIKLMNPQRSTVWY"),
+            Err(CleavageSiteError::CutsEverything)
+        );
+        assert!(CleavageSite::parse("ACDEFGHIKLMNPQRSTVWY!P").is_ok());
+        assert_eq!(CleavageSite::parse("!P"), Err(CleavageSiteError::Empty));
+
+        let mut rng = rand::rngs::StdRng::seed_from_u64(5);
+        let mut seq = parse_sequence("GAKGARGA").unwrap();
+        repair_validity(&mut seq, &cfg, &mut rng);
+        assert!(is_biologically_valid_with(&seq, &cfg));
+    }
+
+    #[test]
+    fn test_isoelectric_point() {
+        let poly_k = parse_sequence("KKKKKKKK").unwrap();
+        assert!(isoelectric_point(&poly_k) > 9.0);
+        let acidic = parse_sequence("DEDEDE").unwrap();
+        assert!(isoelectric_point(&acidic) < 4.5);
+        // no ionisable side chains: halfway between the termini
+        let neutral = parse_sequence("GAGA").unwrap();
+        let pi = isoelectric_point(&neutral);
+        assert!(
+            (pi - 0.5 * (PKA_N_TERM + PKA_C_TERM)).abs() < 0.05,
+            "{}",
+            pi
+        );
+    }
+
+    #[test]
+    fn test_charge_term_penalises_distance_from_target() {
+        let _guard = global_lock();
+        set_motif(12).unwrap();
+        let seq = parse_sequence("KWRWKRWKK").unwrap();
+        let off = FitnessWeights::default();
+        let on = FitnessWeights {
+            target_charge: Some(0.0),
+            charge: 2.0,
+            ..off
+        };
+        let diff = combined_fitness_weighted(&seq, &on) - combined_fitness_weighted(&seq, &off);
+        assert!((diff - 2.0 * net_charge(&seq)).abs() < 1e-4);
+        set_motif(0).unwrap();
+    }
+
+    #[test]
+    fn test_breakdown_sums_to_total() {
+        let _guard = global_lock();
+        set_motif(3).unwrap();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(8);
+        for _ in 0..20 {
+            let seq = PeptideProblem::random_individual(&mut rng);
+            let breakdown = combined_fitness_breakdown(&seq);
+            assert_eq!(breakdown.charge, 0.0);
+            assert_eq!(breakdown.blosum + breakdown.nepre, breakdown.total);
+            assert_eq!(breakdown.total, combined_fitness(&seq));
+        }
+
+        let weights = FitnessWeights {
+            target_charge: Some(1.0),
+            ..FitnessWeights::default()
+        };
+        let seq = parse_sequence("KDEKDE").unwrap();
+        let breakdown = combined_fitness_breakdown_weighted(&seq, &weights);
+        assert!((breakdown.charge - 3.0).abs() < 1e-5);
+        assert_eq!(
+            breakdown.blosum + breakdown.nepre + breakdown.charge,
+            breakdown.total
+        );
+
+        // nothing to score: infinite like `combined_fitness`
+        for seq in [vec![], vec![BLANK, BLANK]] {
+            let breakdown = combined_fitness_breakdown(&seq);
+            assert_eq!(breakdown.total, combined_fitness(&seq));
+            assert_eq!(breakdown.total, f32::INFINITY);
+        }
+        set_motif(0).unwrap();
+    }
+
+    #[test]
+    fn test_background_init_follows_composition() {
+        let mut rng = rand::rngs::StdRng::seed_from_u64(44);
+        let seq = random_peptide(&mut rng, 20_000, InitMode::Background);
+        let count = |c: u8| seq.iter().filter(|&&aa| aa as usize == aa_index(c)).count();
+        // ~9.7% Leu vs ~1.1% Trp
+        assert!(
+            count(b'L') > 4 * count(b'W'),
+            "{} vs {}",
+            count(b'L'),
+            count(b'W')
+        );
+
+        let uniform = random_peptide(&mut rng, 20_000, InitMode::Uniform);
+        assert!(uniform.iter().all(|&aa| (aa as usize) < ALPHABET_SIZE));
+    }
+
+    #[test]
+    fn test_guided_substitution_prefers_similar_residues() {
+        let _guard = global_lock();
+        set_motif(1).unwrap(); // RGD
+        let mut rng = rand::rngs::StdRng::seed_from_u64(45);
+        let draws: Vec<u8> = (0..5_000)
+            .map(|_| guided_substitution(&mut rng, 0))
+            .collect();
+        let count = |c: u8| {
+            draws
+                .iter()
+                .filter(|&&aa| aa as usize == aa_index(c))
+                .count()
+        };
+        // R itself, then K (BLOSUM +2), far ahead of W (-3)
+        assert!(count(b'R') > count(b'K'));
+        assert!(count(b'K') > 10 * count(b'W').max(1));
+        set_motif(0).unwrap();
+    }
+
+    #[test]
+    fn test_hydropathy_follows_aa_letters() {
+        let kyte_doolittle = [
+            (b'A', 1.8),
+            (b'R', -4.5),
+            (b'N', -3.5),
+            (b'D', -3.5),
+            (b'C', 2.5),
+            (b'Q', -3.5),
+            (b'E', -3.5),
+            (b'G', -0.4),
+            (b'H', -3.2),
+            (b'I', 4.5),
+            (b'L', 3.8),
+            (b'K', -3.9),
+            (b'M', 1.9),
+            (b'F', 2.8),
+            (b'P', -1.6),
+            (b'S', -0.8),
+            (b'T', -0.7),
+            (b'W', -0.9),
+            (b'Y', -1.3),
+            (b'V', 4.2),
+        ];
+        for (letter, value) in kyte_doolittle {
+            assert_eq!(HYDROPATHY[aa_index(letter)], value, "{}", letter as char);
+        }
+    }
+
+    #[test]
+    fn test_triplet_term_is_opt_in() {
+        let _guard = global_lock();
+        set_motif(6).unwrap();
+        let mut rng = rand::rngs::StdRng::seed_from_u64(57);
+        let seq = PeptideProblem::random_individual(&mut rng);
+        let pair_only = combined_fitness(&seq);
+
+        // t[a][b][c] = (a + 2b + 3c) / 100
+        let rows: Vec<String> = (0..ALPHABET_SIZE * ALPHABET_SIZE)
+            .map(|i| {
+                let (a, b) = (i / ALPHABET_SIZE, i % ALPHABET_SIZE);
+                (0..ALPHABET_SIZE)
+                    .map(|c| format!("{}", (a + 2 * b + 3 * c) as f32 / 100.0))
+                    .collect::<Vec<_>>()
+                    .join(" ")
+            })
+            .collect();
+        let path = std::env::temp_dir().join("peptide_opt_triplet_fitness.txt");
+        std::fs::write(&path, rows.join("\n")).unwrap();
+        nepre::set_triplet_matrix(path.to_str().unwrap()).unwrap();
+
+        let windows: f32 = seq
+            .windows(3)
+            .map(|w| nepre::triplet(w[0], w[1], w[2]))
+            .sum();
+        let expected = pair_only + NEPRE_WEIGHT * windows;
+        assert!((combined_fitness(&seq) - expected).abs() < 1e-3);
+        for pos in [0, 1, seq.len() / 2, seq.len() - 1] {
+            let new_aa = (seq[pos] + 7) % ALPHABET_SIZE as u8;
+            let mut after = seq.clone();
+            after[pos] = new_aa;
+            let got = combined_fitness(&seq) + fitness_delta(&seq, pos, new_aa);
+            assert!((combined_fitness(&after) - got).abs() < 1e-3);
+        }
+
+        nepre::clear_triplet_matrix();
+        assert_eq!(combined_fitness(&seq), pair_only);
+        std::fs::remove_file(path).unwrap();
+        set_motif(0).unwrap();
+    }
+
+    #[test]
+    fn test_empty_sequence_scores_worst() {
+        let _guard = global_lock();
+        assert_eq!(combined_fitness(&[]), f32::INFINITY);
+        assert_eq!(
+            combined_fitness_weighted(&[], &FitnessWeights::default()),
+            f32::INFINITY
+        );
+        // any real peptide beats it
+        assert!(combined_fitness(&[0]) < combined_fitness(&[]));
+    }
+
+    #[test]
+    fn test_default_weights_match_global() {
+        let _guard = global_lock();
+        assert_eq!(fitness_weights(), FitnessWeights::default());
+    }
 }
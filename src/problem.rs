@@ -1,7 +1,10 @@
+use crate::crossover::CrossoverOp;
+use crate::mutation::MutationSet;
+use crate::peptide::{InitMode, ValidityConfig};
 use rand::Rng;
 
 pub trait TSProblem {
-    type Individ: Clone;
+    type Individ: Clone + PartialEq;
     type Move: Clone + PartialEq;
 
     fn random_individual<R: Rng>(rng: &mut R) -> Self::Individ;
@@ -19,4 +22,109 @@ pub trait TSProblem {
     fn repair(ind: &mut Self::Individ) {
         let _ = ind;
     }
+
+    /// `(position, value)` attributes of a solution, counted by the
+    /// long-term memory of tabu search. Problems without any opt out of
+    /// diversification.
+    fn attributes(ind: &Self::Individ) -> Vec<(usize, usize)> {
+        let _ = ind;
+        Vec::new()
+    }
+
+    /// Member of a GA's initial population; `init` picks the sampling scheme
+    /// for problems that have more than one.
+    fn initial_individual<R: Rng>(rng: &mut R, init: InitMode) -> Self::Individ {
+        let _ = init;
+        Self::random_individual(rng)
+    }
+
+    /// GA recombination of two parents into one child. The default keeps
+    /// the first parent, i.e. no crossover.
+    fn crossover<R: Rng>(
+        a: &Self::Individ,
+        b: &Self::Individ,
+        op: &dyn CrossoverOp,
+        rng: &mut R,
+    ) -> Self::Individ {
+        let _ = (b, op, rng);
+        a.clone()
+    }
+
+    /// GA mutation; the default replaces the individual by a random neighbour.
+    fn mutate<R: Rng>(ind: &mut Self::Individ, rng: &mut R) {
+        if let Some((neigh, _)) = Self::neighbourhood(rng, ind, 1).pop() {
+            *ind = neigh;
+        }
+    }
+
+    /// GA mutation by an explicit operator set. The default leaves the
+    /// individual unchanged.
+    fn mutate_with<R: Rng>(ind: &mut Self::Individ, ops: &MutationSet, rng: &mut R) {
+        let _ = (ind, ops, rng);
+    }
+
+    /// Distance in `[0, 1]` used to measure population diversity; the
+    /// default only tells equal individuals apart from different ones.
+    fn distance(a: &Self::Individ, b: &Self::Individ) -> f64 {
+        if a == b {
+            0.0
+        } else {
+            1.0
+        }
+    }
+}
+
+/// Fixed-alphabet sequence problems, as optimised by `NeighbourGA`: genes
+/// are `0..ALPHABET` and the search scores and filters individual loci.
+pub trait SequenceProblem: TSProblem<Individ = Vec<u8>> {
+    /// Number of symbols a gene can take.
+    const ALPHABET: usize;
+
+    /// Fitness change of setting `seq[pos] = value`; the default rescores
+    /// the whole sequence, problems with local terms can do better.
+    fn substitution_delta(seq: &[u8], pos: usize, value: u8) -> f64 {
+        let before = seq.to_vec();
+        let mut after = before.clone();
+        after[pos] = value;
+        Self::fitness(&after) - Self::fitness(&before)
+    }
+
+    /// Plausibility filter applied to offspring; the default accepts all.
+    fn is_valid(seq: &[u8], validity: &ValidityConfig) -> bool {
+        let _ = (seq, validity);
+        true
+    }
+
+    /// Whether `seq` still passes `is_valid` after `seq[pos] = value`, given
+    /// that it passes now; the default rechecks the whole sequence,
+    /// problems with local rules can do better.
+    fn substitution_keeps_valid(
+        seq: &[u8],
+        pos: usize,
+        value: u8,
+        validity: &ValidityConfig,
+    ) -> bool {
+        let mut after = seq.to_vec();
+        after[pos] = value;
+        Self::is_valid(&after, validity)
+    }
+
+    /// Number of plausibility rules `seq` breaks, the basis of penalty
+    /// scoring; the default counts a failed `is_valid` as one.
+    fn violations(seq: &[u8], validity: &ValidityConfig) -> usize {
+        usize::from(!Self::is_valid(seq, validity))
+    }
+
+    /// Edits `seq` towards passing `is_valid`, best effort; the default
+    /// leaves it unchanged.
+    fn repair_validity<R: Rng>(seq: &mut [u8], validity: &ValidityConfig, rng: &mut R) {
+        let _ = (seq, validity, rng);
+    }
+
+    /// Replacement gene for a guided substitution at `pos`; the default
+    /// draws uniformly.
+    fn guided_gene<R: Rng>(rng: &mut R, pos: usize) -> u8 {
+        let _ = pos;
+        rng.gen_range(0..Self::ALPHABET) as u8
+    }
 }
@@ -0,0 +1,173 @@
+//! Position-specific scoring matrices: per-position residue preferences, the
+//! way motif finders such as MEME report a motif, used as the target in place
+//! of a single motif string (`ScoringMode::Pssm`).
+
+use crate::data::ALPHABET_SIZE;
+use crate::nepre::{self, NepreError};
+use crate::util::best_index;
+
+/// One row per motif position, one column per residue in `AA_LETTERS`
+/// order; higher values are preferred.
+pub type Pssm = Vec<[f32; ALPHABET_SIZE]>;
+
+/// Errors raised while reading a PSSM.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PssmError {
+    /// Token at `line`/`column` (both 1-based) is not a number.
+    Parse {
+        line: usize,
+        column: usize,
+        token: String,
+    },
+    /// Row on `line` does not have exactly 20 values.
+    RowLength { line: usize, found: usize },
+    /// The file has no data rows.
+    Empty,
+    /// The file could not be read.
+    Io { path: String, message: String },
+}
+
+impl std::fmt::Display for PssmError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PssmError::Parse {
+                line,
+                column,
+                token,
+            } => write!(
+                f,
+                "line {}, column {}: '{}' is not a number",
+                line, column, token
+            ),
+            PssmError::RowLength { line, found } => write!(
+                f,
+                "line {}: expected {} values, found {}",
+                line, ALPHABET_SIZE, found
+            ),
+            PssmError::Empty => write!(f, "PSSM has no positions"),
+            PssmError::Io { path, message } => write!(f, "{}: {}", path, message),
+        }
+    }
+}
+
+impl std::error::Error for PssmError {}
+
+// `nepre::parse_table` reports malformed rows as NEPRE errors
+impl From<NepreError> for PssmError {
+    fn from(e: NepreError) -> Self {
+        match e {
+            NepreError::Parse {
+                line,
+                column,
+                token,
+            } => PssmError::Parse {
+                line,
+                column,
+                token,
+            },
+            NepreError::RowLength { line, found } => PssmError::RowLength { line, found },
+            NepreError::RowCount { .. } => PssmError::Empty,
+            NepreError::Io { path, message } => PssmError::Io { path, message },
+        }
+    }
+}
+
+/// Parses a PSSM: one row of 20 whitespace-separated values per position,
+/// with the same comment and blank-line rules as `nepre::load_nepre`. At
+/// least one row is required.
+pub fn parse(txt: &str) -> Result<Pssm, PssmError> {
+    let pssm = nepre::parse_table(txt)?;
+    if pssm.is_empty() {
+        return Err(PssmError::Empty);
+    }
+    Ok(pssm)
+}
+
+/// Reads and parses a PSSM file (see `parse`).
+pub fn load(path: &str) -> Result<Pssm, PssmError> {
+    let txt = std::fs::read_to_string(path).map_err(|e| PssmError::Io {
+        path: path.to_string(),
+        message: e.to_string(),
+    })?;
+    parse(&txt)
+}
+
+/// Energy of `seq` under `pssm` (lower = better): minus the sum of
+/// `pssm[i % len][seq[i]]`, so longer peptides wrap like a positional motif.
+pub fn energy(seq: &[u8], pssm: &[[f32; ALPHABET_SIZE]]) -> f32 {
+    -seq.iter()
+        .enumerate()
+        .map(|(i, &aa)| pssm[i % pssm.len()][aa as usize])
+        .sum::<f32>()
+}
+
+/// Preferred residue at each position, the lowest index on a tie.
+pub fn consensus(pssm: &[[f32; ALPHABET_SIZE]]) -> Vec<u8> {
+    pssm.iter()
+        .map(|row| {
+            let best = best_index(&row.map(|v| -v));
+            best.map_or(0, |(aa, _)| aa as u8)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peptide::{
+        combined_fitness, global_lock, parse_sequence, PeptideProblem, ScoringMode,
+    };
+
+    // Strongly prefers R, G, D in turn and is indifferent to everything else
+    fn rgd_pssm() -> Pssm {
+        parse_sequence("RGD")
+            .unwrap()
+            .into_iter()
+            .map(|aa| {
+                let mut row = [0.0; ALPHABET_SIZE];
+                row[aa as usize] = 10.0;
+                row
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parse() {
+        let row = |v: &str| vec![v; ALPHABET_SIZE].join(" ");
+        let txt = format!("# two positions\n{}\n\n{}\n", row("1"), row("-0.5"));
+        let pssm = parse(&txt).unwrap();
+        assert_eq!(pssm.len(), 2);
+        assert_eq!(pssm[1], [-0.5; ALPHABET_SIZE]);
+        assert_eq!(parse("# nothing\n"), Err(PssmError::Empty));
+        assert_eq!(
+            parse("# nothing\n").unwrap_err().to_string(),
+            "PSSM has no positions"
+        );
+        assert_eq!(
+            parse("1 2 3"),
+            Err(PssmError::RowLength { line: 1, found: 3 })
+        );
+    }
+
+    #[test]
+    fn test_pssm_favours_matching_peptide() {
+        let _guard = global_lock();
+        let pssm = rgd_pssm();
+        assert_eq!(consensus(&pssm), parse_sequence("RGD").unwrap());
+        PeptideProblem::set_scoring_mode(ScoringMode::Pssm(pssm));
+
+        let rgd = parse_sequence("RGD").unwrap();
+        assert_eq!(PeptideProblem::energy(&rgd), -30.0);
+        let best = combined_fitness(&rgd);
+        for other in ["RGE", "KGD", "DGR", "AAA", "RAD"] {
+            let seq = parse_sequence(other).unwrap();
+            assert!(PeptideProblem::energy(&seq) > PeptideProblem::energy(&rgd));
+            assert!(
+                combined_fitness(&seq) > best,
+                "{} scored as well as RGD",
+                other
+            );
+        }
+        PeptideProblem::set_scoring_mode(ScoringMode::Positional);
+    }
+}
@@ -0,0 +1,217 @@
+//! Summary statistics over repeated runs, and the convergence of a single one.
+
+/// Spread of a sample of final fitness values (lower = better).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Summary {
+    pub n: usize,
+    pub mean: f64,
+    /// Sample standard deviation; `0` for a single value.
+    pub std_dev: f64,
+    pub best: f64,
+    pub worst: f64,
+}
+
+/// Arithmetic mean; NaN for an empty sample.
+pub fn mean(xs: &[f64]) -> f64 {
+    xs.iter().sum::<f64>() / xs.len() as f64
+}
+
+/// Sample standard deviation (`n - 1` denominator); `0` below two values.
+pub fn std_dev(xs: &[f64]) -> f64 {
+    if xs.len() < 2 {
+        return 0.0;
+    }
+    let m = mean(xs);
+    let ss: f64 = xs.iter().map(|x| (x - m).powi(2)).sum();
+    (ss / (xs.len() - 1) as f64).sqrt()
+}
+
+/// `None` for an empty sample.
+pub fn summarize(xs: &[f64]) -> Option<Summary> {
+    if xs.is_empty() {
+        return None;
+    }
+    Some(Summary {
+        n: xs.len(),
+        mean: mean(xs),
+        std_dev: std_dev(xs),
+        best: xs.iter().copied().fold(f64::INFINITY, f64::min),
+        worst: xs.iter().copied().fold(f64::NEG_INFINITY, f64::max),
+    })
+}
+
+/// Trailing generations of a (generation, min, max, avg) progress series
+/// whose min fitness did not improve on the best before them; `0` if the
+/// last generation improved or the series is empty.
+pub fn plateau_length(progress: &[(usize, f32, f32, f32)]) -> usize {
+    let mut best = f32::INFINITY;
+    let mut last_improvement = 0;
+    for (i, &(_, min, _, _)) in progress.iter().enumerate() {
+        if min < best {
+            best = min;
+            last_improvement = i;
+        }
+    }
+    progress.len().saturating_sub(last_improvement + 1)
+}
+
+/// Pairs above which `wilcoxon_signed_rank` switches from the exact null
+/// distribution to the normal approximation.
+const WILCOXON_EXACT_MAX: usize = 50;
+
+/// Two-sided p-value of the Wilcoxon signed-rank test that the paired
+/// differences `a[i] - b[i]` are symmetric about zero.
+///
+/// Zero differences are dropped and tied magnitudes get their average rank.
+/// Up to `WILCOXON_EXACT_MAX` pairs the p-value is exact; beyond that it uses
+/// the normal approximation with tie and continuity corrections. Returns `1`
+/// when no non-zero difference is left.
+pub fn wilcoxon_signed_rank(a: &[f64], b: &[f64]) -> f64 {
+    assert_eq!(a.len(), b.len(), "samples must be paired");
+    let mut diffs: Vec<f64> = a
+        .iter()
+        .zip(b)
+        .map(|(x, y)| x - y)
+        .filter(|&d| d != 0.0)
+        .collect();
+    let n = diffs.len();
+    if n == 0 {
+        return 1.0;
+    }
+    diffs.sort_by(|x, y| x.abs().partial_cmp(&y.abs()).unwrap());
+
+    // doubled average ranks stay integral: ties share (first + last) ranks
+    let mut ranks2 = vec![0usize; n];
+    let mut tie_term = 0.0;
+    let mut i = 0;
+    while i < n {
+        let mut j = i;
+        while j + 1 < n && diffs[j + 1].abs() == diffs[i].abs() {
+            j += 1;
+        }
+        ranks2[i..=j].fill(i + j + 2);
+        let t = (j - i + 1) as f64;
+        tie_term += t * t * t - t;
+        i = j + 1;
+    }
+    let w2: usize = diffs
+        .iter()
+        .zip(&ranks2)
+        .filter(|(d, _)| **d > 0.0)
+        .map(|(_, &r)| r)
+        .sum();
+
+    let p = if n <= WILCOXON_EXACT_MAX {
+        // null distribution of the doubled positive rank sum: every
+        // difference is positive or negative with probability 1/2
+        let total: usize = ranks2.iter().sum();
+        let mut dist = vec![0.0; total + 1];
+        dist[0] = 1.0;
+        for &r in &ranks2 {
+            for s in (0..=total).rev() {
+                let with = if s >= r { dist[s - r] } else { 0.0 };
+                dist[s] = 0.5 * (dist[s] + with);
+            }
+        }
+        let lower: f64 = dist[..=w2].iter().sum();
+        let upper: f64 = dist[w2..].iter().sum();
+        2.0 * lower.min(upper)
+    } else {
+        let nf = n as f64;
+        let w = w2 as f64 / 2.0;
+        let mu = nf * (nf + 1.0) / 4.0;
+        let var = nf * (nf + 1.0) * (2.0 * nf + 1.0) / 24.0 - tie_term / 48.0;
+        let z = ((w - mu).abs() - 0.5).max(0.0) / var.sqrt();
+        2.0 * (1.0 - standard_normal_cdf(z))
+    };
+    p.min(1.0)
+}
+
+// Abramowitz & Stegun 7.1.26 approximation of erf, error below 1.5e-7
+fn standard_normal_cdf(z: f64) -> f64 {
+    let x = z.abs() / std::f64::consts::SQRT_2;
+    let t = 1.0 / (1.0 + 0.3275911 * x);
+    let poly = t
+        * (0.254829592
+            + t * (-0.284496736 + t * (1.421413741 + t * (-1.453152027 + t * 1.061405429))));
+    let erf = 1.0 - poly * (-x * x).exp();
+    if z >= 0.0 {
+        0.5 * (1.0 + erf)
+    } else {
+        0.5 * (1.0 - erf)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summarize() {
+        let s = summarize(&[2.0, 4.0, 4.0, 4.0, 5.0, 5.0, 7.0, 9.0]).unwrap();
+        assert_eq!(s.n, 8);
+        assert_eq!(s.mean, 5.0);
+        // squared deviations sum to 32
+        assert!((s.std_dev - (32.0f64 / 7.0).sqrt()).abs() < 1e-12);
+        assert_eq!((s.best, s.worst), (2.0, 9.0));
+
+        let one = summarize(&[-3.5]).unwrap();
+        assert_eq!(
+            (one.mean, one.std_dev, one.best, one.worst),
+            (-3.5, 0.0, -3.5, -3.5)
+        );
+        assert_eq!(summarize(&[]), None);
+    }
+
+    #[test]
+    fn test_plateau_length() {
+        let progress: Vec<_> = [5.0, 4.0, 3.0, 3.5, 3.0, 3.0]
+            .iter()
+            .enumerate()
+            .map(|(g, &min)| (g, min, 9.0, 6.0))
+            .collect();
+        // improving stops at generation 2; equal or worse minima do not count
+        assert_eq!(plateau_length(&progress), 3);
+        assert_eq!(plateau_length(&progress[..3]), 0);
+        assert_eq!(plateau_length(&progress[..1]), 0);
+        assert_eq!(plateau_length(&[]), 0);
+    }
+
+    #[test]
+    fn test_wilcoxon_small_example() {
+        let b = [0.0; 5];
+        // all five differences positive: W+ = 15 has probability 1/32
+        assert!((wilcoxon_signed_rank(&[1.0, 2.0, 3.0, 4.0, 5.0], &b) - 2.0 / 32.0).abs() < 1e-12);
+        // differences 1, -2, 3, 4, 5: W- = 2, reached by {}, {1}, {2}
+        assert!((wilcoxon_signed_rank(&[1.0, -2.0, 3.0, 4.0, 5.0], &b) - 6.0 / 32.0).abs() < 1e-12);
+        // symmetric in the order of the samples
+        assert_eq!(
+            wilcoxon_signed_rank(&[1.0, -2.0, 3.0], &[0.0; 3]),
+            wilcoxon_signed_rank(&[0.0; 3], &[1.0, -2.0, 3.0])
+        );
+        // ties: |d| = 1, 1, 2 get ranks 1.5, 1.5, 3; W+ = 6 of 6 has probability 1/8
+        assert!((wilcoxon_signed_rank(&[1.0, 1.0, 2.0], &[0.0; 3]) - 0.25).abs() < 1e-12);
+        // zero differences are dropped
+        assert_eq!(wilcoxon_signed_rank(&[1.0, 2.0], &[1.0, 2.0]), 1.0);
+    }
+
+    #[test]
+    fn test_wilcoxon_normal_approximation() {
+        // 60 pairs, all improved: far beyond any plausible null
+        let a: Vec<f64> = (1..=60).map(f64::from).collect();
+        let p = wilcoxon_signed_rank(&a, &[0.0; 60]);
+        assert!(p < 1e-9);
+        // alternating signs of equal magnitude-ranked differences: no effect
+        let a: Vec<f64> = (1..=60)
+            .map(|i| {
+                if i % 2 == 0 {
+                    f64::from(i)
+                } else {
+                    -f64::from(i)
+                }
+            })
+            .collect();
+        assert!(wilcoxon_signed_rank(&a, &[0.0; 60]) > 0.5);
+        assert!((standard_normal_cdf(1.959964) - 0.975).abs() < 1e-6);
+    }
+}
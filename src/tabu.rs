@@ -1,74 +1,532 @@
 use crate::problem::TSProblem;
 use rand::SeedableRng;
-use std::collections::VecDeque;
+use std::cell::Cell;
+use std::collections::hash_map::DefaultHasher;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::time::{Duration, Instant};
 
+/// Number of recently visited solutions remembered to detect cycling.
+const VISIT_HISTORY: usize = 50;
+
+/// Growth / shrink factors of the reactive tenure.
+const TENURE_GROW: f64 = 1.2;
+const TENURE_SHRINK: f64 = 0.9;
+
+/// Next tenure of reactive tabu search (Battiti & Tecchiolli): grow on a
+/// revisit, shrink after `current` iterations without one, clamp to bounds.
+fn adapt_tenure(current: usize, revisit: bool, quiet_for: usize, min: usize, max: usize) -> usize {
+    let next = if revisit {
+        ((current as f64 * TENURE_GROW).ceil() as usize).max(current + 1)
+    } else if quiet_for > current {
+        (current as f64 * TENURE_SHRINK).floor() as usize
+    } else {
+        current
+    };
+    next.clamp(min, max)
+}
+
+/// Index of the candidate to move to: the lowest fitness plus `penalty`
+/// among those that are not tabu or that `aspiration` admits; `None` if
+/// there is none. `candidates` holds each one's fitness and tabu status.
+fn best_admissible(
+    candidates: &[(f64, bool)],
+    aspiration: AspirationMode,
+    curr_f: f64,
+    best_f: f64,
+    penalty: impl Fn(usize) -> f64,
+) -> Option<usize> {
+    let mut chosen = None;
+    let mut chosen_score = f64::INFINITY;
+    for (i, &(f, tabu)) in candidates.iter().enumerate() {
+        if tabu && !aspiration.admits(f, curr_f, best_f) {
+            continue;
+        }
+        let score = f + penalty(i);
+        if score < chosen_score {
+            chosen = Some(i);
+            chosen_score = score;
+        }
+    }
+    chosen
+}
+
+fn hash_of<T: Hash>(x: &T) -> u64 {
+    let mut h = DefaultHasher::new();
+    x.hash(&mut h);
+    h.finish()
+}
+
+/// Outcome of `TabuSearch::run`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TabuResult<I> {
+    pub best: I,
+    /// Best fitness so far after every iteration.
+    pub trace: Vec<(usize, f64)>,
+    /// Iteration that produced `best`; `0` if the start was never improved.
+    pub best_found_at: usize,
+    /// Number of `P::fitness` calls made by the run.
+    pub evaluations: u64,
+    /// Iterations at which frequency-based diversification switched on.
+    pub diversified_at: Vec<usize>,
+    /// Iterations after which the tabu list was cleared by a reheat.
+    pub reheated_at: Vec<usize>,
+    /// Current solution and its fitness after every iteration, the search
+    /// path under the best-so-far `trace`; empty unless `record_trajectory`.
+    pub trajectory: Vec<(I, f64)>,
+}
+
+/// What the tabu list remembers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum TabuMode {
+    /// Recently applied moves; membership is a linear scan of the list.
+    #[default]
+    Move,
+    /// Hashes of recently visited solutions, kept in a `HashSet` for O(1)
+    /// membership. Unlike a positional move, a state hash cannot forbid an
+    /// unrelated solution.
+    State,
+}
+
+/// When a candidate on the tabu list may be taken anyway.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize))]
+pub enum AspirationMode {
+    /// It beats the best solution found so far, the textbook rule.
+    #[default]
+    BetterThanBest,
+    /// It beats the current solution by more than the margin.
+    BetterThanCurrent(f64),
+}
+
+impl AspirationMode {
+    /// Whether a tabu candidate of fitness `f` is admissible, given the
+    /// fitness of the current and of the best solution.
+    pub fn admits(self, f: f64, curr_f: f64, best_f: f64) -> bool {
+        match self {
+            AspirationMode::BetterThanBest => f < best_f,
+            AspirationMode::BetterThanCurrent(margin) => f + margin < curr_f,
+        }
+    }
+}
+
+#[cfg_attr(feature = "serde", derive(serde::Serialize), serde(bound = ""))]
 pub struct TabuSearch<P: TSProblem> {
     pub iterations: usize,
     pub neigh_size: usize,
+    /// Tabu list length (the starting one when `reactive` is set).
     pub tabu_len: usize,
+    /// Adapt the tenure to how often recently visited solutions repeat,
+    /// staying within `min_len..=max_len`; `run` panics if `min_len`
+    /// exceeds `max_len`.
+    pub reactive: bool,
+    pub min_len: usize,
+    pub max_len: usize,
+    pub mode: TabuMode,
+    pub aspiration: AspirationMode,
+    /// Long-term memory: once the best has not improved for
+    /// `diversify_after` iterations, candidates are penalised by
+    /// `diversify_weight` times the average frequency of their
+    /// `(position, value)` attributes in previously accepted solutions.
+    pub diversify: bool,
+    pub diversify_after: usize,
+    pub diversify_weight: f64,
+    /// Clear the tabu list every `n` iterations; `None` never reheats.
+    pub reheat_every: Option<usize>,
+    /// Wall-clock limit checked before each iteration; the best so far is
+    /// returned once it is exceeded.
+    pub time_budget: Option<Duration>,
+    /// Keep a copy of the current solution of every iteration in
+    /// `TabuResult::trajectory`.
+    pub record_trajectory: bool,
+    #[cfg_attr(feature = "serde", serde(skip))]
     pub(crate) _phantom: std::marker::PhantomData<P>,
 }
 
-impl<P: TSProblem> TabuSearch<P> {
-    pub fn run(&self, seed: u64) -> (P::Individ, Vec<(usize, f64)>) {
+impl<P: TSProblem> TabuSearch<P>
+where
+    P::Individ: Hash,
+{
+    pub fn new(iterations: usize, neigh_size: usize, tabu_len: usize) -> Self {
+        Self {
+            iterations,
+            neigh_size,
+            tabu_len,
+            reactive: false,
+            min_len: 1,
+            max_len: tabu_len.max(1) * 4,
+            mode: TabuMode::Move,
+            aspiration: AspirationMode::BetterThanBest,
+            diversify: false,
+            diversify_after: 100,
+            diversify_weight: 1.0,
+            reheat_every: None,
+            time_budget: None,
+            record_trajectory: false,
+            _phantom: std::marker::PhantomData,
+        }
+    }
+
+    pub fn run(&self, seed: u64) -> TabuResult<P::Individ> {
+        let evaluations = Cell::new(0u64);
+        let fitness = |ind: &P::Individ| {
+            evaluations.set(evaluations.get() + 1);
+            P::fitness(ind)
+        };
+
         let mut rng = rand::rngs::StdRng::seed_from_u64(seed);
         let mut best = P::random_individual(&mut rng);
         let mut curr = best.clone();
-        let mut best_f = P::fitness(&best);
+        let mut best_f = fitness(&best);
+        let mut curr_f = best_f;
+        let mut best_found_at = 0;
 
         // keeps last moves to avoid revisiting them
         let mut tabu: VecDeque<P::Move> = VecDeque::with_capacity(self.tabu_len);
+        // state mode: FIFO order of the hashes plus a set for membership
+        let mut tabu_states: VecDeque<u64> = VecDeque::with_capacity(self.tabu_len);
+        let mut tabu_set: HashSet<u64> = HashSet::with_capacity(self.tabu_len);
+        let (min_len, max_len) = (self.min_len, self.max_len);
+        let mut tenure = if self.reactive {
+            assert!(
+                min_len <= max_len,
+                "tabu min_len {} exceeds max_len {}",
+                min_len,
+                max_len
+            );
+            self.tabu_len.clamp(min_len, max_len)
+        } else {
+            self.tabu_len
+        };
+        let mut visited: VecDeque<u64> = VecDeque::with_capacity(VISIT_HISTORY);
+        let mut last_revisit = 0;
+
+        // long-term memory: how often each attribute was in an accepted solution
+        let mut freq: HashMap<(usize, usize), usize> = HashMap::new();
+        let mut accepted = 0usize;
+        let mut last_improvement = 0;
+        let mut diversifying = false;
+        let mut diversified_at = Vec::new();
+        let mut reheated_at = Vec::new();
 
         let mut trace = Vec::new();
+        let mut trajectory = Vec::new();
+        let start = Instant::now();
 
         for it in 0..self.iterations {
-            // generete neighbourhood
-            let neigh = P::neighbourhood(&mut rng, &curr, self.neigh_size);
-
-            // choose the best candidate that is not on tabu list
-            let (mut chosen_ind, mut chosen_mv, mut chosen_f) = (None, None, f64::INFINITY);
-            for (cand, mv) in neigh {
-                if tabu.contains(&mv) && P::fitness(&cand) >= best_f {
-                    continue; // skip this move because of tabu
+            if self.time_budget.is_some_and(|b| start.elapsed() >= b) {
+                break;
+            }
+
+            let stalled = self.diversify && it - last_improvement >= self.diversify_after;
+            if stalled && !diversifying {
+                diversified_at.push(it);
+            }
+            diversifying = stalled;
+            let penalty = |cand: &P::Individ| {
+                if !diversifying || accepted == 0 {
+                    return 0.0;
                 }
-                let f = P::fitness(&cand);
+                let used: usize = P::attributes(cand)
+                    .iter()
+                    .map(|a| freq.get(a).copied().unwrap_or(0))
+                    .sum();
+                self.diversify_weight * used as f64 / accepted as f64
+            };
 
-                // aspiration (if tabu move is better than current best)
-                let tabu_hit = tabu.contains(&mv);
-                let aspiration = f + 1.0 < P::fitness(&curr);
-                if tabu_hit && !aspiration {
-                    continue;
+            // score the neighbourhood once, then take the best candidate
+            // that is not tabu or that the aspiration rule lets through
+            let mut neigh = P::neighbourhood(&mut rng, &curr, self.neigh_size);
+            let scored: Vec<(f64, bool)> = neigh
+                .iter()
+                .map(|(cand, mv)| {
+                    let tabu_hit = match self.mode {
+                        TabuMode::Move => tabu.contains(mv),
+                        TabuMode::State => tabu_set.contains(&hash_of(cand)),
+                    };
+                    (fitness(cand), tabu_hit)
+                })
+                .collect();
+            let chosen = best_admissible(&scored, self.aspiration, curr_f, best_f, |i| {
+                penalty(&neigh[i].0)
+            });
+
+            if let Some(i) = chosen {
+                let (ind, mv) = neigh.swap_remove(i);
+                curr = ind;
+                curr_f = scored[i].0;
+                if self.diversify {
+                    for a in P::attributes(&curr) {
+                        *freq.entry(a).or_insert(0) += 1;
+                    }
+                    accepted += 1;
                 }
 
-                if f < chosen_f {
-                    chosen_ind = Some(cand); // candidate individual
-                    chosen_mv = Some(mv); // candidate move
-                    chosen_f = f; // candidate fitness
+                // reactive tenure: cycling back to a recent solution means
+                // the list is too short, a long quiet stretch that it is too long
+                if self.reactive {
+                    let h = hash_of(&curr);
+                    let revisit = visited.contains(&h);
+                    if revisit {
+                        last_revisit = it;
+                    }
+                    let next = adapt_tenure(tenure, revisit, it - last_revisit, min_len, max_len);
+                    if next < tenure {
+                        last_revisit = it;
+                    }
+                    tenure = next;
+                    if visited.len() == VISIT_HISTORY {
+                        visited.pop_front();
+                    }
+                    visited.push_back(h);
                 }
-            }
 
-            if let (Some(ind), Some(mv)) = (chosen_ind, chosen_mv) {
-                curr = ind;
                 // update tabu list
-                if tabu.len() == self.tabu_len {
-                    tabu.pop_front();
+                match self.mode {
+                    TabuMode::Move => {
+                        while !tabu.is_empty() && tabu.len() >= tenure {
+                            tabu.pop_front();
+                        }
+                        tabu.push_back(mv);
+                    }
+                    TabuMode::State => {
+                        while tabu_states.len() >= tenure.max(1) {
+                            if let Some(old) = tabu_states.pop_front() {
+                                tabu_set.remove(&old);
+                            }
+                        }
+                        let h = hash_of(&curr);
+                        if tabu_set.insert(h) {
+                            tabu_states.push_back(h);
+                        }
+                    }
                 }
-                tabu.push_back(mv);
             }
 
             // update global-best
-            let curr_f = P::fitness(&curr);
             if curr_f < best_f {
                 best = curr.clone();
                 best_f = curr_f;
+                best_found_at = it;
+                last_improvement = it;
             }
             trace.push((it, best_f));
+            if self.record_trajectory {
+                trajectory.push((curr.clone(), curr_f));
+            }
 
             // reheat
-            if it % 10_000 == 0 && it != 0 {
-                tabu.clear();
+            if let Some(n) = self.reheat_every.filter(|&n| n > 0) {
+                if it != 0 && it % n == 0 {
+                    tabu.clear();
+                    tabu_states.clear();
+                    tabu_set.clear();
+                    reheated_at.push(it);
+                }
             }
         }
-        (best, trace)
+        TabuResult {
+            best,
+            trace,
+            best_found_at,
+            evaluations: evaluations.get(),
+            diversified_at,
+            reheated_at,
+            trajectory,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::peptide::{global_lock, set_motif, PeptideProblem};
+
+    #[test]
+    fn test_adapt_tenure() {
+        // revisit grows, clamped at max
+        assert_eq!(adapt_tenure(10, true, 0, 2, 40), 12);
+        assert_eq!(adapt_tenure(38, true, 0, 2, 40), 40);
+        // quiet for longer than the tenure shrinks, clamped at min
+        assert_eq!(adapt_tenure(10, false, 11, 2, 40), 9);
+        assert_eq!(adapt_tenure(2, false, 50, 2, 40), 2);
+        // otherwise unchanged
+        assert_eq!(adapt_tenure(10, false, 5, 2, 40), 10);
+    }
+
+    #[test]
+    fn test_aspiration_modes() {
+        // better than the best: admitted by the textbook rule even though it
+        // barely improves on the current solution
+        let (f, curr_f, best_f) = (-10.5, -10.0, -10.2);
+        assert!(AspirationMode::BetterThanBest.admits(f, curr_f, best_f));
+        assert!(!AspirationMode::BetterThanCurrent(1.0).admits(f, curr_f, best_f));
+        assert!(AspirationMode::BetterThanCurrent(0.1).admits(f, curr_f, best_f));
+        // no better than the best
+        assert!(!AspirationMode::BetterThanBest.admits(-10.2, curr_f, best_f));
+    }
+
+    #[test]
+    fn test_best_admissible() {
+        let no_penalty = |_| 0.0;
+        let pick = |cands: &[(f64, bool)]| {
+            best_admissible(
+                cands,
+                AspirationMode::BetterThanBest,
+                -5.0,
+                -8.0,
+                no_penalty,
+            )
+        };
+
+        // a tabu improving move that does not beat the best is passed over
+        // for a non-tabu one
+        assert_eq!(pick(&[(-7.0, true), (-6.0, false)]), Some(1));
+        // a non-tabu worsening move is taken when it is the only admissible one
+        assert_eq!(pick(&[(-7.0, true), (-2.0, false)]), Some(1));
+        assert_eq!(pick(&[(-7.0, true)]), None);
+        // aspiration: a tabu move beating the best is taken
+        assert_eq!(pick(&[(-9.0, true), (-6.0, false)]), Some(0));
+
+        // the penalty decides among admissible candidates
+        let penalised = best_admissible(
+            &[(-6.0, false), (-5.5, false)],
+            AspirationMode::BetterThanBest,
+            -5.0,
+            -8.0,
+            |i| if i == 0 { 1.0 } else { 0.0 },
+        );
+        assert_eq!(penalised, Some(1));
+    }
+
+    #[test]
+    fn test_state_mode_forbids_revisits() {
+        let _guard = global_lock();
+        set_motif(7).unwrap();
+
+        let tenure = 10;
+        let mut ts = TabuSearch::<PeptideProblem>::new(300, 30, tenure);
+        ts.mode = TabuMode::State;
+        ts.record_trajectory = true;
+        let TabuResult {
+            best,
+            trace,
+            best_found_at,
+            trajectory,
+            ..
+        } = ts.run(3);
+        assert_eq!(best.len(), 22);
+        assert_eq!(trace.len(), 300);
+        // no solution comes back while it is still on the tabu list
+        for (i, (genome, _)) in trajectory.iter().enumerate() {
+            let recent = &trajectory[i.saturating_sub(tenure)..i];
+            assert!(
+                recent.iter().all(|(g, _)| g != genome),
+                "iteration {} revisits a tabu state",
+                i
+            );
+        }
+        // the trace first reaches its final value when the best was found
+        let final_f = trace.last().unwrap().1;
+        assert_eq!(trace[best_found_at].1, final_f);
+        assert!(best_found_at == 0 || trace[best_found_at - 1].1 > final_f);
+        // best-so-far never gets worse
+        assert!(trace.windows(2).all(|w| w[1].1 <= w[0].1));
+    }
+
+    #[test]
+    fn test_trajectory() {
+        let _guard = global_lock();
+        set_motif(2).unwrap();
+
+        let mut ts = TabuSearch::<PeptideProblem>::new(50, 10, 5);
+        assert!(ts.run(1).trajectory.is_empty());
+
+        ts.record_trajectory = true;
+        let res = ts.run(1);
+        assert_eq!(res.trajectory.len(), 50);
+        for ((ind, f), (_, best_f)) in res.trajectory.iter().zip(&res.trace) {
+            assert_eq!(*f, PeptideProblem::fitness(ind));
+            assert!(*f >= *best_f);
+        }
+        assert!(res.trajectory.iter().any(|(ind, _)| *ind == res.best));
+    }
+
+    #[test]
+    fn test_reheat_schedule() {
+        let _guard = global_lock();
+        set_motif(7).unwrap();
+
+        let mut ts = TabuSearch::<PeptideProblem>::new(100, 10, 5);
+        assert!(ts.run(0).reheated_at.is_empty());
+
+        ts.reheat_every = Some(30);
+        assert_eq!(ts.run(0).reheated_at, vec![30, 60, 90]);
+
+        // with a tenure longer than the run a state can only come back once
+        // a reheat has emptied the list
+        let revisits = |res: TabuResult<Vec<u8>>| {
+            let t = res.trajectory;
+            (1..t.len())
+                .filter(|&i| t[..i].iter().any(|(g, _)| *g == t[i].0))
+                .count()
+        };
+        let mut ts = TabuSearch::<PeptideProblem>::new(300, 30, 1000);
+        ts.mode = TabuMode::State;
+        ts.record_trajectory = true;
+        assert_eq!(revisits(ts.run(4)), 0);
+        ts.reheat_every = Some(10);
+        assert!(revisits(ts.run(4)) > 0);
+    }
+
+    #[test]
+    fn test_diversification_kicks_in() {
+        let _guard = global_lock();
+        set_motif(7).unwrap();
+
+        let plain = TabuSearch::<PeptideProblem>::new(600, 30, 5).run(2);
+        assert!(plain.diversified_at.is_empty());
+
+        let mut ts = TabuSearch::<PeptideProblem>::new(600, 30, 5);
+        ts.diversify = true;
+        ts.diversify_after = 50;
+        let res = ts.run(2);
+        assert!(!res.diversified_at.is_empty());
+        assert!(res.diversified_at.iter().all(|&it| it >= 50));
+        assert_eq!(res.best.len(), 22);
+    }
+
+    #[test]
+    fn test_reactive_vs_static_on_motif_7() {
+        let _guard = global_lock();
+        set_motif(7).unwrap();
+
+        let mut static_sum = 0.0;
+        let mut reactive_sum = 0.0;
+        for seed in 0..4 {
+            // a one-move tabu list cycles easily, which the reactive tenure counters
+            let fixed = TabuSearch::<PeptideProblem>::new(300, 100, 1);
+            let mut reactive = TabuSearch::<PeptideProblem>::new(300, 100, 1);
+            reactive.reactive = true;
+            reactive.min_len = 2;
+            reactive.max_len = 40;
+
+            let static_trace = fixed.run(seed).trace;
+            let TabuResult {
+                best,
+                trace: reactive_trace,
+                ..
+            } = reactive.run(seed);
+            assert_eq!(best.len(), 22);
+            static_sum += static_trace.last().unwrap().1;
+            reactive_sum += reactive_trace.last().unwrap().1;
+        }
+        assert!(
+            reactive_sum <= static_sum + 1e-6,
+            "reactive {} vs static {}",
+            reactive_sum / 4.0,
+            static_sum / 4.0
+        );
     }
 }
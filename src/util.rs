@@ -0,0 +1,15 @@
+//! Small helpers shared by the scoring and search modules.
+
+/// Index and value of the lowest (best) fitness, the lowest index on a tie,
+/// so the winner does not depend on how evaluation was scheduled; `None`
+/// for an empty slice.
+pub(crate) fn best_index<F: PartialOrd + Copy>(fitness: &[F]) -> Option<(usize, F)> {
+    let mut best: Option<(usize, F)> = None;
+    for (i, &f) in fitness.iter().enumerate() {
+        let better = best.is_none_or(|(_, b)| f.partial_cmp(&b).unwrap().is_lt());
+        if better {
+            best = Some((i, f));
+        }
+    }
+    best
+}
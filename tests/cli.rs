@@ -0,0 +1,165 @@
+//! Runs the binary's subcommands end to end.
+
+use std::process::{Command, Output};
+
+fn peptide_opt(args: &[&str]) -> Output {
+    Command::new(env!("CARGO_BIN_EXE_peptide_opt"))
+        .args(args)
+        .output()
+        .expect("failed to start peptide_opt")
+}
+
+fn stdout(output: &Output) -> String {
+    assert!(
+        output.status.success(),
+        "exited with {}: {}",
+        output.status,
+        String::from_utf8_lossy(&output.stderr)
+    );
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+const SMALL_RUN: [&str; 7] = [
+    "--motif",
+    "3",
+    "--generations",
+    "5",
+    "--pop-size",
+    "20",
+    "--quiet",
+];
+
+#[test]
+fn test_run() {
+    let mut args = vec!["run"];
+    args.extend(SMALL_RUN);
+    let out = stdout(&peptide_opt(&args));
+    assert!(out.starts_with("=== SUMMARY ==="));
+    assert!(out.contains("PKKP"));
+    // quiet drops the per-motif blocks
+    assert!(!out.contains("=== MOTIF"));
+}
+
+#[test]
+fn test_no_subcommand_runs() {
+    let with = stdout(&peptide_opt(&[&["run"][..], &SMALL_RUN].concat()));
+    let without = stdout(&peptide_opt(&SMALL_RUN));
+    assert_eq!(with, without);
+}
+
+#[test]
+fn test_list() {
+    let out = stdout(&peptide_opt(&["list"]));
+    assert!(out.starts_with("Available motifs:"));
+    assert!(out.contains("1: RGD"));
+}
+
+#[test]
+fn test_evaluate() {
+    let out = stdout(&peptide_opt(&["evaluate", "RGD", "--motif", "1"]));
+    assert!(out.starts_with("Motif 1 (RGD): fitness "));
+    assert_eq!(out.lines().count(), 1);
+
+    let bad = peptide_opt(&["evaluate", "RZD"]);
+    assert!(!bad.status.success());
+    let empty = peptide_opt(&["evaluate", ""]);
+    assert_eq!(
+        String::from_utf8_lossy(&empty.stderr),
+        "error: peptide sequence is empty\n"
+    );
+}
+
+#[test]
+fn test_score() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/data/candidates_example.fasta");
+    let out = stdout(&peptide_opt(&["score", path, "--motif", "1"]));
+    assert!(out.starts_with("=== MOTIF 1: RGD ==="));
+    assert!(out.contains("exact"));
+}
+
+#[test]
+fn test_bench() {
+    let out = stdout(&peptide_opt(&[
+        "bench",
+        "--evaluations",
+        "100",
+        "--motif",
+        "1",
+    ]));
+    let lines: Vec<&str> = out.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[1].starts_with("1   RGD"));
+}
+
+#[test]
+fn test_subcommand_rejects_unrelated_flags() {
+    let out = peptide_opt(&["list", "--pop-size", "400"]);
+    assert!(!out.status.success());
+}
+
+#[test]
+fn test_pssm_file() {
+    let path = concat!(env!("CARGO_MANIFEST_DIR"), "/data/pssm_rgd_example.txt");
+    let out = stdout(&peptide_opt(&["evaluate", "RGDS", "--pssm-file", path]));
+    // the consensus of the matrix is registered as the motif
+    assert!(out.contains("(RGDS): fitness "));
+
+    let bad = peptide_opt(&["evaluate", "RGDS", "--pssm-file", path, "--motif", "1"]);
+    assert!(!bad.status.success());
+}
+
+// Out-of-range settings are reported as errors rather than tripping a
+// library assert
+fn assert_rejected(args: &[&str]) {
+    let out = peptide_opt(args);
+    assert_eq!(out.status.code(), Some(1), "{:?} was not rejected", args);
+    assert!(String::from_utf8_lossy(&out.stderr).starts_with("error: "));
+}
+
+#[test]
+fn test_rejects_invalid_settings() {
+    assert_rejected(&["--variable-length", "--min-len", "0"]);
+    assert_rejected(&["--variable-length", "--min-len", "9", "--max-len", "3"]);
+    assert_rejected(&[
+        "--tabu",
+        "--reactive-tabu",
+        "--tabu-min-len",
+        "50",
+        "--tabu-max-len",
+        "10",
+    ]);
+    assert_rejected(&["--islands", "8", "--pop-size", "4"]);
+    assert_rejected(&["--invert-weight=-1"]);
+    assert_rejected(&["--relocate-weight=-1"]);
+    assert_rejected(&["--mutation-prob", "1.5"]);
+    assert_rejected(&["--mutation-prob=-0.1"]);
+    assert_rejected(&["--max-seconds=-1"]);
+    assert_rejected(&["--max-seconds", "nan"]);
+    assert_rejected(&["--motif", "0", "--blosum-weight", "NaN"]);
+    assert_rejected(&["--nepre-weight", "inf"]);
+    assert_rejected(&["--charge-weight=-inf"]);
+    assert_rejected(&["evaluate", "RGD", "--blosum-weight", "nan"]);
+    let out = peptide_opt(&["--cleavage-site", "ACDEFGHIKLMNPQRSTVWY"]);
+    assert!(!out.status.success());
+    assert_rejected(&["--sa", "--sa-alpha", "1.0"]);
+    assert_rejected(&["--sa", "--sa-t-end", "0"]);
+    assert_rejected(&["--sa", "--sa-t-start", "0.5", "--sa-t-end", "1"]);
+}
+
+#[test]
+fn test_seed_wraps_around() {
+    let out = stdout(&peptide_opt(&[
+        "--motif",
+        "1",
+        "--generations",
+        "2",
+        "--pop-size",
+        "10",
+        "--runs",
+        "2",
+        "--seed",
+        "18446744073709551615",
+        "--quiet",
+    ]));
+    assert!(out.starts_with("=== SUMMARY ==="));
+}